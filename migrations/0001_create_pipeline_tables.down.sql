@@ -0,0 +1,6 @@
+DROP TABLE IF EXISTS invalid_websites;
+DROP TABLE IF EXISTS websites_html;
+DROP TABLE IF EXISTS records_data;
+DROP TABLE IF EXISTS records_html;
+DROP TABLE IF EXISTS links_to_record_details;
+DROP TABLE IF EXISTS pages_with_all_records;
@@ -0,0 +1,66 @@
+-- Tables the Houzz pipeline has used from the start. IF NOT EXISTS keeps
+-- databases that were created by hand before migrations existed working.
+
+CREATE TABLE IF NOT EXISTS pages_with_all_records (
+    id INT NOT NULL AUTO_INCREMENT,
+    page VARCHAR(16) NULL,
+    district VARCHAR(255) NULL,
+    query VARCHAR(2048) NULL,
+    html LONGTEXT NULL,
+    processed INT NULL DEFAULT 0,
+    PRIMARY KEY (id),
+    KEY idx_pages_with_all_records_processed (processed)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
+CREATE TABLE IF NOT EXISTS links_to_record_details (
+    id INT NOT NULL AUTO_INCREMENT,
+    pages_with_all_records_id INT NOT NULL,
+    company VARCHAR(255) NOT NULL DEFAULT '',
+    link VARCHAR(768) NOT NULL,
+    visited INT NOT NULL DEFAULT 0,
+    PRIMARY KEY (id),
+    KEY idx_links_to_record_details_page (pages_with_all_records_id),
+    KEY idx_links_to_record_details_link (link),
+    KEY idx_links_to_record_details_visited (visited)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
+CREATE TABLE IF NOT EXISTS records_html (
+    id INT NOT NULL AUTO_INCREMENT,
+    link_to_record_details_id INT NOT NULL,
+    html LONGTEXT NOT NULL,
+    processed INT NOT NULL DEFAULT 0,
+    PRIMARY KEY (id),
+    KEY idx_records_html_link (link_to_record_details_id),
+    KEY idx_records_html_processed (processed)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
+CREATE TABLE IF NOT EXISTS records_data (
+    id INT NOT NULL AUTO_INCREMENT,
+    records_html_id INT NOT NULL,
+    email VARCHAR(1024) NOT NULL DEFAULT '',
+    phone VARCHAR(64) NOT NULL DEFAULT '',
+    website VARCHAR(768) NOT NULL DEFAULT '',
+    contact_us_link VARCHAR(2048) NULL,
+    PRIMARY KEY (id),
+    KEY idx_records_data_records_html (records_html_id),
+    KEY idx_records_data_phone (phone),
+    KEY idx_records_data_website (website)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
+CREATE TABLE IF NOT EXISTS websites_html (
+    id INT NOT NULL AUTO_INCREMENT,
+    records_data_id INT NOT NULL,
+    website VARCHAR(768) NOT NULL,
+    main_page_html LONGTEXT NOT NULL,
+    contact_page_html LONGTEXT NULL,
+    PRIMARY KEY (id),
+    KEY idx_websites_html_records_data (records_data_id),
+    KEY idx_websites_html_website (website)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
+CREATE TABLE IF NOT EXISTS invalid_websites (
+    id INT NOT NULL AUTO_INCREMENT,
+    website VARCHAR(768) NOT NULL,
+    PRIMARY KEY (id),
+    KEY idx_invalid_websites_website (website)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
@@ -0,0 +1,18 @@
+ALTER TABLE links_to_record_details
+    DROP COLUMN extractor_version;
+
+ALTER TABLE invalid_websites
+    DROP KEY idx_invalid_websites_canonical_domain,
+    DROP COLUMN canonical_domain;
+
+ALTER TABLE websites_html
+    DROP KEY idx_websites_html_canonical_domain,
+    DROP COLUMN canonical_domain,
+    DROP COLUMN final_url;
+
+ALTER TABLE records_data
+    DROP KEY idx_records_data_canonical_domain,
+    DROP COLUMN extractor_version,
+    DROP COLUMN company_verified,
+    DROP COLUMN company,
+    DROP COLUMN canonical_domain;
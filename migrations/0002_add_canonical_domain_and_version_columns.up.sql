@@ -0,0 +1,21 @@
+-- Columns added to the original tables for canonical website dedupe,
+-- company names and extractor version stamps.
+
+ALTER TABLE records_data
+    ADD COLUMN canonical_domain VARCHAR(255) NULL,
+    ADD COLUMN company VARCHAR(255) NOT NULL DEFAULT '',
+    ADD COLUMN company_verified INT NULL,
+    ADD COLUMN extractor_version VARCHAR(32) NOT NULL DEFAULT '',
+    ADD KEY idx_records_data_canonical_domain (canonical_domain);
+
+ALTER TABLE websites_html
+    ADD COLUMN final_url VARCHAR(2048) NULL,
+    ADD COLUMN canonical_domain VARCHAR(255) NULL,
+    ADD KEY idx_websites_html_canonical_domain (canonical_domain);
+
+ALTER TABLE invalid_websites
+    ADD COLUMN canonical_domain VARCHAR(255) NULL,
+    ADD KEY idx_invalid_websites_canonical_domain (canonical_domain);
+
+ALTER TABLE links_to_record_details
+    ADD COLUMN extractor_version VARCHAR(32) NOT NULL DEFAULT '';
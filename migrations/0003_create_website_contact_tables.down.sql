@@ -0,0 +1,4 @@
+DROP TABLE IF EXISTS record_social_profiles;
+DROP TABLE IF EXISTS record_emails;
+DROP TABLE IF EXISTS record_phones;
+DROP TABLE IF EXISTS website_pages;
@@ -0,0 +1,46 @@
+-- Crawled website pages and the contact details extracted from them.
+
+CREATE TABLE IF NOT EXISTS website_pages (
+    id INT NOT NULL AUTO_INCREMENT,
+    websites_html_id INT NOT NULL,
+    url VARCHAR(2048) NOT NULL,
+    depth INT NOT NULL DEFAULT 0,
+    score INT NOT NULL DEFAULT 0,
+    html LONGTEXT NOT NULL,
+    PRIMARY KEY (id),
+    KEY idx_website_pages_websites_html (websites_html_id)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
+CREATE TABLE IF NOT EXISTS record_phones (
+    id INT NOT NULL AUTO_INCREMENT,
+    records_data_id INT NOT NULL,
+    phone VARCHAR(64) NOT NULL,
+    source_page VARCHAR(2048) NOT NULL DEFAULT '',
+    source_type VARCHAR(32) NOT NULL DEFAULT '',
+    extractor_version VARCHAR(32) NOT NULL DEFAULT '',
+    PRIMARY KEY (id),
+    UNIQUE KEY uq_record_phones_record_phone (records_data_id, phone)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
+CREATE TABLE IF NOT EXISTS record_emails (
+    id INT NOT NULL AUTO_INCREMENT,
+    record_id INT NOT NULL,
+    email VARCHAR(320) NOT NULL,
+    source_page VARCHAR(2048) NOT NULL DEFAULT '',
+    source_type VARCHAR(32) NOT NULL DEFAULT '',
+    first_seen DATETIME NULL,
+    confidence FLOAT NOT NULL DEFAULT 0,
+    PRIMARY KEY (id),
+    UNIQUE KEY uq_record_emails_record_email (record_id, email)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
+CREATE TABLE IF NOT EXISTS record_social_profiles (
+    id INT NOT NULL AUTO_INCREMENT,
+    record_id INT NOT NULL,
+    network VARCHAR(32) NOT NULL,
+    url VARCHAR(512) NOT NULL,
+    source_page VARCHAR(2048) NOT NULL DEFAULT '',
+    PRIMARY KEY (id),
+    UNIQUE KEY uq_record_social_profiles_record_url (record_id, url),
+    KEY idx_record_social_profiles_network (network)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
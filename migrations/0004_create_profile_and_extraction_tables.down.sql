@@ -0,0 +1,3 @@
+DROP TABLE IF EXISTS extraction_fill_rates;
+DROP TABLE IF EXISTS field_provenance;
+DROP TABLE IF EXISTS records_profile;
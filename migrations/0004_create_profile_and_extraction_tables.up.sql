@@ -0,0 +1,49 @@
+-- Houzz profile details, extraction provenance and per-batch fill rates.
+
+CREATE TABLE IF NOT EXISTS records_profile (
+    id INT NOT NULL AUTO_INCREMENT,
+    records_html_id INT NOT NULL,
+    rating FLOAT NULL,
+    review_count INT NULL,
+    badges VARCHAR(1024) NOT NULL DEFAULT '',
+    license_number VARCHAR(512) NOT NULL DEFAULT '',
+    years_in_business INT NULL,
+    service_areas TEXT NOT NULL,
+    extractor_version VARCHAR(32) NOT NULL DEFAULT '',
+    PRIMARY KEY (id),
+    UNIQUE KEY uq_records_profile_records_html (records_html_id)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
+CREATE TABLE IF NOT EXISTS field_provenance (
+    id INT NOT NULL AUTO_INCREMENT,
+    target_table VARCHAR(64) NOT NULL,
+    target_id INT NOT NULL,
+    field VARCHAR(64) NOT NULL,
+    value TEXT NOT NULL,
+    source_table VARCHAR(64) NOT NULL,
+    source_id INT NOT NULL,
+    page_url VARCHAR(2048) NOT NULL DEFAULT '',
+    rule_id VARCHAR(128) NOT NULL,
+    extractor_version VARCHAR(32) NOT NULL,
+    confidence FLOAT NOT NULL DEFAULT 0,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (id),
+    KEY idx_field_provenance_target (target_table, target_id),
+    KEY idx_field_provenance_rule (rule_id)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
+CREATE TABLE IF NOT EXISTS extraction_fill_rates (
+    id INT NOT NULL AUTO_INCREMENT,
+    batch_id VARCHAR(64) NOT NULL,
+    stage VARCHAR(64) NOT NULL,
+    field VARCHAR(64) NOT NULL,
+    total INT NOT NULL,
+    filled INT NOT NULL,
+    fill_rate FLOAT NOT NULL,
+    failing_ids VARCHAR(1024) NOT NULL DEFAULT '',
+    extractor_version VARCHAR(32) NOT NULL DEFAULT '',
+    halted INT NOT NULL DEFAULT 0,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (id),
+    KEY idx_extraction_fill_rates_stage_field (stage, field)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
@@ -0,0 +1,18 @@
+-- Merged duplicates are not restored.
+
+ALTER TABLE records_data
+    DROP KEY uq_records_data_canonical_domain,
+    DROP KEY uq_records_data_canonical_phone,
+    DROP COLUMN canonical_phone,
+    DROP COLUMN seen_count;
+
+ALTER TABLE record_social_profiles
+    DROP COLUMN seen_count;
+
+ALTER TABLE records_html
+    DROP KEY uq_records_html_link,
+    DROP COLUMN seen_count;
+
+ALTER TABLE links_to_record_details
+    DROP KEY uq_links_to_record_details_link,
+    DROP COLUMN seen_count;
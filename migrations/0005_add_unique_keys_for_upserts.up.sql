@@ -0,0 +1,78 @@
+-- Unique keys so concurrent stages upsert instead of checking and then
+-- inserting. Duplicate links and record pages that slipped in before are
+-- merged into the oldest row first; a pro listed under several districts keeps
+-- its first listing page.
+-- seen_count changes on every duplicate write, so ON DUPLICATE KEY UPDATE
+-- always reports 2 affected rows for an existing row and 1 for a new one.
+
+-- canonical_domain is derived from the website, so records sharing one are not
+-- merged: the oldest keeps the key and the others are left without one.
+-- `canonical-domains` recomputes the keys from the websites afterwards.
+UPDATE records_data
+JOIN (SELECT canonical_domain, MIN(id) AS id FROM records_data WHERE canonical_domain IS NOT NULL GROUP BY canonical_domain) first_record ON first_record.canonical_domain = records_data.canonical_domain
+SET records_data.canonical_domain = NULL
+WHERE records_data.id > first_record.id;
+
+ALTER TABLE records_data
+    ADD UNIQUE KEY uq_records_data_canonical_domain (canonical_domain);
+
+-- Records without a website are deduped on the phone's digits, the same way
+-- extractor::canonical_phone computes them.
+ALTER TABLE records_data
+    ADD COLUMN canonical_phone VARCHAR(15) NULL;
+
+UPDATE records_data
+SET canonical_phone = CASE
+    WHEN REGEXP_REPLACE(phone, '[^0-9]', '') REGEXP '^1[0-9]{10}$' THEN SUBSTRING(REGEXP_REPLACE(phone, '[^0-9]', ''), 2)
+    WHEN REGEXP_REPLACE(phone, '[^0-9]', '') REGEXP '^[0-9]{1,15}$' THEN REGEXP_REPLACE(phone, '[^0-9]', '')
+END;
+
+UPDATE records_data
+JOIN (SELECT canonical_phone, MIN(id) AS id FROM records_data WHERE canonical_phone IS NOT NULL GROUP BY canonical_phone) first_record ON first_record.canonical_phone = records_data.canonical_phone
+SET records_data.canonical_phone = NULL
+WHERE records_data.id > first_record.id;
+
+ALTER TABLE records_data
+    ADD UNIQUE KEY uq_records_data_canonical_phone (canonical_phone);
+
+ALTER TABLE links_to_record_details
+    ADD COLUMN seen_count INT NOT NULL DEFAULT 1;
+
+ALTER TABLE records_html
+    ADD COLUMN seen_count INT NOT NULL DEFAULT 1;
+
+ALTER TABLE records_data
+    ADD COLUMN seen_count INT NOT NULL DEFAULT 1;
+
+ALTER TABLE record_social_profiles
+    ADD COLUMN seen_count INT NOT NULL DEFAULT 1;
+
+UPDATE records_html
+JOIN links_to_record_details duplicate ON duplicate.id = records_html.link_to_record_details_id
+JOIN (SELECT link, MIN(id) AS id FROM links_to_record_details GROUP BY link) first_link ON first_link.link = duplicate.link
+SET records_html.link_to_record_details_id = first_link.id
+WHERE duplicate.id > first_link.id;
+
+DELETE duplicate FROM links_to_record_details duplicate
+JOIN links_to_record_details first_link ON first_link.link = duplicate.link AND first_link.id < duplicate.id;
+
+-- Profiles are re-extracted from the kept page, so the duplicates' rows go.
+DELETE records_profile FROM records_profile
+JOIN records_html duplicate ON duplicate.id = records_profile.records_html_id
+JOIN records_html first_html ON first_html.link_to_record_details_id = duplicate.link_to_record_details_id AND first_html.id < duplicate.id;
+
+UPDATE records_data
+JOIN records_html duplicate ON duplicate.id = records_data.records_html_id
+JOIN (SELECT link_to_record_details_id, MIN(id) AS id FROM records_html GROUP BY link_to_record_details_id) first_html ON first_html.link_to_record_details_id = duplicate.link_to_record_details_id
+SET records_data.records_html_id = first_html.id
+WHERE duplicate.id > first_html.id;
+
+DELETE duplicate FROM records_html duplicate
+JOIN records_html first_html ON first_html.link_to_record_details_id = duplicate.link_to_record_details_id AND first_html.id < duplicate.id;
+
+ALTER TABLE links_to_record_details
+    ADD UNIQUE KEY uq_links_to_record_details_link (link);
+
+ALTER TABLE records_html
+    ADD UNIQUE KEY uq_records_html_link (link_to_record_details_id);
+
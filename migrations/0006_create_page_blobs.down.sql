@@ -0,0 +1,14 @@
+-- SQL cannot decompress the blobs: run `blobs expand` first to copy HTML back
+-- into the row columns, or compacted rows lose their HTML.
+
+ALTER TABLE websites_html
+    DROP COLUMN contact_page_html_hash,
+    DROP COLUMN main_page_html_hash;
+
+ALTER TABLE records_html
+    DROP COLUMN html_hash;
+
+ALTER TABLE pages_with_all_records
+    DROP COLUMN html_hash;
+
+DROP TABLE page_blobs;
@@ -0,0 +1,24 @@
+-- Page HTML is stored once per distinct content in page_blobs, compressed and
+-- keyed by its SHA-256. The HTML columns stay for rows written before this and
+-- are emptied by `blobs compact`; rows with a hash read their HTML from the blob.
+-- seen_count works as in 0005: an upsert of a known blob reports 2 affected rows.
+
+CREATE TABLE page_blobs (
+    hash CHAR(64) NOT NULL PRIMARY KEY,
+    codec VARCHAR(16) NOT NULL,
+    data LONGBLOB NOT NULL,
+    original_size BIGINT NOT NULL,
+    compressed_size BIGINT NOT NULL,
+    seen_count INT NOT NULL DEFAULT 1,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+ALTER TABLE pages_with_all_records
+    ADD COLUMN html_hash CHAR(64) NULL;
+
+ALTER TABLE records_html
+    ADD COLUMN html_hash CHAR(64) NULL;
+
+ALTER TABLE websites_html
+    ADD COLUMN main_page_html_hash CHAR(64) NULL,
+    ADD COLUMN contact_page_html_hash CHAR(64) NULL;
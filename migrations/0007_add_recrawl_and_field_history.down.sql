@@ -0,0 +1,12 @@
+DROP TABLE recrawl_runs;
+
+DROP TABLE record_field_history;
+
+ALTER TABLE websites_html
+    DROP COLUMN fetched_at;
+
+ALTER TABLE records_html
+    DROP COLUMN fetched_at;
+
+ALTER TABLE links_to_record_details
+    DROP COLUMN visited_at;
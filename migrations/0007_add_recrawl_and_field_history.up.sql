@@ -0,0 +1,42 @@
+-- Fetch times for re-crawl, and the versioned history of each business's fields.
+-- The timestamp columns are added NULL first so existing rows stay NULL (age
+-- unknown, so due for re-crawl) and only new rows get the default.
+
+ALTER TABLE links_to_record_details
+    ADD COLUMN visited_at DATETIME NULL;
+
+ALTER TABLE records_html
+    ADD COLUMN fetched_at DATETIME NULL;
+
+ALTER TABLE records_html
+    MODIFY COLUMN fetched_at DATETIME NULL DEFAULT CURRENT_TIMESTAMP;
+
+ALTER TABLE websites_html
+    ADD COLUMN fetched_at DATETIME NULL;
+
+ALTER TABLE websites_html
+    MODIFY COLUMN fetched_at DATETIME NULL DEFAULT CURRENT_TIMESTAMP;
+
+-- One row per value a field took; a row is only written when the value differs
+-- from the latest one for the record and field.
+CREATE TABLE IF NOT EXISTS record_field_history (
+    id INT NOT NULL AUTO_INCREMENT,
+    record_id INT NOT NULL,
+    field VARCHAR(64) NOT NULL,
+    value TEXT NOT NULL,
+    source_table VARCHAR(64) NOT NULL,
+    source_id INT NOT NULL,
+    observed_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (id),
+    KEY idx_record_field_history_record_field (record_id, field),
+    KEY idx_record_field_history_observed_at (observed_at)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
+CREATE TABLE IF NOT EXISTS recrawl_runs (
+    id INT NOT NULL AUTO_INCREMENT,
+    details_max_age_days INT NOT NULL,
+    websites_max_age_days INT NOT NULL,
+    links_requeued INT NOT NULL DEFAULT 0,
+    started_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (id)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
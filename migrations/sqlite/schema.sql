@@ -0,0 +1,135 @@
+-- SQLite schema for SqliteStorage: the tables behind the Storage trait, in
+-- their current shape. Kept in step with the MySQL migrations by hand.
+
+CREATE TABLE IF NOT EXISTS pages_with_all_records (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    page TEXT NULL,
+    district TEXT NULL,
+    query TEXT NULL,
+    html TEXT NULL,
+    processed INTEGER NULL DEFAULT 0
+);
+
+CREATE TABLE IF NOT EXISTS links_to_record_details (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    pages_with_all_records_id INTEGER NOT NULL,
+    company TEXT NOT NULL DEFAULT '',
+    link TEXT NOT NULL UNIQUE,
+    visited INTEGER NOT NULL DEFAULT 0,
+    extractor_version TEXT NOT NULL DEFAULT '',
+    seen_count INTEGER NOT NULL DEFAULT 1,
+    visited_at TEXT NULL
+);
+
+CREATE TABLE IF NOT EXISTS records_html (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    link_to_record_details_id INTEGER NOT NULL UNIQUE,
+    html TEXT NOT NULL,
+    processed INTEGER NOT NULL DEFAULT 0,
+    seen_count INTEGER NOT NULL DEFAULT 1,
+    fetched_at TEXT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS records_data (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    records_html_id INTEGER NOT NULL,
+    email TEXT NOT NULL DEFAULT '',
+    phone TEXT NOT NULL DEFAULT '',
+    website TEXT NOT NULL DEFAULT '',
+    contact_us_link TEXT NULL,
+    canonical_domain TEXT NULL UNIQUE,
+    canonical_phone TEXT NULL UNIQUE,
+    company TEXT NOT NULL DEFAULT '',
+    company_verified INTEGER NULL,
+    extractor_version TEXT NOT NULL DEFAULT '',
+    seen_count INTEGER NOT NULL DEFAULT 1
+);
+
+CREATE INDEX IF NOT EXISTS idx_records_data_phone ON records_data (phone);
+
+CREATE TABLE IF NOT EXISTS websites_html (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    records_data_id INTEGER NOT NULL,
+    website TEXT NOT NULL,
+    main_page_html TEXT NOT NULL,
+    contact_page_html TEXT NULL,
+    final_url TEXT NULL,
+    canonical_domain TEXT NULL,
+    fetched_at TEXT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS website_pages (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    websites_html_id INTEGER NOT NULL,
+    url TEXT NOT NULL,
+    depth INTEGER NOT NULL DEFAULT 0,
+    score INTEGER NOT NULL DEFAULT 0,
+    html TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS record_emails (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    record_id INTEGER NOT NULL,
+    email TEXT NOT NULL,
+    source_page TEXT NOT NULL DEFAULT '',
+    source_type TEXT NOT NULL DEFAULT '',
+    first_seen TEXT NULL,
+    confidence REAL NOT NULL DEFAULT 0,
+    UNIQUE (record_id, email)
+);
+
+CREATE TABLE IF NOT EXISTS field_provenance (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    target_table TEXT NOT NULL,
+    target_id INTEGER NOT NULL,
+    field TEXT NOT NULL,
+    value TEXT NOT NULL,
+    source_table TEXT NOT NULL,
+    source_id INTEGER NOT NULL,
+    page_url TEXT NOT NULL DEFAULT '',
+    rule_id TEXT NOT NULL,
+    extractor_version TEXT NOT NULL,
+    confidence REAL NOT NULL DEFAULT 0,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS record_field_history (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    record_id INTEGER NOT NULL,
+    field TEXT NOT NULL,
+    value TEXT NOT NULL,
+    source_table TEXT NOT NULL,
+    source_id INTEGER NOT NULL,
+    observed_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_record_field_history_record_field ON record_field_history (record_id, field);
+
+CREATE TABLE IF NOT EXISTS extraction_fill_rates (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    batch_id TEXT NOT NULL,
+    stage TEXT NOT NULL,
+    field TEXT NOT NULL,
+    total INTEGER NOT NULL,
+    filled INTEGER NOT NULL,
+    fill_rate REAL NOT NULL,
+    failing_ids TEXT NOT NULL DEFAULT '',
+    extractor_version TEXT NOT NULL DEFAULT '',
+    halted INTEGER NOT NULL DEFAULT 0,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Same shape as the MySQL view; SQLite's group_concat takes no ORDER BY, so
+-- the emails are ordered by a subquery instead.
+CREATE VIEW IF NOT EXISTS view_records_data_emails AS
+SELECT
+    records_data.id,
+    records_data.records_html_id,
+    COALESCE((
+        SELECT group_concat(email, ', ')
+        FROM (SELECT record_emails.email FROM record_emails WHERE record_emails.record_id = records_data.id ORDER BY record_emails.confidence DESC, record_emails.id)
+    ), '') AS email,
+    records_data.phone,
+    records_data.website,
+    records_data.contact_us_link
+FROM records_data;
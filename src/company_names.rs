@@ -0,0 +1,184 @@
+use scraper::{Html, Selector};
+use std::collections::HashSet;
+use crate::text;
+
+// Legal suffixes and filler words that differ between a directory listing and the company's own site.
+const IGNORED_WORDS: [&str; 16] = [
+    "inc", "ltd", "llc", "corp", "corporation", "co", "company", "limited", "the", "and", "of", "home", "welcome",
+    "official", "site", "website",
+];
+
+const TITLE_SEPARATORS: [char; 6] = ['|', '-', '–', '—', ':', '•'];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SiteName {
+    pub name: String,
+    pub source_type: String,
+}
+
+// "Smith &amp; Sons  Renovations " -> "Smith & Sons Renovations"
+pub fn clean_company_name(raw: &str) -> String {
+    text::normalize_text(raw)
+}
+
+// Names a website gives itself: JSON-LD organisation name, og:site_name and <title>, in that order.
+pub fn find_site_names(html: &str) -> Vec<SiteName> {
+    let document = Html::parse_document(html);
+    let mut site_names = Vec::new();
+
+    let script_selector = Selector::parse("script[type='application/ld+json']").unwrap();
+    for script in document.select(&script_selector) {
+        let json = script.text().collect::<String>();
+        if let Ok(value) = serde_json::from_str::<serde_json::Value>(&json) {
+            let mut names = Vec::new();
+            collect_json_ld_names(&value, &mut names);
+            for name in names {
+                push_site_name(&mut site_names, &name, "json_ld");
+            }
+        }
+    }
+
+    let og_selector = Selector::parse("meta[property='og:site_name']").unwrap();
+    for meta in document.select(&og_selector) {
+        push_site_name(&mut site_names, meta.value().attr("content").unwrap_or_default(), "og_site_name");
+    }
+
+    let title_selector = Selector::parse("title").unwrap();
+    if let Some(title) = document.select(&title_selector).next() {
+        push_site_name(&mut site_names, &title.text().collect::<String>(), "title");
+    }
+
+    site_names
+}
+
+// True when the site names itself like the directory does. Titles are compared
+// part by part, so "Home | McFee Landscaping" still matches "McFee Landscaping Inc.".
+pub fn company_name_matches(company: &str, site_name: &str) -> bool {
+    let company_words = significant_words(company);
+
+    if company_words.is_empty() {
+        return false;
+    }
+
+    site_name
+        .split(|c| TITLE_SEPARATORS.contains(&c))
+        .chain(std::iter::once(site_name))
+        .any(|part| {
+            let part_words = significant_words(part);
+            if part_words.is_empty() {
+                return false;
+            }
+
+            let shared = company_words.intersection(&part_words).count();
+            let smaller = company_words.len().min(part_words.len());
+
+            shared * 10 >= smaller * 6 || squash(part).contains(&squash(company)) || squash(company).contains(&squash(part))
+        })
+}
+
+fn push_site_name(site_names: &mut Vec<SiteName>, name: &str, source_type: &str) {
+    let name = clean_company_name(name);
+
+    if name.is_empty() || site_names.iter().any(|site_name| site_name.name == name && site_name.source_type == source_type) {
+        return;
+    }
+
+    site_names.push(SiteName {
+        name,
+        source_type: source_type.to_string(),
+    });
+}
+
+// Only organisation-like nodes; a WebPage or BreadcrumbList "name" is not the company.
+fn collect_json_ld_names(value: &serde_json::Value, names: &mut Vec<String>) {
+    match value {
+        serde_json::Value::Object(map) => {
+            let is_organization = match map.get("@type") {
+                Some(serde_json::Value::String(kind)) => is_organization_type(kind),
+                Some(serde_json::Value::Array(kinds)) => kinds.iter().filter_map(|kind| kind.as_str()).any(is_organization_type),
+                _ => false,
+            };
+
+            if is_organization {
+                if let Some(name) = map.get("name").and_then(|name| name.as_str()) {
+                    names.push(name.to_string());
+                }
+            }
+
+            for value in map.values() {
+                collect_json_ld_names(value, names);
+            }
+        }
+        serde_json::Value::Array(values) => {
+            for value in values {
+                collect_json_ld_names(value, names);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn is_organization_type(kind: &str) -> bool {
+    kind == "Organization" || kind.ends_with("Business") || kind.ends_with("Contractor") || kind == "Corporation"
+}
+
+fn significant_words(value: &str) -> HashSet<String> {
+    value
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty() && !IGNORED_WORDS.contains(word))
+        .map(|word| word.to_string())
+        .collect()
+}
+
+fn squash(value: &str) -> String {
+    value.to_lowercase().chars().filter(|c| c.is_alphanumeric()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static HTML_HOMEPAGE: &str = r#"
+    <html>
+    <head>
+        <title>Home | McFee Construction &amp; Landscaping</title>
+        <meta property="og:site_name" content="McFee Construction">
+        <script type="application/ld+json">
+        {"@context":"https://schema.org","@graph":[
+            {"@type":"WebPage","name":"Home"},
+            {"@type":"HomeAndConstructionBusiness","name":"McFee Construction Ltd."}
+        ]}
+        </script>
+    </head>
+    <body></body>
+    </html>
+"#;
+
+    #[test]
+    fn should_clean_company_names() {
+        assert_eq!(clean_company_name("  Smith &amp; Sons\n Renovations "), "Smith & Sons Renovations");
+        assert_eq!(clean_company_name("McFEE: The Team Delivering &quot;Outdoor Excellence&quot;"), "McFEE: The Team Delivering \"Outdoor Excellence\"");
+    }
+
+    #[test]
+    fn should_find_site_names() {
+        let site_names = find_site_names(HTML_HOMEPAGE);
+        let site_names: Vec<(&str, &str)> = site_names.iter().map(|s| (s.source_type.as_str(), s.name.as_str())).collect();
+
+        assert_eq!(site_names, vec![
+            ("json_ld", "McFee Construction Ltd."),
+            ("og_site_name", "McFee Construction"),
+            ("title", "Home | McFee Construction & Landscaping"),
+        ]);
+    }
+
+    #[test]
+    fn should_match_company_names() {
+        assert!(company_name_matches("McFee Construction Inc.", "McFee Construction Ltd."));
+        assert!(company_name_matches("McFee Construction", "Home | McFee Construction & Landscaping"));
+        assert!(company_name_matches("Let's Landscape Together", "LetsLandscapeTogether"));
+        assert!(!company_name_matches("McFee Construction", "Smith & Sons Renovations"));
+        assert!(!company_name_matches("", "McFee Construction"));
+    }
+}
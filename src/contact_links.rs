@@ -0,0 +1,197 @@
+use scraper::{ElementRef, Html, Selector};
+use std::cmp::Reverse;
+
+// Contact page keywords per language. French matters for Ontario sites.
+const CONTACT_KEYWORDS_EN: [&str; 8] = [
+    "contact", "get in touch", "reach us", "reach out", "talk to us", "write to us", "email us", "call us",
+];
+const CONTACT_KEYWORDS_FR: [&str; 7] = [
+    "nous-joindre", "nous joindre", "joindre", "contactez", "écrivez-nous", "ecrivez-nous", "coordonnées",
+];
+const CONTACT_KEYWORDS_ES: [&str; 4] = ["contacto", "contactenos", "contáctenos", "contactanos"];
+const CONTACT_KEYWORDS_DE: [&str; 2] = ["kontakt", "impressum"];
+const CONTACT_KEYWORDS_IT: [&str; 2] = ["contatti", "contattaci"];
+const CONTACT_KEYWORDS_PT: [&str; 2] = ["contato", "fale conosco"];
+
+// Links that can never be the company's own contact page.
+const SKIPPED_SCHEMES: [&str; 4] = ["mailto:", "tel:", "javascript:", "sms:"];
+const SOCIAL_HOSTS: [&str; 11] = [
+    "facebook.com", "instagram.com", "twitter.com", "x.com", "linkedin.com", "youtube.com", "pinterest.com",
+    "houzz.com", "yelp.com", "google.com", "tiktok.com",
+];
+
+const HREF_SCORE: i32 = 40;
+const TEXT_SCORE: i32 = 50;
+const TITLE_SCORE: i32 = 20;
+const NAV_SCORE: i32 = 10;
+const FOOTER_SCORE: i32 = 5;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ContactLinkCandidate {
+    pub href: String,
+    pub text: String,
+    pub score: i32,
+}
+
+pub fn contact_keywords() -> impl Iterator<Item = &'static str> {
+    CONTACT_KEYWORDS_EN
+        .iter()
+        .chain(CONTACT_KEYWORDS_FR.iter())
+        .chain(CONTACT_KEYWORDS_ES.iter())
+        .chain(CONTACT_KEYWORDS_DE.iter())
+        .chain(CONTACT_KEYWORDS_IT.iter())
+        .chain(CONTACT_KEYWORDS_PT.iter())
+        .copied()
+}
+
+pub fn matches_contact_keyword(value: &str) -> bool {
+    let value = value.to_lowercase();
+    contact_keywords().any(|keyword| value.contains(keyword))
+}
+
+// Every link that looks like a contact page, best first. Equal scores keep document order.
+pub fn rank_contact_links(html: &str) -> Vec<ContactLinkCandidate> {
+    let document = Html::parse_document(html);
+    let link_selector = Selector::parse("a[href]").unwrap();
+    let mut candidates: Vec<ContactLinkCandidate> = Vec::new();
+
+    for element in document.select(&link_selector) {
+        let href = element.value().attr("href").unwrap_or_default().trim();
+        let lowercase_href = href.to_lowercase();
+
+        if href.is_empty() || href.starts_with('#') {
+            continue;
+        }
+
+        if SKIPPED_SCHEMES.iter().any(|scheme| lowercase_href.starts_with(scheme)) {
+            continue;
+        }
+
+        if is_social_link(&lowercase_href) {
+            continue;
+        }
+
+        let text = element.text().collect::<Vec<_>>().join(" ");
+        let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        let title = element.value().attr("title").unwrap_or_default();
+
+        let mut score = 0;
+
+        if matches_contact_keyword(&lowercase_href) {
+            score += HREF_SCORE;
+        }
+
+        if matches_contact_keyword(&text) {
+            score += TEXT_SCORE;
+        }
+
+        if matches_contact_keyword(title) {
+            score += TITLE_SCORE;
+        }
+
+        if score == 0 {
+            continue;
+        }
+
+        score += position_score(element);
+
+        let href = href.split('#').next().unwrap_or_default().to_string();
+
+        match candidates.iter_mut().find(|candidate| candidate.href == href) {
+            Some(candidate) => candidate.score = candidate.score.max(score),
+            None => candidates.push(ContactLinkCandidate { href, text, score }),
+        }
+    }
+
+    candidates.sort_by_key(|candidate| Reverse(candidate.score));
+    candidates
+}
+
+fn is_social_link(href: &str) -> bool {
+    let without_scheme = href.trim_start_matches("https://").trim_start_matches("http://").trim_start_matches("//");
+    let host = without_scheme.split('/').next().unwrap_or_default();
+
+    SOCIAL_HOSTS
+        .iter()
+        .any(|social| host == *social || host.ends_with(&format!(".{}", social)))
+}
+
+// Contact links in the main navigation are the most reliable, footer links come next.
+fn position_score(element: ElementRef) -> i32 {
+    let mut in_footer = false;
+
+    for ancestor in element.ancestors().filter_map(ElementRef::wrap) {
+        let name = ancestor.value().name();
+        let class_and_id = format!(
+            "{} {}",
+            ancestor.value().attr("class").unwrap_or_default(),
+            ancestor.value().id().unwrap_or_default()
+        )
+        .to_lowercase();
+
+        if name == "nav" || name == "header" || class_and_id.contains("menu") || class_and_id.contains("nav") {
+            return NAV_SCORE;
+        }
+
+        if name == "footer" || class_and_id.contains("footer") {
+            in_footer = true;
+        }
+    }
+
+    if in_footer {
+        FOOTER_SCORE
+    } else {
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static HTML_MULTILINGUAL: &str = r#"
+    <header>
+        <nav class="main-menu">
+            <a href="/accueil">Accueil</a>
+            <a href="/nous-joindre" title="Contactez-nous">Nous joindre</a>
+        </nav>
+    </header>
+    <main>
+        <a href="mailto:info@renovationsgagnon.ca">Contact by email</a>
+        <a href="https://www.facebook.com/renovationsgagnon/contact">Contact us on Facebook</a>
+        <a href="/page-12">Get in touch</a>
+        <a href="/de/kontakt">Kontakt</a>
+    </main>
+    <footer>
+        <a href="/contacto">Contacto</a>
+        <a href="/nous-joindre#form">Nous joindre</a>
+    </footer>
+"#;
+
+    static HTML_NO_CONTACT: &str = r#"
+    <nav><a href="/">Home</a><a href="/services">Services</a><a href="tel:+19057131230">Call</a></nav>
+"#;
+
+    #[test]
+    fn should_rank_contact_links() {
+        let candidates = rank_contact_links(HTML_MULTILINGUAL);
+        let hrefs: Vec<&str> = candidates.iter().map(|candidate| candidate.href.as_str()).collect();
+
+        assert_eq!(hrefs, vec!["/nous-joindre", "/contacto", "/de/kontakt", "/page-12"]);
+        assert_eq!(candidates[0].score, HREF_SCORE + TEXT_SCORE + TITLE_SCORE + NAV_SCORE);
+        assert_eq!(candidates[3].score, TEXT_SCORE);
+    }
+
+    #[test]
+    fn should_skip_mailto_and_social_links() {
+        let candidates = rank_contact_links(HTML_MULTILINGUAL);
+
+        assert!(!candidates.iter().any(|candidate| candidate.href.starts_with("mailto:")));
+        assert!(!candidates.iter().any(|candidate| candidate.href.contains("facebook.com")));
+    }
+
+    #[test]
+    fn should_return_no_candidates_without_contact_links() {
+        assert!(rank_contact_links(HTML_NO_CONTACT).is_empty());
+    }
+}
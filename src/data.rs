@@ -227,4 +227,12 @@ pub fn test_generate_houzz_html() -> String{
     "###;
 
     html_houzz.to_owned()
-}
\ No newline at end of file
+}
+pub fn test_generate_houzz_record_profile_html() -> String{
+    let html = r###"
+<div class="hz-pro-header" data-container="Pro Header"><h1 class="sc-mwxddt-0 kTxZpm">McFEE: The Team Delivering "Outdoor Excellence"</h1><div class="sc-183mtny-0 hz-star-rate"><span class="hz-star-rate__rating-number">4.9</span><span class="hz-star-rate__review-string">37 Reviews</span></div><div class="sc-183mtny-0 pro-badges"><span class="pro-badge__label">Best of Houzz 2023 - Service</span><img src="https://st.hzcdn.com/static/badge_43_9.png" alt="Best of Houzz 2022 Design" width="48" height="48"><span class="pro-badge__label">Best of Houzz 2021</span><span class="pro-badge__label">Best of Houzz 2023 - Service</span></div></div>
+<script type="application/ld+json">{"@context":"http://schema.org","@type":"HomeAndConstructionBusiness","name":"McFEE: The Team Delivering \"Outdoor Excellence\"","telephone":"(905) 713-1230","aggregateRating":{"@type":"AggregateRating","ratingValue":"4.9","reviewCount":37}}</script>
+<section id="business" tabindex="-1" aria-labelledby="business-label" class="AnchorSection__StyledSection-sc-1be6hcb-0 kTTnQL sc-183mtny-0 kCIqph"><h2 font-weight="bold" id="business-label" data-container="Business Details" font-size="medium,mediumPlus" class="sc-1hirnpv-0 feeuEH sc-mwxddt-0 fGegvn"><span>Business Details</span></h2><div data-container="Business Details" class="sc-183mtny-0 sc-1wm9uar-0 bmFdUT eFNJDl hui-grid"><div class="sc-183mtny-0 sc-1uw6j8i-0 BusinessDetails__StyledCell-sc-1iscszt-0 dYJOPh ecpWHO gRCcss hui-cell"><h3 font-weight="bold" font-size="16px" class="sc-1hirnpv-0 feeuEH sc-mwxddt-0 bnYGKL">Business Name</h3><p font-size="xSmall,xSmall" class="sc-mwxddt-0 cZJFpr">McFEE: The Team Delivering "Outdoor Excellence"</p></div><div class="sc-183mtny-0 sc-1uw6j8i-0 BusinessDetails__StyledCell-sc-1iscszt-0 dYJOPh ecpWHO gRCcss hui-cell"><h3 font-weight="bold" font-size="16px" class="sc-1hirnpv-0 feeuEH sc-mwxddt-0 bnYGKL">Phone Number</h3><p font-size="xSmall,xSmall" class="sc-mwxddt-0 cZJFpr">(905) 713-1230</p></div><div class="sc-183mtny-0 sc-1uw6j8i-0 BusinessDetails__StyledCell-sc-1iscszt-0 dYJOPh ecpWHO gRCcss hui-cell"><h3 font-weight="bold" font-size="16px" class="sc-1hirnpv-0 feeuEH sc-mwxddt-0 bnYGKL">License Number</h3><p font-size="xSmall,xSmall" class="sc-mwxddt-0 cZJFpr"><span>ON-GC-482913</span><br><span>TSSA 000118273</span></p></div><div class="sc-183mtny-0 sc-1uw6j8i-0 BusinessDetails__StyledCell-sc-1iscszt-0 iwqtdn ecpWHO gRCcss hui-cell"><h3 font-weight="bold" font-size="16px" class="sc-1hirnpv-0 feeuEH sc-mwxddt-0 bnYGKL">Years in Business</h3><p font-size="xSmall,xSmall" class="sc-mwxddt-0 cZJFpr">28 years</p></div><div class="sc-183mtny-0 sc-1uw6j8i-0 BusinessDetails__StyledCell-sc-1iscszt-0 iwqtdn ecpWHO gRCcss hui-cell"><h3 font-weight="bold" font-size="16px" class="sc-1hirnpv-0 feeuEH sc-mwxddt-0 bnYGKL">Service Areas</h3><p font-size="xSmall,xSmall" class="sc-mwxddt-0 cZJFpr"><span>Gormley</span>, <span>Richmond Hill</span>, <span>Markham</span>, <span>Vaughan</span></p></div></div></section>
+"###;
+    html.to_string()
+}
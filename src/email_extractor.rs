@@ -0,0 +1,284 @@
+use scraper::{Html, Selector};
+use regex::Regex;
+use std::collections::HashSet;
+use crate::text::decode_html_entities;
+
+pub struct EmailExtractor {
+    pub html: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FoundEmail {
+    pub email: String,
+    pub source_type: String,
+}
+
+// Matches like image@2x.png come from srcset/asset names, not addresses.
+const ASSET_EXTENSIONS: [&str; 16] = [
+    "png", "jpg", "jpeg", "gif", "svg", "webp", "bmp", "ico", "tif", "css", "js", "woff", "woff2", "ttf", "mp4", "webm",
+];
+
+// Error trackers, site builders and template placeholders that show up in page source.
+const DENIED_DOMAINS: [&str; 10] = [
+    "sentry.io",
+    "sentry-next.wixpress.com",
+    "sentry.wixpress.com",
+    "wixpress.com",
+    "example.com",
+    "example.org",
+    "domain.com",
+    "yourdomain.com",
+    "yoursite.com",
+    "mysite.com",
+];
+
+impl EmailExtractor {
+    pub fn new(html: String) -> Self {
+        Self { html }
+    }
+
+    pub fn find_emails(&self) -> Vec<FoundEmail> {
+        let mut emails = Vec::new();
+        let mut seen = HashSet::new();
+
+        let mut push_email = |candidate: &str, source_type: &str| {
+            if let Some(email) = clean_email(candidate) {
+                if seen.insert(email.clone()) {
+                    emails.push(FoundEmail {
+                        email,
+                        source_type: source_type.to_string(),
+                    });
+                }
+            }
+        };
+
+        let document = Html::parse_document(&self.html);
+        let email_regex = Regex::new(r"[a-zA-Z0-9._%+-]+@[a-zA-Z0-9.-]+\.[a-zA-Z]{2,}").unwrap();
+
+        let cfemail_selector = Selector::parse("[data-cfemail]").unwrap();
+        for element in document.select(&cfemail_selector) {
+            if let Some(email) = decode_cfemail(element.value().attr("data-cfemail").unwrap_or_default()) {
+                push_email(&email, "cfemail");
+            }
+        }
+
+        let protected_selector = Selector::parse("a[href*='/cdn-cgi/l/email-protection#']").unwrap();
+        for element in document.select(&protected_selector) {
+            let href = element.value().attr("href").unwrap_or_default();
+            let encoded = href.rsplit('#').next().unwrap_or_default();
+            if let Some(email) = decode_cfemail(encoded) {
+                push_email(&email, "cfemail");
+            }
+        }
+
+        let mailto_selector = Selector::parse("a[href]").unwrap();
+        for element in document.select(&mailto_selector) {
+            let href = element.value().attr("href").unwrap_or_default().trim();
+            if !href.get(..7).is_some_and(|prefix| prefix.eq_ignore_ascii_case("mailto:")) {
+                continue;
+            }
+
+            let addresses = href[7..].split('?').next().unwrap_or_default();
+            let addresses = percent_decode(addresses);
+            for address in addresses.split(',') {
+                push_email(address, "mailto");
+            }
+        }
+
+        for mat in email_regex.find_iter(&self.html) {
+            push_email(mat.as_str(), "text");
+        }
+
+        let decoded_html = decode_html_entities(&self.html);
+        for mat in email_regex.find_iter(&decoded_html) {
+            push_email(mat.as_str(), "entity");
+        }
+
+        let obfuscated_regex = Regex::new(
+            r"(?i)([a-z0-9._%+-]+)\s*[\[\(\{<]\s*(?:at|@)\s*[\]\)\}>]\s*([a-z0-9-]+(?:\s*[\[\(\{<]\s*(?:dot|\.)\s*[\]\)\}>]\s*[a-z0-9-]+)+)",
+        )
+        .unwrap();
+        let dot_regex = Regex::new(r"(?i)\s*[\[\(\{<]\s*(?:dot|\.)\s*[\]\)\}>]\s*").unwrap();
+        for captures in obfuscated_regex.captures_iter(&decoded_html) {
+            let domain = dot_regex.replace_all(&captures[2], ".");
+            push_email(&format!("{}@{}", &captures[1], domain), "obfuscated");
+        }
+
+        emails
+    }
+}
+
+// Lowercases and validates a candidate, dropping asset names and vendor addresses.
+pub fn clean_email(candidate: &str) -> Option<String> {
+    let email = candidate.trim().trim_end_matches('.').to_lowercase();
+    let (local, domain) = email.split_once('@')?;
+
+    if local.is_empty() || domain.is_empty() || !domain.contains('.') || domain.contains('@') {
+        return None;
+    }
+
+    let tld = domain.rsplit('.').next().unwrap_or_default();
+    if ASSET_EXTENSIONS.contains(&tld) {
+        return None;
+    }
+
+    if DENIED_DOMAINS.iter().any(|denied| domain == *denied || domain.ends_with(&format!(".{}", denied))) {
+        return None;
+    }
+
+    // Sentry DSNs use a 32 character hex key as the local part.
+    if local.len() == 32 && local.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    Some(email)
+}
+
+// Cloudflare email obfuscation: the first byte is the XOR key for the rest.
+pub fn decode_cfemail(encoded: &str) -> Option<String> {
+    if encoded.len() < 4 || !encoded.len().is_multiple_of(2) {
+        return None;
+    }
+
+    let bytes = (0..encoded.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(encoded.get(i..i + 2)?, 16).ok())
+        .collect::<Option<Vec<u8>>>()?;
+
+    let key = bytes[0];
+    let decoded: Vec<u8> = bytes[1..].iter().map(|byte| byte ^ key).collect();
+
+    String::from_utf8(decoded).ok()
+}
+
+pub fn percent_decode(input: &str) -> String {
+    let bytes = input.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 3 <= bytes.len() {
+            if let Some(byte) = input.get(i + 1..i + 3).and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static HTML_MAILTO: &str = r#"
+    <footer>
+        <a href="mailto:info%40mcfees.com?subject=Quote%20request">Email us</a>
+        <a href="MAILTO:Sales@McFees.com,office@mcfees.com">Sales</a>
+    </footer>
+"#;
+
+    static HTML_OBFUSCATED: &str = r#"
+    <div class="contact">
+        Write to john [at] letslandscape [dot] ca or
+        office(at)letslandscape(dot)co(dot)uk for quotes.
+        We meet at home dot com is not an email.
+    </div>
+"#;
+
+    static HTML_ENTITIES: &str = r#"
+    <p>Email: info&#64;mdrlandscapes&#46;com</p>
+    <p>Also: sales&#x40;mdrlandscapes.com and jobs&commat;mdrlandscapes&period;com</p>
+"#;
+
+    static HTML_CFEMAIL: &str = r#"
+    <p>
+        <a href="/cdn-cgi/l/email-protection" class="__cf_email__" data-cfemail="a5cccbc3cae5c8c6c3c0c0d68bc6cac8">[email&#160;protected]</a>
+        <a href="/cdn-cgi/l/email-protection#b3c0d2dfd6c0f3ded0d5d6d6c09dd0dcde">Sales</a>
+    </p>
+"#;
+
+    static HTML_FALSE_POSITIVES: &str = r#"
+    <img srcset="logo@2x.png 2x, hero@3x.webp 3x" src="logo.png">
+    <link href="fonts/icons@1.0.woff2">
+    <script>
+        Sentry.init({dsn: "https://5f2f7c3d9c8a4b1e9d0a1b2c3d4e5f60@o123.ingest.sentry.io/42"});
+        var wix = "605a7baede844d278b89dc95ae0a9123@sentry-next.wixpress.com";
+    </script>
+    <p>Placeholder: you@example.com</p>
+    <p>Real: estimates@mdrlandscapes.com.</p>
+"#;
+
+    fn emails(html: &str) -> Vec<(String, String)> {
+        EmailExtractor::new(html.to_string())
+            .find_emails()
+            .into_iter()
+            .map(|found| (found.email, found.source_type))
+            .collect()
+    }
+
+    #[test]
+    fn should_extract_url_encoded_mailto() {
+        assert_eq!(emails(HTML_MAILTO), vec![
+            ("info@mcfees.com".to_string(), "mailto".to_string()),
+            ("sales@mcfees.com".to_string(), "mailto".to_string()),
+            ("office@mcfees.com".to_string(), "mailto".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn should_extract_obfuscated_emails() {
+        assert_eq!(emails(HTML_OBFUSCATED), vec![
+            ("john@letslandscape.ca".to_string(), "obfuscated".to_string()),
+            ("office@letslandscape.co.uk".to_string(), "obfuscated".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn should_extract_entity_encoded_emails() {
+        assert_eq!(emails(HTML_ENTITIES), vec![
+            ("info@mdrlandscapes.com".to_string(), "entity".to_string()),
+            ("sales@mdrlandscapes.com".to_string(), "entity".to_string()),
+            ("jobs@mdrlandscapes.com".to_string(), "entity".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn should_decode_cloudflare_emails() {
+        assert_eq!(emails(HTML_CFEMAIL), vec![
+            ("info@mcfees.com".to_string(), "cfemail".to_string()),
+            ("sales@mcfees.com".to_string(), "cfemail".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn should_skip_assets_and_vendor_emails() {
+        assert_eq!(emails(HTML_FALSE_POSITIVES), vec![
+            ("estimates@mdrlandscapes.com".to_string(), "text".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn should_skip_non_ascii_hrefs() {
+        let html = r#"<a href="关于我们.html">About</a><a href="MAILTO:info@mcfees.com">Email</a>"#;
+
+        assert_eq!(emails(html), vec![("info@mcfees.com".to_string(), "mailto".to_string())]);
+    }
+
+    #[test]
+    fn should_decode_escape_at_end_of_input() {
+        assert_eq!(percent_decode("info%40acme.co%6D"), "info@acme.com");
+        assert_eq!(emails(r#"<a href="mailto:info%40acme.co%6D">Email</a>"#), vec![("info@acme.com".to_string(), "mailto".to_string())]);
+    }
+
+    #[test]
+    fn should_decode_cfemail_hex() {
+        assert_eq!(decode_cfemail("a5cccbc3cae5c8c6c3c0c0d68bc6cac8"), Some("info@mcfees.com".to_string()));
+        assert_eq!(decode_cfemail("zz"), None);
+    }
+}
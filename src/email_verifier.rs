@@ -0,0 +1,317 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use std::collections::HashMap;
+use trust_dns_resolver::error::ResolveErrorKind;
+use trust_dns_resolver::TokioAsyncResolver;
+use url::Url;
+
+// Throwaway inbox providers; a lead with one of these is not a real business contact.
+const DISPOSABLE_DOMAINS: [&str; 14] = [
+    "mailinator.com",
+    "guerrillamail.com",
+    "guerrillamail.net",
+    "sharklasers.com",
+    "10minutemail.com",
+    "tempmail.com",
+    "temp-mail.org",
+    "yopmail.com",
+    "trashmail.com",
+    "getnada.com",
+    "dispostable.com",
+    "maildrop.cc",
+    "throwawaymail.com",
+    "fakeinbox.com",
+];
+
+const ROLE_ACCOUNTS: [&str; 20] = [
+    "info", "sales", "admin", "office", "contact", "support", "hello", "service", "services", "billing",
+    "accounts", "marketing", "webmaster", "noreply", "no-reply", "postmaster", "enquiries", "inquiries",
+    "estimates", "careers",
+];
+
+#[async_trait]
+pub trait MxLookup {
+    async fn lookup_mx(&self, domain: &str) -> Result<Vec<String>>;
+}
+
+pub struct DnsMxLookup {
+    resolver: TokioAsyncResolver,
+}
+
+impl DnsMxLookup {
+    pub fn from_system_conf() -> Result<Self> {
+        let resolver = TokioAsyncResolver::tokio_from_system_conf()?;
+        Ok(Self { resolver })
+    }
+}
+
+#[async_trait]
+impl MxLookup for DnsMxLookup {
+    async fn lookup_mx(&self, domain: &str) -> Result<Vec<String>> {
+        match self.resolver.mx_lookup(domain).await {
+            Ok(lookup) => Ok(lookup.iter().map(|mx| mx.exchange().to_string()).collect()),
+            Err(e) => match e.kind() {
+                ResolveErrorKind::NoRecordsFound { .. } => Ok(Vec::new()),
+                _ => Err(e.into()),
+            },
+        }
+    }
+}
+
+// Answers MX queries from a fixed table, for tests.
+#[cfg(test)]
+pub struct StubMxLookup {
+    pub records: HashMap<String, Vec<String>>,
+}
+
+#[cfg(test)]
+impl StubMxLookup {
+    pub fn new() -> Self {
+        Self { records: HashMap::new() }
+    }
+
+    pub fn with_mx(mut self, domain: &str, exchange: &str) -> Self {
+        self.records
+            .entry(domain.to_string())
+            .or_default()
+            .push(exchange.to_string());
+        self
+    }
+}
+
+#[cfg(test)]
+#[async_trait]
+impl MxLookup for StubMxLookup {
+    async fn lookup_mx(&self, domain: &str) -> Result<Vec<String>> {
+        Ok(self.records.get(domain).cloned().unwrap_or_default())
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct EmailVerification {
+    pub email: String,
+    pub syntax_valid: bool,
+    pub disposable: bool,
+    pub role_account: bool,
+    pub domain_matches_website: bool,
+    pub has_mx: Option<bool>,
+    pub confidence: f32,
+}
+
+pub struct EmailVerifier<M: MxLookup> {
+    mx_lookup: M,
+    mx_cache: HashMap<String, Option<bool>>,
+}
+
+impl<M: MxLookup> EmailVerifier<M> {
+    pub fn new(mx_lookup: M) -> Self {
+        Self {
+            mx_lookup,
+            mx_cache: HashMap::new(),
+        }
+    }
+
+    pub async fn verify(&mut self, email: &str, website: &str) -> EmailVerification {
+        let email = email.trim().to_lowercase();
+        let syntax_valid = is_valid_syntax(&email);
+        let (local, domain) = email.split_once('@').unwrap_or(("", ""));
+
+        let disposable = DISPOSABLE_DOMAINS.contains(&domain);
+        let role_account = ROLE_ACCOUNTS.contains(&local);
+        let domain_matches_website = domain_matches_website(domain, website);
+
+        let has_mx = if syntax_valid && !disposable {
+            self.has_mx(domain).await
+        } else {
+            None
+        };
+
+        let confidence = score(syntax_valid, disposable, role_account, domain_matches_website, has_mx);
+
+        EmailVerification {
+            email: email.clone(),
+            syntax_valid,
+            disposable,
+            role_account,
+            domain_matches_website,
+            has_mx,
+            confidence,
+        }
+    }
+
+    // None when the lookup itself failed, so a DNS outage does not zero every score.
+    async fn has_mx(&mut self, domain: &str) -> Option<bool> {
+        if let Some(has_mx) = self.mx_cache.get(domain) {
+            return *has_mx;
+        }
+
+        let has_mx = match self.mx_lookup.lookup_mx(domain).await {
+            Ok(exchanges) => Some(!exchanges.is_empty()),
+            Err(e) => {
+                eprintln!("Error looking up MX for {}: {:?}", domain, e);
+                None
+            }
+        };
+
+        self.mx_cache.insert(domain.to_string(), has_mx);
+        has_mx
+    }
+}
+
+fn score(syntax_valid: bool, disposable: bool, role_account: bool, domain_matches_website: bool, has_mx: Option<bool>) -> f32 {
+    if !syntax_valid {
+        return 0.0;
+    }
+
+    if disposable {
+        return 0.05;
+    }
+
+    if has_mx == Some(false) {
+        return 0.1;
+    }
+
+    let mut confidence: f32 = 0.5;
+
+    if has_mx == Some(true) {
+        confidence += 0.2;
+    }
+
+    if domain_matches_website {
+        confidence += 0.3;
+    }
+
+    if role_account {
+        confidence -= 0.1;
+    }
+
+    confidence.clamp(0.0, 1.0)
+}
+
+// RFC 5322 dot-atom addresses; quoted local parts and IP literals are not accepted.
+pub fn is_valid_syntax(email: &str) -> bool {
+    let (local, domain) = match email.rsplit_once('@') {
+        Some(parts) => parts,
+        None => return false,
+    };
+
+    if local.is_empty() || local.len() > 64 || domain.len() > 253 {
+        return false;
+    }
+
+    if local.starts_with('.') || local.ends_with('.') || local.contains("..") {
+        return false;
+    }
+
+    if !local.chars().all(|c| c.is_ascii_alphanumeric() || "!#$%&'*+/=?^_`{|}~-.".contains(c)) {
+        return false;
+    }
+
+    let labels: Vec<&str> = domain.split('.').collect();
+    if labels.len() < 2 {
+        return false;
+    }
+
+    for label in &labels {
+        if label.is_empty() || label.len() > 63 || label.starts_with('-') || label.ends_with('-') {
+            return false;
+        }
+
+        if !label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+            return false;
+        }
+    }
+
+    let tld = labels[labels.len() - 1];
+    tld.len() >= 2 && tld.chars().all(|c| c.is_ascii_alphabetic())
+}
+
+pub fn domain_matches_website(domain: &str, website: &str) -> bool {
+    if domain.is_empty() || website.is_empty() {
+        return false;
+    }
+
+    let website = if website.contains("://") {
+        website.to_string()
+    } else {
+        format!("https://{}", website)
+    };
+
+    let host = match Url::parse(&website) {
+        Ok(url) => url.host_str().unwrap_or_default().to_lowercase(),
+        Err(_) => return false,
+    };
+    let host = host.trim_start_matches("www.");
+
+    host == domain || host.ends_with(&format!(".{}", domain)) || domain.ends_with(&format!(".{}", host))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn verifier() -> EmailVerifier<StubMxLookup> {
+        let stub = StubMxLookup::new()
+            .with_mx("mcfees.com", "mx1.mcfees.com")
+            .with_mx("gmail.com", "gmail-smtp-in.l.google.com");
+
+        EmailVerifier::new(stub)
+    }
+
+    #[test]
+    fn should_check_syntax() {
+        assert!(is_valid_syntax("john.smith@mcfees.com"));
+        assert!(is_valid_syntax("o'brien+quotes@mcfees.co.uk"));
+        assert!(!is_valid_syntax("john..smith@mcfees.com"));
+        assert!(!is_valid_syntax(".john@mcfees.com"));
+        assert!(!is_valid_syntax("john@mcfees"));
+        assert!(!is_valid_syntax("john@-mcfees.com"));
+        assert!(!is_valid_syntax("john smith@mcfees.com"));
+        assert!(!is_valid_syntax("john@mcfees.c0m"));
+    }
+
+    #[test]
+    fn should_match_domain_against_website() {
+        assert!(domain_matches_website("mcfees.com", "www.mcfees.com"));
+        assert!(domain_matches_website("mcfees.com", "https://www.mcfees.com/contact"));
+        assert!(!domain_matches_website("gmail.com", "https://www.mcfees.com"));
+    }
+
+    #[tokio::test]
+    async fn should_score_matching_personal_email_highest() {
+        let mut verifier = verifier();
+        let verification = verifier.verify("john@mcfees.com", "https://www.mcfees.com").await;
+
+        assert!(verification.syntax_valid);
+        assert!(!verification.role_account);
+        assert!(verification.domain_matches_website);
+        assert_eq!(verification.has_mx, Some(true));
+        assert_eq!(verification.confidence, 1.0);
+    }
+
+    #[tokio::test]
+    async fn should_lower_score_for_role_accounts_and_other_domains() {
+        let mut verifier = verifier();
+        let role = verifier.verify("info@mcfees.com", "https://www.mcfees.com").await;
+        let free = verifier.verify("mcfeelandscaping@gmail.com", "https://www.mcfees.com").await;
+
+        assert!(role.role_account);
+        assert!(role.confidence < 1.0);
+        assert!(!free.domain_matches_website);
+        assert!(free.confidence < role.confidence);
+    }
+
+    #[tokio::test]
+    async fn should_flag_disposable_and_domains_without_mx() {
+        let mut verifier = verifier();
+        let disposable = verifier.verify("lead@mailinator.com", "https://www.mcfees.com").await;
+        let no_mx = verifier.verify("john@mcfees-old.com", "https://www.mcfees.com").await;
+        let invalid = verifier.verify("john@@mcfees.com", "https://www.mcfees.com").await;
+
+        assert!(disposable.disposable);
+        assert_eq!(disposable.confidence, 0.05);
+        assert_eq!(no_mx.has_mx, Some(false));
+        assert_eq!(no_mx.confidence, 0.1);
+        assert_eq!(invalid.confidence, 0.0);
+    }
+}
@@ -0,0 +1,56 @@
+use sqlx::{FromRow, Error, query, query_as};
+use sqlx::mysql::MySqlPool;
+
+// One row per field per extraction batch.
+#[derive(Clone, Debug, FromRow)]
+pub struct ExtractionFillRates {
+    pub id: i32,
+    pub batch_id: String,
+    pub stage: String,
+    pub field: String,
+    pub total: i32,
+    pub filled: i32,
+    pub fill_rate: f32,
+    pub failing_ids: String,
+    pub extractor_version: String,
+    pub halted: i32,
+}
+
+impl ExtractionFillRates {
+    pub async fn create_record(pool: &MySqlPool, record: &ExtractionFillRates) -> Result<(), Error> {
+        query("INSERT INTO extraction_fill_rates (batch_id, stage, field, total, filled, fill_rate, failing_ids, extractor_version, halted) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)")
+            .bind(&record.batch_id)
+            .bind(&record.stage)
+            .bind(&record.field)
+            .bind(&record.total)
+            .bind(&record.filled)
+            .bind(&record.fill_rate)
+            .bind(&record.failing_ids)
+            .bind(&record.extractor_version)
+            .bind(&record.halted)
+            .execute(pool)
+            .await?;
+
+        Ok(())
+    }
+
+    // Fill rates of the last batches that were allowed to finish; halted batches are not history.
+    pub async fn get_history(pool: &MySqlPool, stage: &str, field: &str, limit: i64) -> Result<Vec<f32>, Error> {
+        let fill_rates: Vec<(f32,)> = query_as("SELECT fill_rate FROM extraction_fill_rates WHERE stage = ? AND field = ? AND halted = 0 ORDER BY id DESC LIMIT ?")
+            .bind(stage)
+            .bind(field)
+            .bind(limit)
+            .fetch_all(pool)
+            .await?;
+
+        Ok(fill_rates.into_iter().map(|fill_rate| fill_rate.0).collect())
+    }
+
+    pub async fn get_latest_batches(pool: &MySqlPool) -> Result<Vec<ExtractionFillRates>, Error> {
+        let extraction_fill_rates: Vec<ExtractionFillRates> = query_as("SELECT * FROM extraction_fill_rates WHERE id IN ( SELECT MAX(id) FROM extraction_fill_rates GROUP BY stage, field ) ORDER BY stage, field")
+            .fetch_all(pool)
+            .await?;
+
+        Ok(extraction_fill_rates)
+    }
+}
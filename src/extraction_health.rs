@@ -0,0 +1,280 @@
+use anyhow::{anyhow, Error};
+use chrono::Utc;
+use sqlx::MySqlPool;
+use std::collections::HashMap;
+use std::fmt;
+use crate::extraction_fill_rates::ExtractionFillRates;
+use crate::extractor::EXTRACTOR_VERSION;
+use crate::storage::Storage;
+
+// How many failing row ids are kept per field, as examples for the report.
+const MAX_FAILING_EXAMPLES: usize = 10;
+
+#[derive(Clone, Debug)]
+pub struct HealthConfig {
+    // Smaller batches are recorded but never halt a stage; a handful of rows says nothing.
+    pub min_batch_size: usize,
+    // Halt when a field's fill rate falls below this share of its historical average.
+    pub min_ratio_to_history: f32,
+    pub history_batches: i64,
+}
+
+impl Default for HealthConfig {
+    fn default() -> Self {
+        Self {
+            min_batch_size: 20,
+            min_ratio_to_history: 0.5,
+            history_batches: 5,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FieldFillRate {
+    pub field: String,
+    pub total: usize,
+    pub filled: usize,
+    pub failing_ids: Vec<i32>,
+}
+
+impl FieldFillRate {
+    pub fn fill_rate(&self) -> f32 {
+        if self.total == 0 {
+            return 0.0;
+        }
+
+        self.filled as f32 / self.total as f32
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FillRateAlert {
+    pub stage: String,
+    pub field: String,
+    pub fill_rate: f32,
+    pub history_fill_rate: f32,
+    pub failing_ids: Vec<i32>,
+}
+
+impl fmt::Display for FillRateAlert {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} {} fill rate {:.1}% (history {:.1}%), e.g. ids {:?}",
+            self.stage,
+            self.field,
+            self.fill_rate * 100.0,
+            self.history_fill_rate * 100.0,
+            self.failing_ids
+        )
+    }
+}
+
+// Counts, per field, how many rows of one extraction batch got a non-empty value.
+pub struct FillRateTracker {
+    pub stage: String,
+    fields: Vec<FieldFillRate>,
+}
+
+impl FillRateTracker {
+    pub fn new(stage: &str, fields: &[&str]) -> Self {
+        Self {
+            stage: stage.to_string(),
+            fields: fields
+                .iter()
+                .map(|field| FieldFillRate {
+                    field: field.to_string(),
+                    total: 0,
+                    filled: 0,
+                    failing_ids: Vec::new(),
+                })
+                .collect(),
+        }
+    }
+
+    pub fn record(&mut self, row_id: i32, values: &[(&str, bool)]) {
+        for (field, filled) in values {
+            let field_fill_rate = match self.fields.iter_mut().find(|field_fill_rate| field_fill_rate.field == *field) {
+                Some(field_fill_rate) => field_fill_rate,
+                None => continue,
+            };
+
+            field_fill_rate.total += 1;
+
+            if *filled {
+                field_fill_rate.filled += 1;
+            } else if field_fill_rate.failing_ids.len() < MAX_FAILING_EXAMPLES {
+                field_fill_rate.failing_ids.push(row_id);
+            }
+        }
+    }
+
+    pub fn fill_rates(&self) -> &[FieldFillRate] {
+        &self.fields
+    }
+
+    // Fields whose fill rate collapsed compared with the average of earlier batches.
+    pub fn check(&self, history: &HashMap<String, Vec<f32>>, config: &HealthConfig) -> Vec<FillRateAlert> {
+        let mut alerts = Vec::new();
+
+        for field_fill_rate in &self.fields {
+            if field_fill_rate.total < config.min_batch_size {
+                continue;
+            }
+
+            let history_fill_rates = match history.get(&field_fill_rate.field) {
+                Some(history_fill_rates) if !history_fill_rates.is_empty() => history_fill_rates,
+                _ => continue,
+            };
+
+            let history_fill_rate = history_fill_rates.iter().sum::<f32>() / history_fill_rates.len() as f32;
+
+            if field_fill_rate.fill_rate() < history_fill_rate * config.min_ratio_to_history {
+                alerts.push(FillRateAlert {
+                    stage: self.stage.clone(),
+                    field: field_fill_rate.field.clone(),
+                    fill_rate: field_fill_rate.fill_rate(),
+                    history_fill_rate,
+                    failing_ids: field_fill_rate.failing_ids.clone(),
+                });
+            }
+        }
+
+        alerts
+    }
+}
+
+// Saves the batch's fill rates and fails the stage, before anything is written,
+// when a field collapsed compared with history.
+pub async fn finish_batch(storage: &dyn Storage, tracker: &FillRateTracker, config: &HealthConfig) -> Result<(), Error> {
+    let mut history = HashMap::new();
+    for field_fill_rate in tracker.fill_rates() {
+        let history_fill_rates = storage.get_fill_rate_history(&tracker.stage, &field_fill_rate.field, config.history_batches).await?;
+        history.insert(field_fill_rate.field.clone(), history_fill_rates);
+    }
+
+    let alerts = tracker.check(&history, config);
+    let batch_id = format!("{}-{}", tracker.stage, Utc::now().format("%Y%m%dT%H%M%S"));
+
+    for field_fill_rate in tracker.fill_rates() {
+        println!("{} {}: {}/{} filled", tracker.stage, field_fill_rate.field, field_fill_rate.filled, field_fill_rate.total);
+
+        let extraction_fill_rate = ExtractionFillRates {
+            id: 0,
+            batch_id: batch_id.clone(),
+            stage: tracker.stage.clone(),
+            field: field_fill_rate.field.clone(),
+            total: field_fill_rate.total as i32,
+            filled: field_fill_rate.filled as i32,
+            fill_rate: field_fill_rate.fill_rate(),
+            failing_ids: join_ids(&field_fill_rate.failing_ids),
+            extractor_version: EXTRACTOR_VERSION.to_string(),
+            halted: if alerts.is_empty() { 0 } else { 1 },
+        };
+
+        storage.create_fill_rate(&extraction_fill_rate).await?;
+    }
+
+    if alerts.is_empty() {
+        return Ok(());
+    }
+
+    for alert in &alerts {
+        eprintln!("ALERT: {}", alert);
+    }
+
+    Err(anyhow!("{} halted: {} fields below their usual fill rate", tracker.stage, alerts.len()))
+}
+
+// health: latest fill rate of every stage and field, with example ids of rows that came back empty.
+pub async fn run_health_report(pool: &MySqlPool) -> Result<(), Error> {
+    let latest_batches = ExtractionFillRates::get_latest_batches(pool).await?;
+
+    println!("{:<18} {:<18} {:>8} {:>8} {:>7}  failing ids", "stage", "field", "filled", "total", "rate");
+    for batch in latest_batches {
+        println!(
+            "{:<18} {:<18} {:>8} {:>8} {:>6.1}%  {}{}",
+            batch.stage,
+            batch.field,
+            batch.filled,
+            batch.total,
+            batch.fill_rate * 100.0,
+            batch.failing_ids,
+            if batch.halted == 1 { "  (halted)" } else { "" }
+        );
+    }
+
+    Ok(())
+}
+
+fn join_ids(ids: &[i32]) -> String {
+    ids.iter().map(|id| id.to_string()).collect::<Vec<_>>().join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data;
+    use crate::extractor::Extractor;
+
+    fn history(field: &str, fill_rates: &[f32]) -> HashMap<String, Vec<f32>> {
+        let mut history = HashMap::new();
+        history.insert(field.to_string(), fill_rates.to_vec());
+        history
+    }
+
+    #[test]
+    fn should_count_fill_rates_and_failing_ids() {
+        let mut tracker = FillRateTracker::new("records_data", &["phone", "website"]);
+        tracker.record(1, &[("phone", true), ("website", true)]);
+        tracker.record(2, &[("phone", true), ("website", false)]);
+        tracker.record(3, &[("phone", false), ("website", false)]);
+        tracker.record(4, &[("phone", true), ("website", true)]);
+
+        let fill_rates = tracker.fill_rates();
+        assert_eq!(fill_rates[0].fill_rate(), 0.75);
+        assert_eq!(fill_rates[0].failing_ids, vec![3]);
+        assert_eq!(fill_rates[1].fill_rate(), 0.5);
+        assert_eq!(fill_rates[1].failing_ids, vec![2, 3]);
+    }
+
+    #[test]
+    fn should_alert_when_fill_rate_collapses() {
+        let config = HealthConfig::default();
+        let mut tracker = FillRateTracker::new("records_data", &["phone"]);
+
+        // Houzz markup changed: the selector no longer matches, so every phone comes back empty.
+        let broken_html = data::test_generate_houzz_record_html().replace("BusinessDetails__StyledCell", "ProDetails__Cell").replace("<section id=\"business\"", "<section id=\"pro-info\"");
+        for row_id in 0..25 {
+            let company_details = Extractor::new(broken_html.clone()).get_company_details_houzz();
+            tracker.record(row_id, &[("phone", company_details.phone != "")]);
+        }
+
+        let alerts = tracker.check(&history("phone", &[0.92, 0.88]), &config);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].fill_rate, 0.0);
+        assert_eq!(alerts[0].failing_ids.len(), MAX_FAILING_EXAMPLES);
+    }
+
+    #[test]
+    fn should_not_alert_on_healthy_or_small_batches() {
+        let config = HealthConfig::default();
+
+        let mut healthy = FillRateTracker::new("records_data", &["phone"]);
+        for row_id in 0..25 {
+            let company_details = Extractor::new(data::test_generate_houzz_record_html()).get_company_details_houzz();
+            healthy.record(row_id, &[("phone", company_details.phone != "")]);
+        }
+        assert!(healthy.check(&history("phone", &[0.9]), &config).is_empty());
+
+        let mut small = FillRateTracker::new("records_data", &["phone"]);
+        small.record(1, &[("phone", false)]);
+        assert!(small.check(&history("phone", &[0.9]), &config).is_empty());
+
+        let mut no_history = FillRateTracker::new("records_data", &["phone"]);
+        for row_id in 0..25 {
+            no_history.record(row_id, &[("phone", false)]);
+        }
+        assert!(no_history.check(&HashMap::new(), &config).is_empty());
+    }
+}
@@ -1,12 +1,67 @@
-use scraper::{Html, Selector};
+use scraper::{Html, Node, Selector};
 use std::convert::TryInto;
 use regex::Regex;
 use std::collections::HashSet;
 use crate::records_data::RecordsData;
 use crate::data;
+use crate::contact_links::{self, ContactLinkCandidate};
+use crate::text;
+// Bumped whenever a selector or rule below changes what it extracts.
+pub const EXTRACTOR_VERSION: &str = "1.1.1";
+
+// Rule id, the selector or pattern it stands for, and how far its values are trusted.
+pub const EXTRACTION_RULES: [(&str, &str, f32); 18] = [
+    ("houzz.details.phone", "#business > div > div:nth-child(2) > p", 0.9),
+    ("houzz.details.website", "div[data-component='Website'] span[font-size='smallPlus,medium']", 0.9),
+    ("landscape_ontario.details.phone", ".member-contact a[href^='tel:']", 0.9),
+    ("landscape_ontario.details.website", ".member-contact a[href^='http://'], .member-contact a[href^='https://']", 0.9),
+    ("directory.company", "span[itemprop='name'], h3 > a", 0.9),
+    ("houzz.details.company_json_ld", "script[type='application/ld+json'] name", 0.7),
+    ("houzz.profile.rating_json_ld", "aggregateRating.ratingValue", 0.9),
+    ("houzz.profile.rating_text", "N out of 5 | N stars", 0.6),
+    ("houzz.profile.review_count_json_ld", "aggregateRating.reviewCount", 0.9),
+    ("houzz.profile.review_count_text", "N Reviews", 0.6),
+    ("houzz.profile.badges", "Best of Houzz YYYY", 0.8),
+    ("houzz.profile.license_number", "#business .hui-cell h3 = License Number", 0.9),
+    ("houzz.profile.years_in_business", "#business .hui-cell h3 = Years in Business", 0.9),
+    ("houzz.profile.service_areas", "#business .hui-cell h3 = Service Areas", 0.9),
+    ("phone.tel", "a[href^='tel:' i]", 0.9),
+    ("phone.json_ld", "script[type='application/ld+json'] telephone", 0.8),
+    ("phone.text", "phone pattern in visible text", 0.5),
+    ("social.link", "a[href] to a social profile", 0.8),
+];
+
+pub fn rule_confidence(rule_id: &str) -> f32 {
+    EXTRACTION_RULES
+        .iter()
+        .find(|(id, _, _)| *id == rule_id)
+        .map(|(_, _, confidence)| *confidence)
+        .unwrap_or(0.5)
+}
+
 pub struct Extractor {
     pub html: String,
 }
+
+// One extracted value and the rule that produced it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExtractedField {
+    pub field: String,
+    pub value: String,
+    pub rule_id: String,
+    pub confidence: f32,
+}
+
+impl ExtractedField {
+    pub fn new(field: &str, value: &str, rule_id: &str) -> Self {
+        Self {
+            field: field.to_string(),
+            value: value.to_string(),
+            rule_id: rule_id.to_string(),
+            confidence: rule_confidence(rule_id),
+        }
+    }
+}
 #[derive(Debug)]
 pub struct CompanyInfo {
     pub company: String,
@@ -16,6 +71,24 @@ pub struct CompanyInfo {
 pub struct CompanyContactDetails {
     pub phone: String,
     pub website: String,
+    pub fields: Vec<ExtractedField>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CompanyProfile {
+    pub rating: Option<f32>,
+    pub review_count: Option<i32>,
+    pub badges: Vec<String>,
+    pub license_number: String,
+    pub years_in_business: Option<i32>,
+    pub service_areas: String,
+    pub fields: Vec<ExtractedField>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FoundPhone {
+    pub phone: String,
+    pub source_type: String,
 }
 
 impl Extractor {
@@ -45,7 +118,7 @@ impl Extractor {
                 let company = profile
                     .select(&Selector::parse("h3 > a").unwrap())
                     .next()
-                    .map(|e| e.inner_html())
+                    .map(|e| text::normalize_text(&e.inner_html()))
                     .unwrap_or_default();
 
                 company_info_list.push(CompanyInfo { company, link });
@@ -63,7 +136,7 @@ impl Extractor {
         let phone = document
             .select(&phone_selector)
             .next()
-            .map(|e| e.inner_html())
+            .map(|e| text::normalize_text(&e.inner_html()))
             .unwrap_or_default();
 
         // Selector for website URL
@@ -74,45 +147,86 @@ impl Extractor {
         let website = document
             .select(&website_selector)
             .next()
-            .map(|e| e.value().attr("href").unwrap_or_default())
+            .map(|e| text::normalize_text(e.value().attr("href").unwrap_or_default()))
             .unwrap_or_default();
 
         CompanyContactDetails {
+            fields: contact_detail_fields(&phone, &website, "landscape_ontario"),
             phone,
             website: website.to_string(),
         }
     }
 
     pub fn find_contact_us_link(&self) -> Option<String> {
-        let document = Html::parse_document(&self.html);
-        let contact_us_selector = Selector::parse("a[href*='contact']").unwrap();
-        let contact_us_link = document
-            .select(&contact_us_selector)
+        self.rank_contact_us_links()
+            .into_iter()
             .next()
-            .map(|e| e.value().attr("href").unwrap_or_default())
-            .unwrap_or_default();
+            .map(|candidate| candidate.href)
+    }
 
-        if contact_us_link.is_empty() {
-            None
-        } else {
-            Some(contact_us_link.to_string())
-        }
+    pub fn rank_contact_us_links(&self) -> Vec<ContactLinkCandidate> {
+        contact_links::rank_contact_links(&self.html)
     }
 
-    pub fn find_emails_by_regex(&self) -> String {
-        let email_regex = Regex::new(r"[a-zA-Z0-9._%+-]+@[a-zA-Z0-9.-]+\.[a-zA-Z]{2,}").unwrap();
-        
-        let mut emails = Vec::new();
+    // Phones from the company's own site: tel: links first, then JSON-LD
+    // telephone values, then numbers written in the visible text.
+    pub fn find_phones(&self) -> Vec<FoundPhone> {
+        let document = Html::parse_document(&self.html);
+        let mut phones = Vec::new();
         let mut seen = HashSet::new();
-    
-        for mat in email_regex.find_iter(&self.html) {
-            let email = mat.as_str().to_string();
-            if seen.insert(email.clone()) {
-                emails.push(email);
+
+        let mut push_phone = |raw: &str, source_type: &str| {
+            if let Some(phone) = normalize_phone(raw) {
+                if seen.insert(phone.clone()) {
+                    phones.push(FoundPhone {
+                        phone,
+                        source_type: source_type.to_string(),
+                    });
+                }
             }
+        };
+
+        let tel_selector = Selector::parse("a[href^='tel:' i]").unwrap();
+        for element in document.select(&tel_selector) {
+            let href = element.value().attr("href").unwrap_or_default();
+            let number = href[4..].replace("%20", " ").replace("%2B", "+").replace("%2b", "+");
+            // Sites often write "tel:+416-948-2966" without the country code; link text
+            // with the same digits says how the number is meant to be read.
+            let text = element.text().collect::<String>();
+            if phone_digits(&text) == phone_digits(&number) {
+                push_phone(&text, "tel");
+            } else {
+                push_phone(&number, "tel");
+            }
+        }
+
+        let json_ld_selector = Selector::parse("script[type='application/ld+json']").unwrap();
+        for element in document.select(&json_ld_selector) {
+            let json = element.text().collect::<String>();
+            if let Ok(value) = serde_json::from_str::<serde_json::Value>(&json) {
+                let mut telephones = Vec::new();
+                collect_json_ld_telephones(&value, &mut telephones);
+                for telephone in telephones {
+                    push_phone(&telephone, "json_ld");
+                }
+            }
+        }
+
+        let text = visible_text(&document);
+        let phone_regex = Regex::new(
+            r"\+\d{1,3}(?:[\s.-]?\(?\d{1,4}\)?){2,5}|(?:\b1[\s.-]?)?\(?\b[2-9]\d{2}\)?[\s.-]?[2-9]\d{2}[\s.-]?\d{4}\b",
+        )
+        .unwrap();
+        for mat in phone_regex.find_iter(&text) {
+            let before = text[..mat.start()].chars().next_back();
+            let after = text[mat.end()..].chars().next();
+            if before.is_some_and(|c| c.is_ascii_digit()) || after.is_some_and(|c| c.is_ascii_digit()) {
+                continue;
+            }
+            push_phone(mat.as_str(), "text");
         }
 
-        emails.join(", ")
+        phones
     }
 
     pub fn get_company_info_houzz(&self) -> Vec<CompanyInfo> {
@@ -132,7 +246,7 @@ impl Extractor {
                     .to_string();
     
                 if let Some(company_element) = link_element.select(&company_selector).next() {
-                    let company = company_element.inner_html();
+                    let company = text::normalize_text(&company_element.inner_html());
     
                     company_info_list.push(CompanyInfo { company, link });
                 }
@@ -150,7 +264,7 @@ impl Extractor {
         let phone = document
             .select(&phone_selector)
             .next()
-            .map(|e| e.inner_html())
+            .map(|e| text::normalize_text(&e.inner_html()))
             .unwrap_or_default();
 
         // Selector for website URL
@@ -161,14 +275,308 @@ impl Extractor {
         let website = document
             .select(&website_selector)
             .next()
-            .map(|e| e.inner_html())
+            .map(|e| text::normalize_text(&e.inner_html()))
             .unwrap_or_default();
 
         CompanyContactDetails {
+            fields: contact_detail_fields(&phone, &website, "houzz"),
             phone,
             website: website.to_string(),
         }
     }
+
+    // Reviews and awards sit in the profile header, license and service areas in the #business cells.
+    pub fn get_company_profile_houzz(&self) -> CompanyProfile {
+        let document = Html::parse_document(&self.html);
+        let text = visible_text(&document);
+
+        let script_selector = Selector::parse("script[type='application/ld+json']").unwrap();
+        let mut rating = None;
+        let mut review_count = None;
+        let mut fields = Vec::new();
+
+        for script in document.select(&script_selector) {
+            let json = script.text().collect::<String>();
+            if let Ok(value) = serde_json::from_str::<serde_json::Value>(&json) {
+                if let Some((json_rating, json_review_count)) = find_json_ld_aggregate_rating(&value) {
+                    if rating.is_none() && json_rating.is_some() {
+                        rating = json_rating;
+                        fields.push(ExtractedField::new("rating", &json_rating.unwrap_or_default().to_string(), "houzz.profile.rating_json_ld"));
+                    }
+                    if review_count.is_none() && json_review_count.is_some() {
+                        review_count = json_review_count;
+                        fields.push(ExtractedField::new("review_count", &json_review_count.unwrap_or_default().to_string(), "houzz.profile.review_count_json_ld"));
+                    }
+                }
+            }
+        }
+
+        if rating.is_none() {
+            let rating_regex = Regex::new(r"(?i)\b([0-5](?:\.\d)?)\s*(?:out of 5|stars?)\b").unwrap();
+            rating = rating_regex
+                .captures(&text)
+                .and_then(|captures| captures[1].parse::<f32>().ok());
+
+            if let Some(rating) = rating {
+                fields.push(ExtractedField::new("rating", &rating.to_string(), "houzz.profile.rating_text"));
+            }
+        }
+
+        if review_count.is_none() {
+            let review_regex = Regex::new(r"(?i)\b(\d[\d,]*)\s+reviews?\b").unwrap();
+            review_count = review_regex
+                .captures(&text)
+                .and_then(|captures| captures[1].replace(',', "").parse::<i32>().ok());
+
+            if let Some(review_count) = review_count {
+                fields.push(ExtractedField::new("review_count", &review_count.to_string(), "houzz.profile.review_count_text"));
+            }
+        }
+
+        // Badges are often images, so their alt text counts as well.
+        let img_selector = Selector::parse("img[alt]").unwrap();
+        let mut badge_text = text.clone();
+        for img in document.select(&img_selector) {
+            badge_text.push_str(img.value().attr("alt").unwrap_or_default());
+            badge_text.push(' ');
+        }
+
+        let badge_regex = Regex::new(r"(?i)best of houzz\s+(\d{4})(?:\s*[-–]?\s*(customer service|service|design))?").unwrap();
+        let mut badges: Vec<String> = Vec::new();
+        for captures in badge_regex.captures_iter(&badge_text) {
+            let badge = match captures.get(2) {
+                Some(kind) => format!("Best of Houzz {} {}", &captures[1], title_case(kind.as_str())),
+                None => format!("Best of Houzz {}", &captures[1]),
+            };
+
+            if !badges.contains(&badge) {
+                badges.push(badge);
+            }
+        }
+
+        if !badges.is_empty() {
+            fields.push(ExtractedField::new("badges", &badges.join(", "), "houzz.profile.badges"));
+        }
+
+        let license_number = business_detail(&document, &["License Number", "Licence Number", "License"]).unwrap_or_default();
+        if license_number != "" {
+            fields.push(ExtractedField::new("license_number", &license_number, "houzz.profile.license_number"));
+        }
+
+        let service_areas = business_detail(&document, &["Service Areas", "Service Area", "Areas Served"]).unwrap_or_default();
+        if service_areas != "" {
+            fields.push(ExtractedField::new("service_areas", &service_areas, "houzz.profile.service_areas"));
+        }
+
+        let years_regex = Regex::new(r"\d+").unwrap();
+        let years_in_business = business_detail(&document, &["Years in Business"])
+            .and_then(|years| years_regex.find(&years).and_then(|years| years.as_str().parse::<i32>().ok()));
+        if let Some(years_in_business) = years_in_business {
+            fields.push(ExtractedField::new("years_in_business", &years_in_business.to_string(), "houzz.profile.years_in_business"));
+        }
+
+        CompanyProfile {
+            rating,
+            review_count,
+            badges,
+            license_number,
+            years_in_business,
+            service_areas,
+            fields,
+        }
+    }
+}
+
+fn contact_detail_fields(phone: &str, website: &str, directory: &str) -> Vec<ExtractedField> {
+    let mut fields = Vec::new();
+
+    if phone != "" {
+        fields.push(ExtractedField::new("phone", phone, &format!("{}.details.phone", directory)));
+    }
+
+    if website != "" {
+        fields.push(ExtractedField::new("website", website, &format!("{}.details.website", directory)));
+    }
+
+    fields
+}
+
+// Text of the #business cell whose heading matches one of the labels, e.g. "License Number".
+fn business_detail(document: &Html, labels: &[&str]) -> Option<String> {
+    let cell_selector = Selector::parse("#business .hui-cell").unwrap();
+    let heading_selector = Selector::parse("h3").unwrap();
+
+    for cell in document.select(&cell_selector) {
+        let heading = match cell.select(&heading_selector).next() {
+            Some(heading) => heading,
+            None => continue,
+        };
+        let label = heading.text().collect::<String>();
+
+        if !labels.iter().any(|expected| label.trim().eq_ignore_ascii_case(expected)) {
+            continue;
+        }
+
+        let values: Vec<String> = cell
+            .descendants()
+            .filter(|node| !node.ancestors().any(|ancestor| ancestor.id() == heading.id()))
+            .filter_map(|node| node.value().as_text().map(|value| value.to_string()))
+            .map(|value| text::normalize_text(&value))
+            .map(|value| value.trim_matches(|c: char| c == ',' || c.is_whitespace()).to_string())
+            .filter(|value| !value.is_empty())
+            .collect();
+
+        if !values.is_empty() {
+            return Some(values.join(", "));
+        }
+    }
+
+    None
+}
+
+fn find_json_ld_aggregate_rating(value: &serde_json::Value) -> Option<(Option<f32>, Option<i32>)> {
+    match value {
+        serde_json::Value::Object(map) => {
+            if let Some(aggregate_rating) = map.get("aggregateRating") {
+                let rating = aggregate_rating.get("ratingValue").and_then(json_number).map(|rating| rating as f32);
+                let review_count = aggregate_rating
+                    .get("reviewCount")
+                    .or_else(|| aggregate_rating.get("ratingCount"))
+                    .and_then(json_number)
+                    .map(|review_count| review_count as i32);
+                return Some((rating, review_count));
+            }
+
+            map.values().find_map(find_json_ld_aggregate_rating)
+        }
+        serde_json::Value::Array(values) => values.iter().find_map(find_json_ld_aggregate_rating),
+        _ => None,
+    }
+}
+
+// JSON-LD numbers are sometimes written as strings, e.g. "ratingValue": "4.9".
+fn json_number(value: &serde_json::Value) -> Option<f64> {
+    match value {
+        serde_json::Value::Number(number) => number.as_f64(),
+        serde_json::Value::String(number) => number.trim().parse::<f64>().ok(),
+        _ => None,
+    }
+}
+
+fn title_case(value: &str) -> String {
+    value
+        .split_whitespace()
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => format!("{}{}", first.to_uppercase(), chars.as_str().to_lowercase()),
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// NANP numbers are formatted like the directory phones, e.g. "(905) 713-1230",
+// so they compare equal to records_data.phone. Anything else is kept as +digits.
+// A '+' number is only NANP when it carries the country code 1; "+43 1 234 5678" is Vienna.
+pub fn normalize_phone(raw: &str) -> Option<String> {
+    let raw = raw.trim();
+    let digits = phone_digits(raw);
+    let international = raw.starts_with('+');
+
+    let national = if digits.len() == 11 && digits.starts_with('1') {
+        Some(&digits[1..])
+    } else if digits.len() == 10 && !international {
+        Some(&digits[..])
+    } else {
+        None
+    };
+
+    if let Some(national) = national {
+        let bytes = national.as_bytes();
+        if bytes[0] < b'2' || bytes[3] < b'2' {
+            return None;
+        }
+        return Some(format!("({}) {}-{}", &national[..3], &national[3..6], &national[6..]));
+    }
+
+    if international && digits.len() >= 8 && digits.len() <= 15 {
+        return Some(format!("+{}", digits));
+    }
+
+    None
+}
+
+// Matches the canonical_phone column: the digits without the NANP country code,
+// so "+1 (905) 713-1230" and "905.713.1230" share one key.
+pub fn canonical_phone(phone: &str) -> Option<String> {
+    let digits = phone_digits(phone);
+    let digits = match digits.strip_prefix('1') {
+        Some(national) if digits.len() == 11 => national.to_string(),
+        _ => digits,
+    };
+
+    if digits.is_empty() || digits.len() > 15 {
+        return None;
+    }
+
+    Some(digits)
+}
+
+fn phone_digits(raw: &str) -> String {
+    raw.chars().filter(|c| c.is_ascii_digit()).collect()
+}
+
+fn collect_json_ld_telephones(value: &serde_json::Value, telephones: &mut Vec<String>) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (key, value) in map {
+                if key == "telephone" {
+                    match value {
+                        serde_json::Value::String(telephone) => telephones.push(telephone.clone()),
+                        serde_json::Value::Array(values) => {
+                            for value in values {
+                                if let Some(telephone) = value.as_str() {
+                                    telephones.push(telephone.to_string());
+                                }
+                            }
+                        }
+                        _ => {}
+                    }
+                } else {
+                    collect_json_ld_telephones(value, telephones);
+                }
+            }
+        }
+        serde_json::Value::Array(values) => {
+            for value in values {
+                collect_json_ld_telephones(value, telephones);
+            }
+        }
+        _ => {}
+    }
+}
+
+// Text the visitor actually sees, without script, style and noscript content.
+fn visible_text(document: &Html) -> String {
+    let mut text = String::new();
+
+    for node in document.tree.nodes() {
+        if let Node::Text(node_text) = node.value() {
+            let hidden = node.ancestors().any(|ancestor| match ancestor.value() {
+                Node::Element(element) => matches!(element.name(), "script" | "style" | "noscript" | "head"),
+                _ => false,
+            });
+
+            if !hidden {
+                text.push_str(node_text);
+                text.push(' ');
+            }
+        }
+    }
+
+    text
 }
 
 impl TryInto<RecordsData> for CompanyContactDetails {
@@ -184,6 +592,9 @@ impl TryInto<RecordsData> for CompanyContactDetails {
             phone: self.phone.to_string(),
             website: self.website.to_string(),
             contact_us_link: Some(String::new()), // Default value or derive from context
+            company: String::new(),
+            company_verified: None,
+            extractor_version: EXTRACTOR_VERSION.to_string(),
         })
     }
 }
@@ -192,6 +603,7 @@ impl CompanyContactDetails {}
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::email_extractor::EmailExtractor;
     static HTML: &str = r#"
     <div class="searchprofile col-md-5 col-xs-12">
     <div class="logo">
@@ -372,6 +784,26 @@ mod tests {
 
 
 
+    static HTML_WEBSITE_PHONES: &str = r#"
+    <html>
+    <head>
+        <title>McFee Landscaping</title>
+        <script type="application/ld+json">
+        {"@context": "https://schema.org", "@type": "LocalBusiness", "name": "McFee", "address": {"@type": "PostalAddress", "telephone": "+1 905-713-1231"}}
+        </script>
+    </head>
+    <body>
+        <header>
+            <a href="tel:+1-905-713-1230">Call us</a>
+        </header>
+        <p>Office: (905) 713-1230 or toll free 1-800-555-0199</p>
+        <p>UK office: +44 20 7946 0958</p>
+        <p>Member since 2019, project #20231115</p>
+        <script>var tracking = "416-555-0100";</script>
+    </body>
+    </html>
+"#;
+
     #[test]
     fn should_extract_company_info_houzz() {
         let html_houzz = data::test_generate_houzz_html();
@@ -390,6 +822,58 @@ mod tests {
         assert_eq!(company_details.phone, "(905) 713-1230");
         assert_eq!(company_details.website, "www.mcfees.com");
     }
+
+    #[test]
+    fn should_extract_company_profile_houzz(){
+        let html_record_houzz = data::test_generate_houzz_record_profile_html();
+        let extractor = Extractor::new(html_record_houzz.to_string());
+        let company_profile = extractor.get_company_profile_houzz();
+
+        assert_eq!(company_profile.rating, Some(4.9));
+        assert_eq!(company_profile.review_count, Some(37));
+        assert_eq!(company_profile.badges, vec!["Best of Houzz 2023 Service", "Best of Houzz 2021", "Best of Houzz 2022 Design"]);
+        assert_eq!(company_profile.license_number, "ON-GC-482913, TSSA 000118273");
+        assert_eq!(company_profile.years_in_business, Some(28));
+        assert_eq!(company_profile.service_areas, "Gormley, Richmond Hill, Markham, Vaughan");
+
+        let company_details = extractor.get_company_details_houzz();
+        assert_eq!(company_details.phone, "(905) 713-1230");
+    }
+
+    #[test]
+    fn should_record_rules_for_extracted_fields(){
+        let extractor = Extractor::new(data::test_generate_houzz_record_profile_html());
+        let company_profile = extractor.get_company_profile_houzz();
+        let rules: Vec<(&str, &str)> = company_profile.fields.iter().map(|f| (f.field.as_str(), f.rule_id.as_str())).collect();
+
+        assert_eq!(rules, vec![
+            ("rating", "houzz.profile.rating_json_ld"),
+            ("review_count", "houzz.profile.review_count_json_ld"),
+            ("badges", "houzz.profile.badges"),
+            ("license_number", "houzz.profile.license_number"),
+            ("service_areas", "houzz.profile.service_areas"),
+            ("years_in_business", "houzz.profile.years_in_business"),
+        ]);
+        assert_eq!(company_profile.fields[0].confidence, 0.9);
+
+        let extractor = Extractor::new(data::test_generate_houzz_record_html());
+        let company_details = extractor.get_company_details_houzz();
+
+        assert_eq!(company_details.fields, vec![
+            ExtractedField::new("phone", "(905) 713-1230", "houzz.details.phone"),
+            ExtractedField::new("website", "www.mcfees.com", "houzz.details.website"),
+        ]);
+        assert_eq!(rule_confidence("unknown.rule"), 0.5);
+    }
+
+    #[test]
+    fn should_extract_empty_company_profile_houzz(){
+        let html_record_houzz = data::test_generate_houzz_record_html();
+        let extractor = Extractor::new(html_record_houzz.to_string());
+        let company_profile = extractor.get_company_profile_houzz();
+
+        assert_eq!(company_profile, CompanyProfile::default());
+    }
     #[test]
     fn should_extract_company_info() {
         let extractor = Extractor::new(HTML.to_string());
@@ -447,20 +931,78 @@ mod tests {
 
     #[test]
     fn should_extract_emails(){
-        let extractor = Extractor::new(HTML_CONTACT_FIND_EMAIL.to_string());
-        let emails = extractor.find_emails_by_regex();
+        let emails: Vec<String> = EmailExtractor::new(HTML_CONTACT_FIND_EMAIL.to_string()).find_emails().into_iter().map(|found| found.email).collect();
 
-        assert_eq!(emails, "djolecs97@gmail.com, ikariam1234@youtube.com, test123@blabla.com");
+        assert_eq!(emails, vec!["djolecs97@gmail.com", "ikariam1234@youtube.com", "test123@blabla.com"]);
     }
 
     #[test]
     fn should_extract_single_email(){
-        let extractor = Extractor::new(HTML_CONTACT_FIND_EMAIL_SINGLE.to_string());
-        let emails = extractor.find_emails_by_regex();
+        let emails: Vec<String> = EmailExtractor::new(HTML_CONTACT_FIND_EMAIL_SINGLE.to_string()).find_emails().into_iter().map(|found| found.email).collect();
+
+        assert_eq!(emails, vec!["djoko@bestbuy.org"]);
+
+
+    }
+
+    #[test]
+    fn should_extract_phones_from_website(){
+        let extractor = Extractor::new(HTML_WEBSITE_PHONES.to_string());
+        let phones = extractor.find_phones();
+
+        let phones: Vec<(&str, &str)> = phones.iter().map(|p| (p.phone.as_str(), p.source_type.as_str())).collect();
+        assert_eq!(phones, vec![
+            ("(905) 713-1230", "tel"),
+            ("(905) 713-1231", "json_ld"),
+            ("(800) 555-0199", "text"),
+            ("+442079460958", "text"),
+        ]);
+    }
+
+    #[test]
+    fn should_extract_phone_from_tel_link(){
+        let extractor = Extractor::new(HTML_CONTACT.to_string());
+        let phones = extractor.find_phones();
+
+        assert_eq!(phones.len(), 1);
+        assert_eq!(phones[0].phone, "(416) 948-2966");
+        assert_eq!(phones[0].source_type, "tel");
+    }
+
+    #[test]
+    fn should_read_tel_links_in_any_case(){
+        let extractor = Extractor::new(r#"<a href="Tel:+43 1 234 5678">Vienna office</a><a href="TEL:905-713-1230">Call</a>"#.to_string());
+        let phones = extractor.find_phones();
+
+        let phones: Vec<&str> = phones.iter().map(|p| p.phone.as_str()).collect();
+        assert_eq!(phones, vec!["+4312345678", "(905) 713-1230"]);
+    }
+
+    #[test]
+    fn should_not_extract_phones_without_numbers(){
+        let extractor = Extractor::new(HTML_CONTACT_FIND_PHONE.to_string());
+        let phones = extractor.find_phones();
 
-        assert_eq!(emails, "djoko@bestbuy.org");
+        assert!(phones.is_empty());
+    }
 
+    #[test]
+    fn should_normalize_phones(){
+        assert_eq!(normalize_phone("905.713.1230"), Some("(905) 713-1230".to_string()));
+        assert_eq!(normalize_phone("+1 (905) 713-1230"), Some("(905) 713-1230".to_string()));
+        assert_eq!(normalize_phone("+33 1 23 45 67 89"), Some("+33123456789".to_string()));
+        assert_eq!(normalize_phone("+43 1 234 5678"), Some("+4312345678".to_string()));
+        assert_eq!(normalize_phone("123-456-7890"), None);
+        assert_eq!(normalize_phone("2019"), None);
+    }
 
+    #[test]
+    fn should_compute_canonical_phone(){
+        assert_eq!(canonical_phone("(905) 713-1230"), Some("9057131230".to_string()));
+        assert_eq!(canonical_phone("+1 905.713.1230"), Some("9057131230".to_string()));
+        assert_eq!(canonical_phone("+33123456789"), Some("33123456789".to_string()));
+        assert_eq!(canonical_phone(""), None);
+        assert_eq!(canonical_phone("call us"), None);
     }
     
 }
@@ -0,0 +1,88 @@
+use sqlx::{FromRow, Error, query, query_as};
+use sqlx::mysql::MySqlPool;
+use crate::extractor::{ExtractedField, EXTRACTOR_VERSION};
+
+// Where a stored value came from: the row it was extracted from, the page, the
+// rule that matched, the extractor version and how much the rule is trusted.
+#[derive(Clone, Debug, FromRow)]
+pub struct FieldProvenance {
+    pub id: i32,
+    pub target_table: String,
+    pub target_id: i32,
+    pub field: String,
+    pub value: String,
+    pub source_table: String,
+    pub source_id: i32,
+    pub page_url: String,
+    pub rule_id: String,
+    pub extractor_version: String,
+    pub confidence: f32,
+}
+
+impl FieldProvenance {
+    pub async fn create_record(pool: &MySqlPool, record: &FieldProvenance) -> Result<(), Error> {
+        query("INSERT INTO field_provenance (target_table, target_id, field, value, source_table, source_id, page_url, rule_id, extractor_version, confidence) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)")
+            .bind(&record.target_table)
+            .bind(&record.target_id)
+            .bind(&record.field)
+            .bind(&record.value)
+            .bind(&record.source_table)
+            .bind(&record.source_id)
+            .bind(&record.page_url)
+            .bind(&record.rule_id)
+            .bind(&record.extractor_version)
+            .bind(&record.confidence)
+            .execute(pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_records_by_target(pool: &MySqlPool, target_table: &str, target_id: i32) -> Result<Vec<FieldProvenance>, Error> {
+        let field_provenance: Vec<FieldProvenance> = query_as("SELECT * FROM field_provenance WHERE target_table = ? AND target_id = ?")
+            .bind(target_table)
+            .bind(target_id)
+            .fetch_all(pool)
+            .await?;
+
+        Ok(field_provenance)
+    }
+
+    pub async fn get_records_by_rule(pool: &MySqlPool, rule_id: &str) -> Result<Vec<FieldProvenance>, Error> {
+        let field_provenance: Vec<FieldProvenance> = query_as("SELECT * FROM field_provenance WHERE rule_id = ?")
+            .bind(rule_id)
+            .fetch_all(pool)
+            .await?;
+
+        Ok(field_provenance)
+    }
+
+    // One provenance row per extracted field of a stored value.
+    pub fn from_fields(target_table: &str, target_id: i32, source_table: &str, source_id: i32, page_url: &str, fields: &[ExtractedField]) -> Vec<FieldProvenance> {
+        fields
+            .iter()
+            .map(|field| FieldProvenance {
+                id: 0,
+                target_table: target_table.to_string(),
+                target_id,
+                field: field.field.clone(),
+                value: field.value.clone(),
+                source_table: source_table.to_string(),
+                source_id,
+                page_url: page_url.to_string(),
+                rule_id: field.rule_id.clone(),
+                extractor_version: EXTRACTOR_VERSION.to_string(),
+                confidence: field.confidence,
+            })
+            .collect()
+    }
+
+    // Logs and skips failed inserts; a missing provenance row must not fail the stage.
+    pub async fn save_fields(pool: &MySqlPool, target_table: &str, target_id: i32, source_table: &str, source_id: i32, page_url: &str, fields: &[ExtractedField]) {
+        for field_provenance in FieldProvenance::from_fields(target_table, target_id, source_table, source_id, page_url, fields) {
+            if let Err(e) = FieldProvenance::create_record(pool, &field_provenance).await {
+                eprintln!("Error inserting provenance: {:?}", e);
+            }
+        }
+    }
+}
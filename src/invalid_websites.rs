@@ -1,6 +1,7 @@
 use sqlx::{Row, FromRow, Error, MySql, query, query_as};
 
 use sqlx::mysql::MySqlPool;
+use crate::urls;
 
 #[derive(Clone, Debug, FromRow)]
 pub struct InvalidWebsites {
@@ -10,17 +11,20 @@ pub struct InvalidWebsites {
 impl InvalidWebsites {
     pub async fn create_record(pool: &MySqlPool, record: &InvalidWebsites) -> Result<(), Error> {
         println!("Creating invalid website: {:?}", record);
-        query("INSERT INTO invalid_websites (website) VALUES (?)")
+        query("INSERT INTO invalid_websites (website, canonical_domain) VALUES (?, ?)")
             .bind(&record.website)
+            .bind(urls::canonical_domain(&record.website))
             .execute(pool)
             .await?;
 
         Ok(())
     }
 
+    // A website is invalid when any URL on its domain failed to resolve.
     pub async fn record_exists(pool: &MySqlPool, website: &str) -> Result<bool, Error> {
-        let exists: (i32,) = query_as("SELECT EXISTS( SELECT 1 FROM invalid_websites WHERE website = ? )")
+        let exists: (i32,) = query_as("SELECT EXISTS( SELECT 1 FROM invalid_websites WHERE website = ? OR canonical_domain = ? )")
             .bind(website)
+            .bind(urls::canonical_domain(website))
             .fetch_one(pool)
             .await?;
 
@@ -35,6 +39,16 @@ impl InvalidWebsites {
         Ok(invalid_websites)
     }
 
+    pub async fn update_canonical_domain(pool: &MySqlPool, record: &InvalidWebsites) -> Result<(), Error> {
+        query("UPDATE invalid_websites SET canonical_domain = ? WHERE website = ?")
+            .bind(urls::canonical_domain(&record.website))
+            .bind(&record.website)
+            .execute(pool)
+            .await?;
+
+        Ok(())
+    }
+
     pub async fn delete_record(pool: &MySqlPool, record: &InvalidWebsites) -> Result<(), Error> {
         println!("Deleting invalid website: {:?}", record);
         query("DELETE FROM invalid_websites WHERE website = ?")
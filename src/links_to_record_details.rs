@@ -1,4 +1,4 @@
-use sqlx::{Row, FromRow, Error, MySql, query, query_as};
+use sqlx::{Row, FromRow, Error, Executor, MySql, query, query_as};
 use sqlx::mysql::MySqlPool;
 use anyhow::Result;
 
@@ -8,20 +8,25 @@ pub struct LinksToRecordDetails {
     pub pages_with_all_records_id: i32,
     pub company: String,
     pub link: String,
-    pub visited: i32
+    pub visited: i32,
+    pub extractor_version: String,
 }
 
 impl LinksToRecordDetails {
-    pub async fn create_record(pool: &MySqlPool, link: &LinksToRecordDetails) -> Result<(), Error> {
-        println!("Creating link: {:?}", link);
-        query("INSERT INTO links_to_record_details (pages_with_all_records_id, company, link) VALUES (?, ?, ?)")
+    // Inserts the link, or counts another sighting of it. True when the row is new.
+    // Takes the pool or a transaction.
+    pub async fn upsert_record<'e, E: Executor<'e, Database = MySql>>(executor: E, link: &LinksToRecordDetails) -> Result<bool, Error> {
+        println!("Upserting link: {:?}", link);
+        let result = query("INSERT INTO links_to_record_details (pages_with_all_records_id, company, link, extractor_version) VALUES (?, ?, ?, ?) ON DUPLICATE KEY UPDATE seen_count = seen_count + 1")
             .bind(&link.pages_with_all_records_id)
             .bind(&link.company)
             .bind(&link.link)
-            .execute(pool)
+            .bind(&link.extractor_version)
+            .execute(executor)
             .await?;
 
-        Ok(())
+        // 1 for an insert, 2 for an update; seen_count always changes on a duplicate.
+        Ok(result.rows_affected() == 1)
     }
 
     pub async fn delete_record(pool: &MySqlPool, link: &LinksToRecordDetails) -> Result<(), Error> {
@@ -36,16 +41,27 @@ impl LinksToRecordDetails {
         Ok(())
     }
 
-    pub async fn mark_record_as_visited(pool: &MySqlPool, link: &LinksToRecordDetails) -> Result<(), Error> {
+    pub async fn mark_record_as_visited<'e, E: Executor<'e, Database = MySql>>(executor: E, link: &LinksToRecordDetails) -> Result<(), Error> {
         println!("Marking link as visited: {:?}", link);
-        query("UPDATE links_to_record_details SET visited = 1 WHERE link = ?")
+        query("UPDATE links_to_record_details SET visited = 1, visited_at = NOW() WHERE link = ?")
             .bind(&link.link)
-            .execute(pool)
+            .execute(executor)
             .await?;
 
         Ok(())
     }
 
+    // Puts links visited more than `max_age_days` ago back in the queue of the
+    // details stage. Links visited before visited_at existed count as stale.
+    pub async fn requeue_stale_records(pool: &MySqlPool, max_age_days: i32) -> Result<u64, Error> {
+        let result = query("UPDATE links_to_record_details SET visited = 0 WHERE visited = 1 AND (visited_at IS NULL OR visited_at < NOW() - INTERVAL ? DAY)")
+            .bind(max_age_days)
+            .execute(pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
     pub async fn get_all_records(pool: &MySqlPool) -> Result<Vec<LinksToRecordDetails>, Error> {
         let links_to_record_details: Vec<LinksToRecordDetails> = query_as("SELECT * FROM links_to_record_details")
             .fetch_all(pool)
@@ -58,7 +74,7 @@ impl LinksToRecordDetails {
         //let query = "SELECT DISTINCT links_to_record_details.link, links_to_record_details.pages_with_all_records_id, links_to_record_details.company, links_to_record_details.visited, links_to_record_details.id FROM links_to_record_details INNER JOIN pages_with_all_records ON pages_with_all_records.id = links_to_record_details.pages_with_all_records_id WHERE district LIKE '%Home Builders in Ontario - Houzz%'";
         //let query2 = "SELECT DISTINCT links_to_record_details.link, links_to_record_details.company FROM links_to_record_details INNER JOIN pages_with_all_records ON links_to_record_details.pages_with_all_records_id = pages_with_all_records.id WHERE district LIKE '%Home Builders in Ontario - Houzz%'";
         
-        let links_to_record_details: Vec<LinksToRecordDetails> = query_as("SELECT DISTINCT links_to_record_details.link, links_to_record_details.pages_with_all_records_id, links_to_record_details.company, links_to_record_details.visited, links_to_record_details.id FROM links_to_record_details INNER JOIN pages_with_all_records ON pages_with_all_records.id = links_to_record_details.pages_with_all_records_id WHERE district LIKE '%Home Builders in Ontario - Houzz%'")
+        let links_to_record_details: Vec<LinksToRecordDetails> = query_as("SELECT DISTINCT links_to_record_details.link, links_to_record_details.pages_with_all_records_id, links_to_record_details.company, links_to_record_details.visited, links_to_record_details.id, links_to_record_details.extractor_version FROM links_to_record_details INNER JOIN pages_with_all_records ON pages_with_all_records.id = links_to_record_details.pages_with_all_records_id WHERE district LIKE '%Home Builders in Ontario - Houzz%'")
             .fetch_all(pool)
             .await?;
 
@@ -76,6 +92,45 @@ impl LinksToRecordDetails {
         Ok(exists.0 == 1)
     }
 
+    pub async fn update_company(pool: &MySqlPool, link: &LinksToRecordDetails) -> Result<(), Error> {
+        println!("Updating company: {:?}", link);
+        query("UPDATE links_to_record_details SET company = ?, extractor_version = ? WHERE id = ?")
+            .bind(&link.company)
+            .bind(&link.extractor_version)
+            .bind(&link.id)
+            .execute(pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_records_by_pages_with_all_records_id(pool: &MySqlPool, pages_with_all_records_id: i32) -> Result<Vec<LinksToRecordDetails>, Error> {
+        let links_to_record_details: Vec<LinksToRecordDetails> = query_as("SELECT * FROM links_to_record_details WHERE pages_with_all_records_id = ?")
+            .bind(pages_with_all_records_id)
+            .fetch_all(pool)
+            .await?;
+
+        Ok(links_to_record_details)
+    }
+
+    pub async fn get_record_by_id(pool: &MySqlPool, id: i32) -> Result<LinksToRecordDetails, Error> {
+        let link: LinksToRecordDetails = query_as("SELECT * FROM links_to_record_details WHERE id = ?")
+            .bind(id)
+            .fetch_one(pool)
+            .await?;
+
+        Ok(link)
+    }
+
+    pub async fn get_record_by_link(pool: &MySqlPool, link: &str) -> Result<Option<LinksToRecordDetails>, Error> {
+        let link: Option<LinksToRecordDetails> = query_as("SELECT * FROM links_to_record_details WHERE link = ?")
+            .bind(link)
+            .fetch_optional(pool)
+            .await?;
+
+        Ok(link)
+    }
+
     pub async fn get_all_unvisited_records(pool: &MySqlPool) -> Result<Vec<LinksToRecordDetails>, Error> {
         let links_to_record_details: Vec<LinksToRecordDetails> = query_as("SELECT * FROM links_to_record_details WHERE visited = 0")
             .fetch_all(pool)
@@ -9,8 +9,35 @@ mod extractor;
 mod records_data;
 mod websites_html;
 mod invalid_websites;
-
-use anyhow::Error;
+mod record_phones;
+mod email_extractor;
+mod email_verifier;
+mod record_emails;
+mod site_crawler;
+mod website_pages;
+mod contact_links;
+mod urls;
+mod social_links;
+mod record_social_profiles;
+mod records_profile;
+mod company_names;
+mod text;
+mod field_provenance;
+mod reextract;
+mod extraction_health;
+mod extraction_fill_rates;
+mod migrations;
+mod storage;
+mod sqlite_storage;
+#[cfg(test)]
+mod memory_storage;
+mod page_blobs;
+mod warc;
+mod replay;
+mod record_field_history;
+mod recrawl;
+
+use anyhow::{anyhow, Error};
 use fantoccini::{Client, ClientBuilder};
 use serde_json::json;
 use sqlx::MySql;
@@ -20,6 +47,7 @@ use tokio::sync::Mutex;
 use tokio::sync::Semaphore;
 use pages_with_all_records::PagesWithAllRecords;
 use std::env;
+use std::path::Path;
 use sqlx::MySqlPool;
 use data::HouzzEntry;
 use rand::Rng;
@@ -28,10 +56,24 @@ use records_html::RecordsHtml;
 use records_data::RecordsData;
 use links_to_record_details::LinksToRecordDetails;
 use websites_html::WebsitesHtml;
-use extractor::Extractor;
+use extractor::{ExtractedField, Extractor, EXTRACTOR_VERSION};
 use invalid_websites::InvalidWebsites;
+use record_phones::RecordPhones;
+use email_verifier::{DnsMxLookup, EmailVerifier, MxLookup};
+use email_extractor::EmailExtractor;
+use record_emails::RecordEmails;
+use site_crawler::{CrawlConfig, CrawlFrontier};
+use website_pages::WebsitePages;
+use urls::UrlResolver;
+use record_social_profiles::RecordSocialProfiles;
+use records_profile::RecordsProfile;
+use field_provenance::FieldProvenance;
+use record_field_history::RecordFieldHistory;
+use recrawl::RecrawlConfig;
+use extraction_health::{FillRateTracker, HealthConfig};
 use std::convert::TryInto;
-use std::collections::HashSet;
+use storage::{MySqlStorage, Storage, BATCH_SIZE};
+use sqlite_storage::SqliteStorage;
 
 
 pub struct UrlData {
@@ -98,13 +140,29 @@ async fn generate_clients(headless: bool, number_of_clients: i32) -> Result<Vec<
     Ok(clients)
 }
 
+// REPLAY=storage answers every fetch from pages already in the database and
+// REPLAY=<WARC file or directory> from an archive; no browser is started.
+// Otherwise the stage gets live browsers, archived when WARC_DIR is set.
+async fn create_scheduler(storage: Arc<dyn Storage>, headless: bool, number_of_clients: i32) -> Result<scheduler::Scheduler, Error> {
+    let source: Arc<dyn replay::PageSource> = match env::var("REPLAY").ok().as_deref() {
+        None | Some("") => {
+            let clients = generate_clients(headless, number_of_clients).await?;
+            return Ok(scheduler::Scheduler::new(clients).with_archive(warc::WarcWriter::from_env()?));
+        },
+        Some("storage") => Arc::new(replay::StoredPages::new(storage)),
+        Some(path) => Arc::new(replay::ArchivePages::open(Path::new(path))?),
+    };
+
+    println!("Replaying stored pages instead of fetching");
+    Ok(scheduler::Scheduler::replay(source, number_of_clients as usize))
+}
+
 
 #[tokio::main]
 async fn main() -> Result<(), Error> {
     
     let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
     let data = data::generate_houzz_input_records();
-    let pool = MySqlPool::connect(&database_url).await?;
 
     println!("Data: {:?}", data);
 
@@ -114,20 +172,59 @@ async fn main() -> Result<(), Error> {
 
     println!("Houzz data: {:?}", houzz_data_record);
 
-    //run_get_all_pages_houzz(pool, houzz_data_record).await?;
-    //get_link_details_from_pages(&pool).await?;
-    //run_get_all_records_html_from_links(pool).await?;
-    //populate_records_data_from_records_html(&pool).await?;
+    // DATABASE_URL=sqlite://crawl.db runs the Houzz directory stages and email
+    // extraction against a local file, one stage per command; the other stages
+    // still need MySQL.
+    if let Some(path) = database_url.strip_prefix("sqlite://") {
+        let storage: Arc<dyn Storage> = Arc::new(SqliteStorage::open(path)?);
+        let args: Vec<String> = env::args().collect();
+
+        return match args.get(1).map(|command| command.as_str()) {
+            Some("pages") => run_get_all_pages_houzz(storage, houzz_data_record).await,
+            Some("links") => get_link_details_from_pages(storage.as_ref()).await,
+            Some("records-html") => run_get_all_records_html_from_links(storage).await,
+            Some("records-data") => populate_records_data_from_records_html(storage.as_ref()).await,
+            Some("emails") => update_record_data_email(storage.as_ref(), &mut EmailVerifier::new(DnsMxLookup::from_system_conf()?)).await,
+            Some("warc-import") => run_warc_import(storage.as_ref(), &args[2..]).await,
+            _ => Err(anyhow!("With a sqlite:// DATABASE_URL, run one of: pages, links, records-html, records-data, emails, warc-import")),
+        };
+    }
+
+    let pool = MySqlPool::connect(&database_url).await?;
+
+    migrations::migrate(&pool).await?;
+
+    let args: Vec<String> = env::args().collect();
+    match args.get(1).map(|command| command.as_str()) {
+        Some("migrate") => return migrations::run_migrate(&pool, &args[2..]).await,
+        Some("reextract") => return reextract::run_reextract(&pool, &args[2..]).await,
+        Some("health") => return extraction_health::run_health_report(&pool).await,
+        Some("blobs") => return page_blobs::run_blobs(&pool, &args[2..]).await,
+        Some("warc-import") => return run_warc_import(&MySqlStorage::new(pool.clone()), &args[2..]).await,
+        Some("recrawl") => return run_recrawl(&pool).await,
+        Some("changes") => return recrawl::run_changes_report(&pool, &args[2..]).await,
+        Some("canonical-domains") => return fix_canonical_domains(&pool).await,
+        _ => {}
+    }
+
+    //let storage: Arc<dyn Storage> = Arc::new(MySqlStorage::new(pool.clone()));
+    //run_get_all_pages_houzz(storage.clone(), houzz_data_record).await?;
+    //get_link_details_from_pages(storage.as_ref()).await?;
+    //run_get_all_records_html_from_links(storage.clone()).await?;
+    //populate_records_data_from_records_html(storage.as_ref()).await?;
+    //populate_records_profile_from_records_html(&pool).await?;
     //fix_records_websites(&pool).await?;
+    //update_records_data_company_names(&pool).await?;
+    //fix_websites_canonical_domains(&pool).await?;
     // Need to change get records houzz function to run the function below
-    //run_insert_website_html_from_records_data_websites(&pool).await?;
-    //update_contact_us_link_from_website_html(&pool).await?;
-    //run_update_contact_page_html_from_websites_html(pool).await?;
-    //update_record_data_email(&pool).await?;
+    //run_crawl_websites_from_records_data(&pool).await?;
+    //update_record_data_email(storage.as_ref(), &mut EmailVerifier::new(DnsMxLookup::from_system_conf()?)).await?;
+    //update_record_phones_from_websites_html(&pool).await?;
+    //update_record_social_profiles_from_websites_html(&pool).await?;
     Ok(())
 }
 
-pub async fn get_all_pages_houzz(semaphore: Arc<Semaphore>, scheduler_clone: Arc<Mutex<scheduler::Scheduler>>, houzz_data_record: HouzzEntry, pool: MySqlPool, urls: Vec<UrlData>){
+pub async fn get_all_pages_houzz(semaphore: Arc<Semaphore>, scheduler_clone: Arc<Mutex<scheduler::Scheduler>>, houzz_data_record: HouzzEntry, storage: Arc<dyn Storage>, urls: Vec<UrlData>){
     let (cancel_tx, _) = broadcast::channel::<(usize, ())>(1);
     let cancel_tx = Arc::new(cancel_tx);
 
@@ -137,7 +234,7 @@ pub async fn get_all_pages_houzz(semaphore: Arc<Semaphore>, scheduler_clone: Arc
         let semaphore = Arc::clone(&semaphore);
         let scheduler_clone = Arc::clone(&scheduler_clone);
         let houzz_data_record_clone = houzz_data_record.clone();
-        let pool = pool.clone();
+        let storage = Arc::clone(&storage);
         let mut cancel_rx = cancel_tx.subscribe(); // Create a new receiver
         let cancel_tx_clone = Arc::clone(&cancel_tx); // Clone the Arc<Sender>
         tokio::spawn(async move {
@@ -165,10 +262,11 @@ pub async fn get_all_pages_houzz(semaphore: Arc<Semaphore>, scheduler_clone: Arc
                 }
             };
 
-            let scrapper = scrapper::Scrapper::new(&client);
+            let archive = scheduler_clone.lock().await.archive.clone();
+            let scrapper = scrapper::Scrapper::new(&client).with_archive(archive);
 
             // Try to get the body.
-            let body = match scrapper.get_element_html(&url_data.url, ".pro-results").await {
+            let body = match scrapper.get_element_html(&url_data.url, warc::HOUZZ_LISTING_SELECTOR).await {
                 Ok(body) => body,
                 Err(e) => {
                     eprintln!("Error getting body: {:?}", e);
@@ -199,7 +297,7 @@ pub async fn get_all_pages_houzz(semaphore: Arc<Semaphore>, scheduler_clone: Arc
                 return;
             }
     
-            match PagesWithAllRecords::create_record(&page_with_all_records, &pool).await {
+            match storage.create_page(&page_with_all_records).await {
                 Ok(_) => {
                     println!("Inserted and sleeping for");
                 },
@@ -233,21 +331,22 @@ pub async fn get_all_pages_houzz(semaphore: Arc<Semaphore>, scheduler_clone: Arc
 
 }
 
-pub async fn get_all_records_html_from_links(semaphore: Arc<Semaphore>, scheduler_clone: Arc<Mutex<scheduler::Scheduler>>, pool: MySqlPool, urls: Vec<UrlDataLinks>) -> Result<(), Error>{
+pub async fn get_all_records_html_from_links(semaphore: Arc<Semaphore>, scheduler_clone: Arc<Mutex<scheduler::Scheduler>>, storage: Arc<dyn Storage>, urls: Vec<UrlDataLinks>) -> Result<(), Error>{
 
     let tasks: Vec<_> = urls
     .into_iter()
     .map(|url_data: UrlDataLinks| {
         let semaphore = Arc::clone(&semaphore);
         let scheduler_clone = Arc::clone(&scheduler_clone);
-        let pool = pool.clone();
+        let storage = Arc::clone(&storage);
         tokio::spawn(async move {
 
             // Acquire a permit from the semaphore.
             let _permit = semaphore.acquire().await;
 
 
-            let record_exists = match RecordsHtml::record_exists(&pool, url_data.link_to_record_details_id).await{
+            // An unvisited link that already has a page was requeued by recrawl.
+            let record_exists = match storage.records_html_exists(url_data.link_to_record_details_id).await{
                 Ok(exists) => exists,
                 Err(e) => {
                     eprintln!("Error checking if record exists: {:?}", e);
@@ -255,11 +354,6 @@ pub async fn get_all_records_html_from_links(semaphore: Arc<Semaphore>, schedule
                 }
             };
 
-            if record_exists {
-                println!("Record already exists, skipping");
-                return;
-            }
-
             let client;
             loop {
                 let mut locked_scheduler = scheduler_clone.lock().await;
@@ -277,10 +371,11 @@ pub async fn get_all_records_html_from_links(semaphore: Arc<Semaphore>, schedule
 
             println!("Client id: {:?}", client.session_id().await);
 
-            let scrapper = scrapper::Scrapper::new(&client);
+            let archive = scheduler_clone.lock().await.archive.clone();
+            let scrapper = scrapper::Scrapper::new(&client).with_archive(archive);
 
-            // Try to get the body.
-            let body = match scrapper.get_element_html(&url_data.url, "#business").await {
+            // Whole page: rating, reviews and badges are in the header, outside #business.
+            let body = match scrapper.get_body(&url_data.url).await {
                 Ok(body) => body,
                 Err(e) => {
                     eprintln!("Error getting body: {:?}", e);
@@ -307,29 +402,45 @@ pub async fn get_all_records_html_from_links(semaphore: Arc<Semaphore>, schedule
                 processed: 0,
             };
     
-            match RecordsHtml::create_record(&pool, &record_html).await {
-                Ok(_) => {
-                    println!("Inserted and sleeping for");
-                },
-                Err(e) => {
-                    // Log the error and continue with the next iteration
-                    eprintln!("Error inserting record: {:?}", e);
-                }
-            }
-    
-            let sleep_time = rand::thread_rng().gen_range(1..3);
-            tokio::time::sleep(tokio::time::Duration::from_secs(sleep_time)).await;
-
-
             let link_to_record_details = LinksToRecordDetails {
                 id: url_data.link_to_record_details_id,
                 pages_with_all_records_id: 0,
                 company: "".to_string(),
                 link: url_data.url.clone(),
                 visited: 1,
+                extractor_version: "".to_string(),
             };
 
-            LinksToRecordDetails::mark_record_as_visited(&pool, &link_to_record_details).await;
+            // The page and the visited flag are committed together; a failure leaves the link to retry.
+            if record_exists {
+                match storage.refresh_record_html(&record_html, &link_to_record_details).await {
+                    Ok(true) => {
+                        println!("Page changed since the last crawl");
+                    },
+                    Ok(false) => {
+                        println!("Page unchanged since the last crawl");
+                    },
+                    Err(e) => {
+                        eprintln!("Error refreshing record: {:?}", e);
+                    }
+                }
+            } else {
+                match storage.save_record_html(&record_html, &link_to_record_details).await {
+                    Ok(true) => {
+                        println!("Inserted and sleeping for");
+                    },
+                    Ok(false) => {
+                        println!("Record already exists, skipping");
+                    },
+                    Err(e) => {
+                        // Log the error and continue with the next iteration
+                        eprintln!("Error inserting record: {:?}", e);
+                    }
+                }
+            }
+    
+            let sleep_time = rand::thread_rng().gen_range(1..3);
+            tokio::time::sleep(tokio::time::Duration::from_secs(sleep_time)).await;
 
             {
                 let mut locked_scheduler = scheduler_clone.lock().await;
@@ -350,108 +461,397 @@ pub async fn get_all_records_html_from_links(semaphore: Arc<Semaphore>, schedule
     Ok(())
 }
 
-pub async fn get_link_details_from_pages(pool: &Pool<MySql> ) -> Result<(), Error> {
-    let pages_with_all_records = PagesWithAllRecords::get_all_unprocessed_records(&pool).await?;
+pub async fn get_link_details_from_pages(storage: &dyn Storage) -> Result<(), Error> {
+    let mut after_id = 0;
+
+    loop {
+        let pages_with_all_records = storage.get_unprocessed_pages(after_id, BATCH_SIZE).await?;
+        let last_id = match pages_with_all_records.last() {
+            Some(page) => page.id,
+            None => break,
+        };
+
+        println!("Pages with all records: {:?}", pages_with_all_records.len());
+        for page_with_all_records in pages_with_all_records {
+            let html = page_with_all_records.clone().html.unwrap();
+            let page_url = page_with_all_records.clone().query.unwrap_or_default();
+            let url_resolver = UrlResolver::new(&page_url, &html);
+            let extractor = Extractor::new(html);
+
+
+            let company_info_list = extractor.get_company_info_houzz();
+            let mut links = Vec::new();
+
+            for company_info in company_info_list {
+                let link = match url_resolver.as_ref().and_then(|resolver| resolver.resolve(&company_info.link)) {
+                    Some(link) => link,
+                    None => company_info.link,
+                };
+
+                let link = LinksToRecordDetails {
+                    id: 0,
+                    pages_with_all_records_id: page_with_all_records.id,
+                    company: company_names::clean_company_name(&company_info.company),
+                    link: link,
+                    visited: 0,
+                    extractor_version: EXTRACTOR_VERSION.to_string(),
+                };
+
+                println!("Link: {:?}", link);
+                links.push(link);
+            }
+
+            // Links and the processed flag are committed together; a failed page stays unprocessed.
+            match storage.save_page_links(&page_with_all_records, &links).await {
+                Ok(inserted) => {
+                    println!("Inserted {} of {} links", inserted, links.len());
+                },
+                Err(e) => {
+                    // Log the error and continue with the next iteration
+                    eprintln!("Error saving links for page {}: {:?}", page_with_all_records.id, e);
+                }
+            }
+        }
+
+        after_id = last_id;
+    }
+
+    Ok(())
+}
+
+pub async fn populate_records_data_from_records_html(storage: &dyn Storage) -> Result<(), Error>{
+    let mut after_id = 0;
+
+    // Fill rates are checked per batch, so a markup change stops the stage after one batch.
+    loop {
+        let records_html = storage.get_unprocessed_records_html(after_id, BATCH_SIZE).await?;
+        let last_id = match records_html.last() {
+            Some(record_html) => record_html.id,
+            None => break,
+        };
+
+        let mut fill_rate_tracker = FillRateTracker::new("records_data", &["company", "phone", "website"]);
+        let mut extracted = Vec::new();
+
+        for record_html in records_html {
+            let (link, company_field) = match get_record_company_name(storage, &record_html).await {
+                Ok(company) => company,
+                Err(e) => {
+                    eprintln!("Error getting company name: {:?}", e);
+                    continue;
+                }
+            };
 
-    println!("Pages with all records: {:?}", pages_with_all_records.len());
-    for page_with_all_records in pages_with_all_records {
-        let extractor = Extractor::new(page_with_all_records.clone().html.unwrap());
+            let extractor = Extractor::new(record_html.html.clone());
 
+            let record_data = extractor.get_company_details_houzz();
 
-        let company_info_list = extractor.get_company_info_houzz();
 
-        for company_info in company_info_list {
-            let link = LinksToRecordDetails {
+            let records_data = RecordsData {
                 id: 0,
-                pages_with_all_records_id: page_with_all_records.id,
-                company: company_info.company,
-                link: company_info.link,
-                visited: 0,
+                records_html_id: record_html.id,
+                email: "".to_string(),
+                phone: record_data.phone,
+                website: record_data.website,
+                contact_us_link: Some("".to_string()),
+                company: company_field.as_ref().map(|field| field.value.clone()).unwrap_or_default(),
+                company_verified: None,
+                extractor_version: EXTRACTOR_VERSION.to_string(),
             };
 
-            println!("Link: {:?}", link);
+            let mut fields = record_data.fields;
+            fields.extend(company_field);
 
-            match LinksToRecordDetails::create_record(&pool, &link).await {
-                Ok(_) => {
+            fill_rate_tracker.record(record_html.id, &[
+                ("company", records_data.company != ""),
+                ("phone", records_data.phone != ""),
+                ("website", records_data.website != ""),
+            ]);
+
+            extracted.push((link, records_data, fields));
+        }
+
+        // Stop before inserting a batch of empty values when the Houzz markup changed.
+        extraction_health::finish_batch(storage, &fill_rate_tracker, &HealthConfig::default()).await?;
+
+        for (link, records_data, fields) in extracted {
+            // A page fetched again by recrawl: the business is already known.
+            match storage.get_records_data_by_records_html_id(records_data.records_html_id).await {
+                Ok(Some(existing)) => {
+                    update_known_records_data(storage, &existing, &records_data, &link, &fields).await;
+                    continue;
+                },
+                Ok(None) => {},
+                Err(e) => {
+                    eprintln!("Error getting existing record: {:?}", e);
+                    continue;
+                }
+            }
+
+            // The same website or phone is caught by the unique keys on upsert.
+            match storage.upsert_records_data(&records_data).await {
+                Ok((records_data_id, true)) => {
                     println!("Inserted and sleeping for");
+                    storage.save_fields("records_data", records_data_id, "records_html", records_data.records_html_id, &link.link, &fields).await;
+                    save_field_versions(storage, records_data_id, &records_data).await;
+                },
+                Ok((_, false)) => {
+                    println!("Record already exists, skipping");
                 },
                 Err(e) => {
                     // Log the error and continue with the next iteration
                     eprintln!("Error inserting record: {:?}", e);
                 }
             }
+
         }
 
-        PagesWithAllRecords::mark_record_as_processed(&page_with_all_records, &pool).await?;
+        after_id = last_id;
     }
 
     Ok(())
+
 }
 
-pub async fn populate_records_data_from_records_html(pool: &MySqlPool) -> Result<(), Error>{
-    let records_html = RecordsHtml::get_all_unprocessed_records(&pool).await?;
+// Applies what a re-crawled page says to the stored business and versions the
+// fields. An empty value is more often a failed extraction than a removed field,
+// so it never replaces a stored one.
+async fn update_known_records_data(storage: &dyn Storage, existing: &RecordsData, extracted: &RecordsData, link: &LinksToRecordDetails, fields: &[ExtractedField]) {
+    // Records from before the history existed get their stored values as the first version.
+    save_field_versions(storage, existing.id, existing).await;
+
+    let updated = RecordsData {
+        phone: if extracted.phone != "" { extracted.phone.clone() } else { existing.phone.clone() },
+        website: if extracted.website != "" { extracted.website.clone() } else { existing.website.clone() },
+        extractor_version: EXTRACTOR_VERSION.to_string(),
+        ..existing.clone()
+    };
 
-    for record_html in records_html {
-        let extractor = Extractor::new(record_html.html);
+    if updated.phone == existing.phone && updated.website == existing.website {
+        println!("Record unchanged since the last crawl");
+        if let Err(e) = storage.mark_records_html_processed(extracted.records_html_id).await {
+            eprintln!("Error marking page processed: {:?}", e);
+        }
+        return;
+    }
 
-        let record_data = extractor.get_company_details_houzz();
+    match storage.update_records_data_fields(&updated).await {
+        Ok(_) => {
+            println!("Record changed since the last crawl");
+            let changed_fields: Vec<ExtractedField> = fields.iter().filter(|field| field.field == "phone" || field.field == "website").cloned().collect();
+            storage.save_fields("records_data", existing.id, "records_html", existing.records_html_id, &link.link, &changed_fields).await;
+            save_field_versions(storage, existing.id, &updated).await;
+        },
+        Err(e) => {
+            eprintln!("Error updating record: {:?}", e);
+        }
+    }
+}
 
+// Logs and skips failed writes, like save_fields.
+async fn save_field_versions(storage: &dyn Storage, records_data_id: i32, records_data: &RecordsData) {
+    for (field, value) in [("phone", &records_data.phone), ("website", &records_data.website)] {
+        let version = RecordFieldHistory::new(records_data_id, field, value, "records_html", records_data.records_html_id);
 
-        let records_data = RecordsData {
-            id: 0,
-            records_html_id: record_html.id,
-            email: "".to_string(),
-            phone: record_data.phone,
-            website: record_data.website,
-            contact_us_link: Some("".to_string()),
+        if let Err(e) = storage.save_field_version(&version).await {
+            eprintln!("Error saving {} version: {:?}", field, e);
+        }
+    }
+}
+
+// Directory listing name, or the detail page's JSON-LD name when the listing had none.
+async fn get_record_company_name(storage: &dyn Storage, record_html: &RecordsHtml) -> Result<(LinksToRecordDetails, Option<ExtractedField>), Error> {
+    let link = storage.get_link_by_id(record_html.link_to_record_details_id).await?;
+    let company = company_names::clean_company_name(&link.company);
+
+    if company != "" {
+        let company_field = ExtractedField::new("company", &company, "directory.company");
+        return Ok((link, Some(company_field)));
+    }
+
+    let company_field = company_names::find_site_names(&record_html.html)
+        .into_iter()
+        .find(|site_name| site_name.source_type == "json_ld")
+        .map(|site_name| ExtractedField::new("company", &site_name.name, "houzz.details.company_json_ld"));
+
+    Ok((link, company_field))
+}
+
+// Fills company on older records, then checks it against the name the business website gives itself.
+pub async fn update_records_data_company_names(pool: &MySqlPool) -> Result<(), Error> {
+    let storage = MySqlStorage::new(pool.clone());
+    let records_data = RecordsData::get_all_records(&pool).await?;
+
+    for mut record_data in records_data {
+        if record_data.company != "" {
+            continue;
+        }
+
+        let record_html = match RecordsHtml::get_record_by_id(&pool, record_data.records_html_id).await {
+            Ok(record_html) => record_html,
+            Err(e) => {
+                eprintln!("Error getting records html: {:?}", e);
+                continue;
+            }
         };
 
+        let (link, company_field) = match get_record_company_name(&storage, &record_html).await {
+            Ok(company) => company,
+            Err(e) => {
+                eprintln!("Error getting company name: {:?}", e);
+                continue;
+            }
+        };
 
-        if records_data.website != ""{
-            let record_exists_by_website = match RecordsData::record_exists_by_website(&pool, &records_data.website).await{
-                Ok(exists) => exists,
+        let company_field = match company_field {
+            Some(company_field) => company_field,
+            None => continue,
+        };
+        record_data.company = company_field.value.clone();
+
+        match RecordsData::update_company(&pool, &record_data).await {
+            Ok(_) => {
+                FieldProvenance::save_fields(&pool, "records_data", record_data.id, "records_html", record_html.id, &link.link, &[company_field]).await;
+            },
+            Err(e) => {
+                eprintln!("Error updating company: {:?}", e);
+            }
+        }
+    }
+
+    let mut after_id = 0;
+
+    loop {
+        let websites_html = WebsitesHtml::get_websites_after(pool, after_id, BATCH_SIZE).await?;
+        let last_id = match websites_html.last() {
+            Some(website_html) => website_html.id,
+            None => break,
+        };
+
+        for website_html in websites_html {
+            let mut record_data = match RecordsData::get_record_data_by_records_data_id(&pool, website_html.records_data_id).await {
+                Ok(record_data) => record_data,
                 Err(e) => {
-                    eprintln!("Error checking if record exists by website: {:?}", e);
+                    eprintln!("Error getting record data: {:?}", e);
                     continue;
                 }
             };
-    
-            if record_exists_by_website {
-                println!("Record already exists, skipping");
+
+            let homepage_html = match WebsitePages::get_pages_for_website(&pool, &website_html).await {
+                Ok(pages) => pages.into_iter().next().map(|(_, html)| html).unwrap_or_default(),
+                Err(e) => {
+                    eprintln!("Error getting website pages: {:?}", e);
+                    continue;
+                }
+            };
+
+            let site_names = company_names::find_site_names(&homepage_html);
+
+            if site_names.is_empty() || record_data.company == "" {
                 continue;
             }
+
+            let matched = site_names
+                .iter()
+                .any(|site_name| company_names::company_name_matches(&record_data.company, &site_name.name));
+
+            if !matched {
+                println!("Company name mismatch for {}: {:?} vs {:?}", website_html.website, record_data.company, site_names);
+            }
+
+            record_data.company_verified = Some(if matched { 1 } else { 0 });
+
+            if let Err(e) = RecordsData::update_company_verified(&pool, &record_data).await {
+                eprintln!("Error updating company verification: {:?}", e);
+            }
         }
 
-        if records_data.phone != ""{
-            let record_exists_by_phone = match RecordsData::record_exists_by_phone(&pool, &records_data.phone).await{
+        after_id = last_id;
+    }
+
+    Ok(())
+}
+
+pub async fn populate_records_profile_from_records_html(pool: &MySqlPool) -> Result<(), Error>{
+    let mut after_id = 0;
+
+    loop {
+        let records_html = RecordsHtml::get_records_after(&pool, after_id, BATCH_SIZE).await?;
+        let last_id = match records_html.last() {
+            Some(record_html) => record_html.id,
+            None => break,
+        };
+
+        let mut fill_rate_tracker = FillRateTracker::new("records_profile", &["rating", "review_count", "badges", "license_number", "years_in_business", "service_areas"]);
+        let mut extracted = Vec::new();
+
+        for record_html in records_html {
+            let record_exists = match RecordsProfile::record_exists(&pool, record_html.id).await {
                 Ok(exists) => exists,
                 Err(e) => {
-                    eprintln!("Error checking if record exists by phone: {:?}", e);
+                    eprintln!("Error checking if profile exists: {:?}", e);
                     continue;
                 }
             };
-    
-            if record_exists_by_phone {
-                println!("Record already exists, skipping");
+
+            if record_exists {
                 continue;
             }
+
+            let page_url = match LinksToRecordDetails::get_record_by_id(&pool, record_html.link_to_record_details_id).await {
+                Ok(link) => link.link,
+                Err(e) => {
+                    eprintln!("Error getting link: {:?}", e);
+                    continue;
+                }
+            };
+
+            let extractor = Extractor::new(record_html.html);
+            let company_profile = extractor.get_company_profile_houzz();
+
+            let records_profile = RecordsProfile {
+                id: 0,
+                records_html_id: record_html.id,
+                rating: company_profile.rating,
+                review_count: company_profile.review_count,
+                badges: company_profile.badges.join(", "),
+                license_number: company_profile.license_number,
+                years_in_business: company_profile.years_in_business,
+                service_areas: company_profile.service_areas,
+                extractor_version: EXTRACTOR_VERSION.to_string(),
+            };
+
+            fill_rate_tracker.record(record_html.id, &[
+                ("rating", records_profile.rating.is_some()),
+                ("review_count", records_profile.review_count.is_some()),
+                ("badges", records_profile.badges != ""),
+                ("license_number", records_profile.license_number != ""),
+                ("years_in_business", records_profile.years_in_business.is_some()),
+                ("service_areas", records_profile.service_areas != ""),
+            ]);
+
+            extracted.push((page_url, records_profile, company_profile.fields));
         }
 
+        extraction_health::finish_batch(&MySqlStorage::new(pool.clone()), &fill_rate_tracker, &HealthConfig::default()).await?;
 
-        match RecordsData::create_record(&pool, &records_data).await {
-            Ok(_) => {
-                println!("Inserted and sleeping for");
-            },
-            Err(e) => {
-                // Log the error and continue with the next iteration
-                eprintln!("Error inserting record: {:?}", e);
+        for (page_url, records_profile, fields) in extracted {
+            match RecordsProfile::create_record(&pool, &records_profile).await {
+                Ok(records_profile_id) => {
+                    println!("Inserted profile");
+                    FieldProvenance::save_fields(&pool, "records_profile", records_profile_id, "records_html", records_profile.records_html_id, &page_url, &fields).await;
+                },
+                Err(e) => {
+                    // Log the error and continue with the next iteration
+                    eprintln!("Error inserting profile: {:?}", e);
+                }
             }
         }
 
+        after_id = last_id;
     }
 
     Ok(())
-
 }
 
 pub async fn fix_records_websites(pool: &MySqlPool) -> Result<(), Error>{
@@ -459,15 +859,18 @@ pub async fn fix_records_websites(pool: &MySqlPool) -> Result<(), Error>{
 
     for record_data in records_data {
         let mut records_data = record_data.clone();
-        let mut extractor = Extractor::new(record_data.website.clone());
 
         if records_data.website == ""{
             continue;
         }
 
-        if !records_data.website.contains("http"){
-            records_data.website = format!("https://{}", records_data.website);
-        }
+        records_data.website = match urls::canonicalize_url(&records_data.website) {
+            Some(website) => website,
+            None => {
+                println!("Website is not a valid url: {}", records_data.website);
+                continue;
+            }
+        };
 
 
         match RecordsData::update_website(&pool, &records_data).await {
@@ -485,7 +888,65 @@ pub async fn fix_records_websites(pool: &MySqlPool) -> Result<(), Error>{
     Ok(())
 }
 
-pub async fn insert_website_html_from_records_data_websites(semaphore: Arc<Semaphore>, scheduler_clone: Arc<Mutex<scheduler::Scheduler>>, pool: &MySqlPool, urls: Vec<UrlDataRecord>) -> Result<(), Error>{
+// Backfills canonical_domain for rows stored before the column existed.
+pub async fn fix_websites_canonical_domains(pool: &MySqlPool) -> Result<(), Error>{
+    let mut after_id = 0;
+
+    loop {
+        let websites_html = WebsitesHtml::get_websites_after(&pool, after_id, BATCH_SIZE).await?;
+        let last_id = match websites_html.last() {
+            Some(website_html) => website_html.id,
+            None => break,
+        };
+
+        for website_html in websites_html {
+            if let Err(e) = WebsitesHtml::update_canonical_domain(&pool, &website_html).await {
+                eprintln!("Error updating website: {:?}", e);
+            }
+        }
+
+        after_id = last_id;
+    }
+
+    let invalid_websites = InvalidWebsites::get_all_records(&pool).await?;
+
+    for invalid_website in invalid_websites {
+        if let Err(e) = InvalidWebsites::update_canonical_domain(&pool, &invalid_website).await {
+            eprintln!("Error updating invalid website: {:?}", e);
+        }
+    }
+
+    Ok(())
+}
+
+// Recomputes every stored canonical_domain from its website. Where records share
+// one, the oldest keeps it and the others are listed.
+pub async fn fix_canonical_domains(pool: &MySqlPool) -> Result<(), Error>{
+    let mut after_id = 0;
+    let mut unkeyed = 0;
+
+    loop {
+        let records_data = RecordsData::get_records_after(&pool, after_id, BATCH_SIZE).await?;
+        let last_id = match records_data.last() {
+            Some(record_data) => record_data.id,
+            None => break,
+        };
+
+        for record_data in records_data {
+            if record_data.website != "" && RecordsData::update_canonical_domain(&pool, &record_data).await?.is_none() {
+                unkeyed += 1;
+            }
+        }
+
+        after_id = last_id;
+    }
+
+    println!("{} records_data rows have a website without a canonical_domain of their own", unkeyed);
+
+    fix_websites_canonical_domains(&pool).await
+}
+
+pub async fn insert_website_html_from_records_data_websites(semaphore: Arc<Semaphore>, scheduler_clone: Arc<Mutex<scheduler::Scheduler>>, pool: &MySqlPool, urls: Vec<UrlDataRecord>, recrawl_config: RecrawlConfig) -> Result<(), Error>{
 
     let tasks: Vec<_> = urls
     .into_iter()
@@ -493,6 +954,7 @@ pub async fn insert_website_html_from_records_data_websites(semaphore: Arc<Semap
         let semaphore = Arc::clone(&semaphore);
         let scheduler_clone = Arc::clone(&scheduler_clone);
         let pool = pool.clone();
+        let websites_max_age_days = recrawl_config.websites_max_age_days;
         tokio::spawn(async move {
 
             // Acquire a permit from the semaphore.
@@ -518,10 +980,22 @@ pub async fn insert_website_html_from_records_data_websites(semaphore: Arc<Semap
                 }
             };
 
-            if record_exists {
-                println!("Record already exists, skipping");
-                return;
-            }
+            // Known websites are fetched again once their main page is older than the configured age.
+            let stale_website_id = if record_exists {
+                match WebsitesHtml::get_stale_website_id(&pool, &url_data.url, websites_max_age_days).await {
+                    Ok(Some(website_id)) => Some(website_id),
+                    Ok(None) => {
+                        println!("Record already exists, skipping");
+                        return;
+                    },
+                    Err(e) => {
+                        eprintln!("Error checking if record is stale: {:?}", e);
+                        return;
+                    }
+                }
+            } else {
+                None
+            };
 
             // Try to get a client.
             let client;
@@ -539,7 +1013,8 @@ pub async fn insert_website_html_from_records_data_websites(semaphore: Arc<Semap
                 }
             }
 
-            let scrapper = scrapper::Scrapper::new(&client);
+            let archive = scheduler_clone.lock().await.archive.clone();
+            let scrapper = scrapper::Scrapper::new(&client).with_archive(archive);
             let mut body: String = "".to_string(); 
 
             match scrapper.get_body(&url_data.url).await {
@@ -619,14 +1094,20 @@ pub async fn insert_website_html_from_records_data_websites(semaphore: Arc<Semap
                 return;
             }
             let record_html = WebsitesHtml {
-                id: 0,
+                id: stale_website_id.unwrap_or(0),
                 website: url_data.url.clone(),
                 main_page_html: body.clone().to_string(),
                 contact_page_html: "".to_string(),
                 records_data_id: url_data.record_id,
+                final_url: None,
+            };
+
+            let result = match stale_website_id {
+                Some(_) => WebsitesHtml::update_main_page_html(&pool, &record_html).await,
+                None => WebsitesHtml::create_record(&pool, &record_html).await.map(|_| ()),
             };
 
-            match WebsitesHtml::create_record(&pool, &record_html).await {
+            match result {
                 Ok(_) => {
                     println!("Inserted and sleeping for");
                 },
@@ -660,84 +1141,263 @@ pub async fn insert_website_html_from_records_data_websites(semaphore: Arc<Semap
     Ok(())
 }
 
-pub async fn update_contact_us_link_from_website_html(pool: &MySqlPool) -> Result<(), Error>{
-    let websites_html = WebsitesHtml::get_all_websites(&pool).await?;
-
-    for website_html in websites_html {
-        let mut website_html = website_html.clone();
-
-        if website_html.contact_page_html != ""{
-            continue;
-        }
-
-        let mut extractor = Extractor::new(website_html.main_page_html.clone());
-
-        let contact_us_link = match extractor.find_contact_us_link(){
-            Some(link) => link,
-            None => "".to_string(),
-        };
-
-        if contact_us_link == ""{
-            continue;
-        }
-
-        let mut records_data = RecordsData::get_record_data_by_records_data_id(&pool, website_html.records_data_id).await?;
-        records_data.contact_us_link = Some(contact_us_link.clone());
-
-
-        let ( domain, authority ) = match parse_url(&website_html.website){
-            Some((sld, tld)) => (sld, tld),
-            None => ("", ""),
-        };
+pub async fn crawl_websites_from_records_data(semaphore: Arc<Semaphore>, scheduler_clone: Arc<Mutex<scheduler::Scheduler>>, pool: &MySqlPool, urls: Vec<UrlDataRecord>, crawl_config: CrawlConfig, recrawl_config: RecrawlConfig) -> Result<(), Error>{
 
-        if domain == "" || authority == ""{
-            continue;
-        }
+    let tasks: Vec<_> = urls
+    .into_iter()
+    .map(|url_data: UrlDataRecord| {
+        let semaphore = Arc::clone(&semaphore);
+        let scheduler_clone = Arc::clone(&scheduler_clone);
+        let pool = pool.clone();
+        let crawl_config = crawl_config.clone();
+        let websites_max_age_days = recrawl_config.websites_max_age_days;
+        tokio::spawn(async move {
 
-        let mut absolute_url = "".to_string();
+            // Acquire a permit from the semaphore.
+            let _permit = semaphore.acquire().await;
 
-        if contact_us_link.contains(domain){
-            absolute_url = contact_us_link;
-        }else{
-            if contact_us_link.starts_with("/") {
-                absolute_url = format!("{}://{}.{}{}", "https", domain, authority, contact_us_link);
-            }else{
-                absolute_url = format!("{}://{}.{}/{}", "https", domain, authority, contact_us_link.replace("/", ""));
+            match InvalidWebsites::record_exists(&pool, &url_data.url).await{
+                Ok(exists) => {
+                    if exists {
+                        println!("Website is invalid, skipping");
+                        return;
+                    }
+                },
+                Err(e) => {
+                    eprintln!("Error checking if record exists: {:?}", e);
+                    return;
+                }
             }
-        }
 
-        records_data.contact_us_link = Some(absolute_url.clone());
-        
-        match RecordsData::set_contact_us_link(&pool, &records_data).await {
-            Ok(_) => {
-                println!("Updated and sleeping for");
-            },
-            Err(e) => {
-                // Log the error and continue with the next iteration
-                eprintln!("Error updating record: {:?}", e);
+            let record_exists = match WebsitesHtml::website_exists(&pool, &url_data.url).await{
+                Ok(exists) => exists,
+                Err(e) => {
+                    eprintln!("Error checking if record exists: {:?}", e);
+                    return;
+                }
+            };
+
+            // Known websites are crawled again once their pages are older than the configured age.
+            let stale_website_id = if record_exists {
+                match WebsitesHtml::get_stale_website_id(&pool, &url_data.url, websites_max_age_days).await {
+                    Ok(Some(website_id)) => Some(website_id),
+                    Ok(None) => {
+                        println!("Record already exists, skipping");
+                        return;
+                    },
+                    Err(e) => {
+                        eprintln!("Error checking if record is stale: {:?}", e);
+                        return;
+                    }
+                }
+            } else {
+                None
+            };
+
+            let mut frontier = match CrawlFrontier::new(&url_data.url, crawl_config) {
+                Some(frontier) => frontier,
+                None => {
+                    println!("Website is not a valid url: {}", url_data.url);
+                    return;
+                }
+            };
+
+            // Try to get a client.
+            let client;
+            loop {
+                let mut locked_scheduler = scheduler_clone.lock().await;
+                match locked_scheduler.get_client().await {
+                    Ok(available_client) => {
+                        client = available_client.clone();
+                        break;
+                    },
+                    Err(_) => {
+                        println!("No available clients, retrying in 5 seconds...");
+                        tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
+                    }
+                }
             }
-        }
 
+            let archive = scheduler_clone.lock().await.archive.clone();
+            let scrapper = scrapper::Scrapper::new(&client).with_archive(archive);
+            let mut pages: Vec<WebsitePages> = Vec::new();
+
+            while let Some(candidate) = frontier.next() {
+                let body = match scrapper.get_body(&candidate.url).await {
+                    Ok(body) => body,
+                    Err(e) => {
+                        eprintln!("Error getting body: {:?}", e);
+                        println!("Website: {}", candidate.url);
+                        let error_string = format!("{:?}", e);
+
+                        // Only the homepage decides whether the whole website is invalid.
+                        if candidate.depth == 0 && (error_string.contains("ERR_NAME_NOT_RESOLVED") || error_string.contains("ERR_ADDRESS_UNREACHABLE")) {
+                            let invalid_website = InvalidWebsites {
+                                website: url_data.url.clone(),
+                            };
+
+                            if let Err(e) = InvalidWebsites::create_record(&pool, &invalid_website).await {
+                                eprintln!("Error inserting invalid website: {:?}", e);
+                            }
+                            break;
+                        } else if error_string.contains("ERR_SSL_VERSION_OR_CIPHER_MISMATCH") || error_string.contains("ERR_SSL_PROTOCOL_ERROR") {
+                            match scrapper.get_body(&candidate.url.replace("https", "http")).await {
+                                Ok(body) => body,
+                                Err(_) => continue,
+                            }
+                        } else {
+                            continue;
+                        }
+                    }
+                };
+
+                if body == "" {
+                    println!("Body empty.");
+                    continue;
+                }
+
+                // Relative links are resolved against where the browser landed, not where we asked to go.
+                let final_url = match scrapper.current_url().await {
+                    Ok(url) => urls::canonicalize_url(&url).unwrap_or(candidate.url.clone()),
+                    Err(_) => candidate.url.clone(),
+                };
+
+                frontier.add_links(&final_url, &body, candidate.depth);
+
+                pages.push(WebsitePages {
+                    id: 0,
+                    websites_html_id: 0,
+                    url: final_url,
+                    depth: candidate.depth as i32,
+                    score: candidate.score,
+                    html: body,
+                });
+
+                let sleep_time = rand::thread_rng().gen_range(1..3);
+                tokio::time::sleep(tokio::time::Duration::from_secs(sleep_time)).await;
+            }
+
+            {
+                let mut locked_scheduler = scheduler_clone.lock().await;
+                if let Err(e) = locked_scheduler.replace_client(&client, false).await {
+                    println!("Failed to release client: {}", e);
+                }
+            }
+
+            if pages.is_empty() {
+                println!("No pages crawled for {}", url_data.url);
+                return;
+            }
+
+            // The start page doubles as main_page_html, which the contact link stage reads.
+            let website_html = WebsitesHtml {
+                id: stale_website_id.unwrap_or(0),
+                website: url_data.url.clone(),
+                main_page_html: pages[0].html.clone(),
+                contact_page_html: "".to_string(),
+                records_data_id: url_data.record_id,
+                final_url: Some(pages[0].url.clone()),
+            };
+
+            if stale_website_id.is_some() {
+                if let Err(e) = WebsitesHtml::replace_crawled_pages(&pool, &website_html, &pages).await {
+                    eprintln!("Error replacing website pages: {:?}", e);
+                }
+                return;
+            }
+
+            // The homepage may redirect to a domain another record already crawled.
+            if website_html.canonical_domain() != urls::canonical_domain(&url_data.url) {
+                match WebsitesHtml::website_exists(&pool, &pages[0].url).await {
+                    Ok(true) => {
+                        println!("Website redirects to an existing record, skipping");
+                        return;
+                    },
+                    Ok(false) => {},
+                    Err(e) => {
+                        eprintln!("Error checking if record exists: {:?}", e);
+                        return;
+                    }
+                }
+            }
+
+            if let Err(e) = WebsitesHtml::create_crawled_website(&pool, &website_html, &pages).await {
+                eprintln!("Error inserting website: {:?}", e);
+            }
+
+        })
+    })
+    .collect();
+
+
+    for task in tasks {
+        task.await.unwrap();
     }
 
     Ok(())
 }
 
-fn parse_url(url: &str) -> Option<(&str, &str)> {
-    let without_protocol = url.trim_start_matches("http://").trim_start_matches("https://");
-    let domain_end = without_protocol.find('/').unwrap_or_else(|| without_protocol.len());
-    let domain = &without_protocol[..domain_end];
-    let parts: Vec<&str> = domain.split('.').collect();
-    let len = parts.len();
+pub async fn update_contact_us_link_from_website_html(pool: &MySqlPool) -> Result<(), Error>{
+    let mut after_id = 0;
+
+    loop {
+        let websites_html = WebsitesHtml::get_websites_after(&pool, after_id, BATCH_SIZE).await?;
+        let last_id = match websites_html.last() {
+            Some(website_html) => website_html.id,
+            None => break,
+        };
+
+        for website_html in websites_html {
+            let mut website_html = website_html.clone();
+
+            if website_html.contact_page_html != ""{
+                continue;
+            }
+
+            let mut extractor = Extractor::new(website_html.main_page_html.clone());
+
+            let contact_us_link = match extractor.find_contact_us_link(){
+                Some(link) => link,
+                None => "".to_string(),
+            };
+
+            if contact_us_link == ""{
+                continue;
+            }
+
+            let mut records_data = RecordsData::get_record_data_by_records_data_id(&pool, website_html.records_data_id).await?;
+            records_data.contact_us_link = Some(contact_us_link.clone());
 
-    if len < 2 {
-        None
-    } else {
-        let sld = parts[len - 2];
-        let tld = parts[len - 1];
 
-        Some((sld, tld))
+            // Relative links resolve against the page as fetched, after any redirects.
+            let website = match urls::canonicalize_url(website_html.final_url.as_deref().unwrap_or(&website_html.website)) {
+                Some(website) => website,
+                None => continue,
+            };
+
+            let absolute_url = match UrlResolver::new(&website, &website_html.main_page_html).and_then(|resolver| resolver.resolve(&contact_us_link)) {
+                Some(url) => url,
+                None => continue,
+            };
+
+            records_data.contact_us_link = Some(absolute_url.clone());
+        
+            match RecordsData::set_contact_us_link(&pool, &records_data).await {
+                Ok(_) => {
+                    println!("Updated and sleeping for");
+                },
+                Err(e) => {
+                    // Log the error and continue with the next iteration
+                    eprintln!("Error updating record: {:?}", e);
+                }
+            }
+
+        }
+
+        after_id = last_id;
     }
+
+    Ok(())
 }
 
 pub async fn update_contact_page_html_from_websites_html(semaphore: Arc<Semaphore>, scheduler_clone: Arc<Mutex<scheduler::Scheduler>>, pool: MySqlPool, urls: Vec<WebsitesHtmlData>) -> Result<(), Error>{
@@ -784,7 +1444,8 @@ pub async fn update_contact_page_html_from_websites_html(semaphore: Arc<Semaphor
 
             println!("Client id: {:?}", client.session_id().await);
 
-            let scrapper = scrapper::Scrapper::new(&client);
+            let archive = scheduler_clone.lock().await.archive.clone();
+            let scrapper = scrapper::Scrapper::new(&client).with_archive(archive);
 
             let mut body: String = "".to_string();
 
@@ -871,6 +1532,7 @@ pub async fn update_contact_page_html_from_websites_html(semaphore: Arc<Semaphor
                 main_page_html: "".to_string(),
                 contact_page_html: body.clone().to_string(),
                 records_data_id: url_data.record_id,
+                final_url: None,
             };
 
             match WebsitesHtml::update_contact_page_html(&pool, &website).await {
@@ -908,51 +1570,235 @@ pub async fn update_contact_page_html_from_websites_html(semaphore: Arc<Semaphor
     Ok(())
 }
 
-pub async fn update_record_data_email(pool: &MySqlPool) -> Result<(), Error> {
-    let websites_html = WebsitesHtml::get_all_websites(pool).await?;
+pub async fn update_record_data_email<M: MxLookup>(storage: &dyn Storage, email_verifier: &mut EmailVerifier<M>) -> Result<(), Error> {
+    let mut after_id = 0;
 
-    for website_html in websites_html {
-        let mut extractor = Extractor::new(website_html.main_page_html);
-        let emails = extractor.find_emails_by_regex();
+    loop {
+        let websites_html = storage.get_websites(after_id, BATCH_SIZE).await?;
+        let last_id = match websites_html.last() {
+            Some(website_html) => website_html.id,
+            None => break,
+        };
 
-        extractor.set_html(website_html.contact_page_html);
-        let emails_from_contact_page = extractor.find_emails_by_regex();
+        for website_html in websites_html {
+            let pages = match storage.get_pages_for_website(&website_html).await {
+                Ok(pages) => pages,
+                Err(e) => {
+                    eprintln!("Error getting website pages: {:?}", e);
+                    continue;
+                }
+            };
 
-        // Combine emails from both pages and remove duplicates
-        let all_emails_combined = format!("{},{}", emails, emails_from_contact_page);
-        let mut all_emails: Vec<String> = all_emails_combined.split(',')
-                                                             .map(String::from)
-                                                             .collect();
+            // Every address the website gives now, stored or not, for the field history.
+            let mut found_emails = std::collections::BTreeSet::new();
 
-        // Remove duplicates while maintaining order
-        let mut seen = HashSet::new();
-        all_emails.retain(|email| seen.insert(email.clone()));
+            for (source_page, html) in pages {
+                if html == "" {
+                    continue;
+                }
 
-        // Join the emails back into a single string
-        let unique_emails = all_emails.join(", ");
+                let email_extractor = EmailExtractor::new(html);
 
-        let record_data = RecordsData {
-            id: website_html.records_data_id,
-            records_html_id: 0,
-            email: unique_emails,
-            phone: "".to_string(),
-            website: "".to_string(),
-            contact_us_link: Some("".to_string()),
+                for found_email in email_extractor.find_emails() {
+                    let verification = email_verifier.verify(&found_email.email, &website_html.website).await;
+                    println!("Email {} confidence {:.2}", verification.email, verification.confidence);
+
+                    // Drop invalid and disposable addresses
+                    if !verification.syntax_valid || verification.disposable {
+                        continue;
+                    }
+                    found_emails.insert(verification.email.clone());
+
+                    let record_exists = match storage.record_email_exists(website_html.records_data_id, &verification.email).await {
+                        Ok(exists) => exists,
+                        Err(e) => {
+                            eprintln!("Error checking if email exists: {:?}", e);
+                            continue;
+                        }
+                    };
+
+                    if record_exists {
+                        continue;
+                    }
+
+                    let email_field = ExtractedField {
+                        field: "email".to_string(),
+                        value: verification.email.clone(),
+                        rule_id: format!("email.{}", found_email.source_type),
+                        confidence: verification.confidence,
+                    };
+
+                    let record_email = RecordEmails {
+                        id: 0,
+                        record_id: website_html.records_data_id,
+                        email: verification.email,
+                        source_page: source_page.clone(),
+                        source_type: found_email.source_type,
+                        first_seen: None,
+                        confidence: verification.confidence,
+                    };
+
+                    match storage.create_record_email(&record_email).await {
+                        Ok(record_email_id) => {
+                            println!("Inserted email");
+                            storage.save_fields("record_emails", record_email_id, "websites_html", website_html.id, &source_page, &[email_field]).await;
+                        },
+                        Err(e) => {
+                            // Log the error and continue with the next iteration
+                            eprintln!("Error inserting email: {:?}", e);
+                        }
+                    }
+                }
+            }
+
+            let emails = found_emails.into_iter().collect::<Vec<_>>().join(", ");
+            let version = RecordFieldHistory::new(website_html.records_data_id, "emails", &emails, "websites_html", website_html.id);
+            if let Err(e) = storage.save_field_version(&version).await {
+                eprintln!("Error saving emails version: {:?}", e);
+            }
+
+            println!("Record updated");
+            std::thread::sleep(std::time::Duration::from_millis(100));
+        }
+
+        after_id = last_id;
+    }
+
+    storage.refresh_email_view().await?;
+
+    Ok(())
+}
+
+pub async fn update_record_phones_from_websites_html(pool: &MySqlPool) -> Result<(), Error> {
+    let mut after_id = 0;
+
+    loop {
+        let websites_html = WebsitesHtml::get_websites_after(pool, after_id, BATCH_SIZE).await?;
+        let last_id = match websites_html.last() {
+            Some(website_html) => website_html.id,
+            None => break,
+        };
+
+        for website_html in websites_html {
+            let pages = match WebsitePages::get_pages_for_website(&pool, &website_html).await {
+                Ok(pages) => pages,
+                Err(e) => {
+                    eprintln!("Error getting website pages: {:?}", e);
+                    continue;
+                }
+            };
+
+            for (source_page, html) in pages {
+                if html == "" {
+                    continue;
+                }
+
+                let extractor = Extractor::new(html);
+
+                for found_phone in extractor.find_phones() {
+                    let record_exists = match RecordPhones::record_exists(&pool, website_html.records_data_id, &found_phone.phone).await {
+                        Ok(exists) => exists,
+                        Err(e) => {
+                            eprintln!("Error checking if phone exists: {:?}", e);
+                            continue;
+                        }
+                    };
+
+                    if record_exists {
+                        continue;
+                    }
+
+                    let phone_field = ExtractedField::new("phone", &found_phone.phone, &format!("phone.{}", found_phone.source_type));
+
+                    let record_phone = RecordPhones {
+                        id: 0,
+                        records_data_id: website_html.records_data_id,
+                        phone: found_phone.phone,
+                        source_page: source_page.clone(),
+                        source_type: found_phone.source_type,
+                        extractor_version: EXTRACTOR_VERSION.to_string(),
+                    };
+
+                    match RecordPhones::create_record(&pool, &record_phone).await {
+                        Ok(record_phone_id) => {
+                            println!("Inserted phone");
+                            FieldProvenance::save_fields(&pool, "record_phones", record_phone_id, "websites_html", website_html.id, &source_page, &[phone_field]).await;
+                        },
+                        Err(e) => {
+                            // Log the error and continue with the next iteration
+                            eprintln!("Error inserting phone: {:?}", e);
+                        }
+                    }
+                }
+            }
+        }
+
+        after_id = last_id;
+    }
+
+    Ok(())
+}
+
+pub async fn update_record_social_profiles_from_websites_html(pool: &MySqlPool) -> Result<(), Error> {
+    let mut after_id = 0;
+
+    loop {
+        let websites_html = WebsitesHtml::get_websites_after(pool, after_id, BATCH_SIZE).await?;
+        let last_id = match websites_html.last() {
+            Some(website_html) => website_html.id,
+            None => break,
         };
 
-        RecordsData::update_email(&pool, &record_data).await?;
+        for website_html in websites_html {
+            let pages = match WebsitePages::get_pages_for_website(&pool, &website_html).await {
+                Ok(pages) => pages,
+                Err(e) => {
+                    eprintln!("Error getting website pages: {:?}", e);
+                    continue;
+                }
+            };
+
+            for (source_page, html) in pages {
+                if html == "" {
+                    continue;
+                }
+
+                for social_profile in social_links::find_social_profiles(&html, &source_page) {
+                    let social_field = ExtractedField::new(&social_profile.network, &social_profile.url, "social.link");
 
-        println!("Record updated");
-        std::thread::sleep(std::time::Duration::from_millis(100));
+                    let record_social_profile = RecordSocialProfiles {
+                        record_id: website_html.records_data_id,
+                        network: social_profile.network,
+                        url: social_profile.url,
+                        source_page: source_page.clone(),
+                    };
+
+                    match RecordSocialProfiles::upsert_record(&pool, &record_social_profile).await {
+                        Ok((record_social_profile_id, true)) => {
+                            println!("Inserted social profile");
+                            FieldProvenance::save_fields(&pool, "record_social_profiles", record_social_profile_id, "websites_html", website_html.id, &source_page, &[social_field]).await;
+                        },
+                        Ok((_, false)) => {},
+                        Err(e) => {
+                            // Log the error and continue with the next iteration
+                            eprintln!("Error inserting social profile: {:?}", e);
+                        }
+                    }
+                }
+            }
+        }
+
+        after_id = last_id;
     }
 
+    RecordSocialProfiles::create_export_view(&pool).await?;
+
     Ok(())
 }
 
-pub async fn run_get_all_pages_houzz(pool: MySqlPool, houzz_data_record: HouzzEntry) -> Result<(), Error> {
+pub async fn run_get_all_pages_houzz(storage: Arc<dyn Storage>, houzz_data_record: HouzzEntry) -> Result<(), Error> {
 
-    let clients = generate_clients(true, 5).await?;
-    let scheduler = scheduler::Scheduler::new(clients.clone());
+    let scheduler = create_scheduler(storage.clone(), true, 5).await?;
     let mut urls: Vec<UrlData> = Vec::new();
 
     println!("Getting data");
@@ -971,18 +1817,17 @@ pub async fn run_get_all_pages_houzz(pool: MySqlPool, houzz_data_record: HouzzEn
     let scheduler_clone = Arc::new(Mutex::new(scheduler));
     let semaphore = Arc::new(Semaphore::new(5));
 
-    get_all_pages_houzz(semaphore, scheduler_clone, houzz_data_record, pool.clone(), urls).await;
+    get_all_pages_houzz(semaphore, scheduler_clone, houzz_data_record, storage, urls).await;
 
     Ok(())
 }
 
-pub async fn run_get_all_records_html_from_links(pool: MySqlPool) -> Result<(), Error> {
+pub async fn run_get_all_records_html_from_links(storage: Arc<dyn Storage>) -> Result<(), Error> {
 
-    let clients = generate_clients(false, 10).await?;
-    let scheduler = scheduler::Scheduler::new(clients.clone());
+    let scheduler = create_scheduler(storage.clone(), false, 10).await?;
     let mut urls: Vec<UrlDataLinks> = Vec::new();
 
-    let links_to_record_details = LinksToRecordDetails::get_all_unvisited_records(&pool).await?;
+    let links_to_record_details = storage.get_unvisited_links().await?;
 
     for link_to_record_details in links_to_record_details {
         let url = link_to_record_details.link.clone();
@@ -996,15 +1841,37 @@ pub async fn run_get_all_records_html_from_links(pool: MySqlPool) -> Result<(),
     let scheduler_clone = Arc::new(Mutex::new(scheduler));
     let semaphore = Arc::new(Semaphore::new(10));
 
-    get_all_records_html_from_links(semaphore, scheduler_clone, pool.clone(), urls).await?;
+    get_all_records_html_from_links(semaphore, scheduler_clone, storage, urls).await?;
 
     Ok(())
 }
 
 pub async fn run_insert_website_html_from_records_data_websites(pool: &MySqlPool) -> Result<(), Error> {
 
-    let clients = generate_clients(false, 10).await?;
-    let scheduler = scheduler::Scheduler::new(clients.clone());
+    let scheduler = create_scheduler(Arc::new(MySqlStorage::new(pool.clone())), false, 10).await?;
+    let mut urls: Vec<UrlDataRecord> = Vec::new();
+
+    let records_data = RecordsData::get_all_records_houzz(&pool).await?;
+
+    for record_data in records_data {
+        let url = record_data.website.clone();
+        urls.push(UrlDataRecord{
+            url: url,
+            record_id: record_data.id,
+        });
+    }
+
+    let scheduler_clone = Arc::new(Mutex::new(scheduler));
+    let semaphore = Arc::new(Semaphore::new(10));
+
+    insert_website_html_from_records_data_websites(semaphore, scheduler_clone, pool, urls, RecrawlConfig::from_env()).await?;
+
+    Ok(())
+}
+
+pub async fn run_crawl_websites_from_records_data(pool: &MySqlPool) -> Result<(), Error> {
+
+    let scheduler = create_scheduler(Arc::new(MySqlStorage::new(pool.clone())), false, 10).await?;
     let mut urls: Vec<UrlDataRecord> = Vec::new();
 
     let records_data = RecordsData::get_all_records_houzz(&pool).await?;
@@ -1020,15 +1887,98 @@ pub async fn run_insert_website_html_from_records_data_websites(pool: &MySqlPool
     let scheduler_clone = Arc::new(Mutex::new(scheduler));
     let semaphore = Arc::new(Semaphore::new(10));
 
-    insert_website_html_from_records_data_websites(semaphore, scheduler_clone, pool, urls).await?;
+    crawl_websites_from_records_data(semaphore, scheduler_clone, pool, urls, CrawlConfig::default(), RecrawlConfig::from_env()).await?;
+
+    Ok(())
+}
+
+// Feeds archived fetches back into the Houzz stages without the network: listing
+// pages first, then link extraction, so detail pages can be matched to their links.
+// Website pages have no Houzz link and are skipped.
+pub async fn run_warc_import(storage: &dyn Storage, paths: &[String]) -> Result<(), Error> {
+    let mut pages = 0;
+    let mut details = 0;
+    let mut skipped = 0;
+
+    for path in paths {
+        for record in warc::read_file(Path::new(path))? {
+            let record = record?;
+            if record.selector.as_deref() != Some(warc::HOUZZ_LISTING_SELECTOR) {
+                continue;
+            }
+
+            let page_with_all_records = PagesWithAllRecords {
+                id: 0,
+                page: None,
+                district: None,
+                query: Some(record.target_uri),
+                html: Some(record.html),
+                processed: Some(0),
+            };
+            storage.create_page(&page_with_all_records).await?;
+            pages += 1;
+        }
+    }
+
+    get_link_details_from_pages(storage).await?;
+
+    for path in paths {
+        for record in warc::read_file(Path::new(path))? {
+            let record = record?;
+            if record.selector.is_some() {
+                continue;
+            }
+
+            let link = match storage.get_link_by_url(&record.target_uri).await? {
+                Some(link) => link,
+                None => {
+                    skipped += 1;
+                    continue;
+                }
+            };
+
+            let record_html = RecordsHtml {
+                id: 0,
+                link_to_record_details_id: link.id,
+                html: record.html,
+                processed: 0,
+            };
+            if storage.save_record_html(&record_html, &link).await? {
+                details += 1;
+            }
+        }
+    }
+
+    println!("Imported {} listing pages and {} detail pages, skipped {} other pages", pages, details, skipped);
+
+    Ok(())
+}
+
+// Meant to run on a schedule: fetches again the detail pages and websites older
+// than RecrawlConfig's ages, re-extracts them and reports what changed. Only
+// changed detail pages go through extraction again.
+pub async fn run_recrawl(pool: &MySqlPool) -> Result<(), Error> {
+    let config = RecrawlConfig::from_env();
+    let links_requeued = LinksToRecordDetails::requeue_stale_records(pool, config.details_max_age_days).await?;
+    let started_at = recrawl::start_run(pool, &config, links_requeued).await?;
+    println!("Recrawl started at {}: {} detail pages older than {} days requeued", started_at, links_requeued, config.details_max_age_days);
+
+    let storage: Arc<dyn Storage> = Arc::new(MySqlStorage::new(pool.clone()));
+
+    run_get_all_records_html_from_links(storage.clone()).await?;
+    populate_records_data_from_records_html(storage.as_ref()).await?;
+    // Emails are read from website_pages, so stale websites are crawled again, not just their homepage.
+    run_crawl_websites_from_records_data(pool).await?;
+    update_record_data_email(storage.as_ref(), &mut EmailVerifier::new(DnsMxLookup::from_system_conf()?)).await?;
+
+    recrawl::print_changes(&RecordFieldHistory::get_changes_since(pool, started_at).await?);
 
     Ok(())
 }
 
 async fn run_update_contact_page_html_from_websites_html(pool: MySqlPool) -> Result<(), Error> {
 
-    let clients = generate_clients(false, 10).await?;
-    let scheduler = scheduler::Scheduler::new(clients.clone());
+    let scheduler = create_scheduler(Arc::new(MySqlStorage::new(pool.clone())), false, 10).await?;
 
     let scheduler_clone = Arc::new(Mutex::new(scheduler));
     let semaphore = Arc::new(Semaphore::new(10));
@@ -1061,17 +2011,245 @@ FROM view_records_with_districts
 WHERE districts LIKE '%General Contractors%';
 
 SELECT 
-    links_to_record_details.company, 
+    records_data.company, 
     links_to_record_details.link, 
-    records_data.email, 
-    records_data.phone
+    view_records_data_emails.email, 
+    records_data.phone,
+    view_records_social_profiles.facebook,
+    view_records_social_profiles.instagram,
+    view_records_social_profiles.linkedin,
+    view_records_social_profiles.youtube,
+    view_records_social_profiles.x,
+    records_profile.rating,
+    records_profile.review_count,
+    records_profile.badges,
+    records_profile.license_number,
+    records_profile.years_in_business,
+    records_profile.service_areas
 INTO OUTFILE '/var/lib/mysql-files/records_without_website_gc.csv'
 FIELDS TERMINATED BY ',' 
 ENCLOSED BY '"'
 LINES TERMINATED BY '\n'
 FROM records_data
+JOIN view_records_data_emails ON view_records_data_emails.id = records_data.id
+LEFT JOIN view_records_social_profiles ON view_records_social_profiles.record_id = records_data.id
 JOIN records_html ON records_data.records_html_id = records_html.id
+LEFT JOIN records_profile ON records_profile.records_html_id = records_html.id
 JOIN links_to_record_details ON records_html.link_to_record_details_id = links_to_record_details.id
 JOIN pages_with_all_records ON links_to_record_details.pages_with_all_records_id = pages_with_all_records.id
 WHERE pages_with_all_records.district LIKE '%General Contractors%' AND records_data.website = '';
-*/
\ No newline at end of file
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use email_verifier::StubMxLookup;
+    use memory_storage::MemoryStorage;
+    use storage::{LinksRepository, PagesRepository, RecordEmailsRepository, RecordsDataRepository, RecordsHtmlRepository, WebsitesRepository};
+
+    fn link(pages_with_all_records_id: i32, company: &str, link: &str) -> LinksToRecordDetails {
+        LinksToRecordDetails {
+            id: 0,
+            pages_with_all_records_id,
+            company: company.to_string(),
+            link: link.to_string(),
+            visited: 0,
+            extractor_version: EXTRACTOR_VERSION.to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn should_store_links_from_unprocessed_pages() {
+        let storage = MemoryStorage::new();
+        let page = PagesWithAllRecords {
+            id: 0,
+            page: Some("1".to_string()),
+            district: Some("Landscape Contractors in Ontario - Houzz".to_string()),
+            query: Some("https://www.houzz.com/professionals/landscape-contractors/ontario-ca-probr0-bo~t_11812~r_6093943".to_string()),
+            html: Some(data::test_generate_houzz_html()),
+            processed: Some(0),
+        };
+        // The same listing fetched twice gives the same links; they are stored once.
+        storage.create_page(&page).await.unwrap();
+        storage.create_page(&page).await.unwrap();
+
+        get_link_details_from_pages(&storage).await.unwrap();
+
+        let tables = storage.tables().unwrap();
+        assert_eq!(tables.links_to_record_details.len(), 6);
+        assert!(tables.links_to_record_details.iter().all(|link| link.link.starts_with("https://www.houzz.com/") && link.pages_with_all_records_id == 1));
+        assert!(tables.pages_with_all_records.iter().all(|page| page.processed == Some(1)));
+    }
+
+    #[tokio::test]
+    async fn should_import_listing_and_detail_pages_from_warc() {
+        let directory = env::temp_dir().join(format!("warc-import-{}", uuid::Uuid::new_v4()));
+        let writer = warc::WarcWriter::new(warc::WarcConfig { directory: directory.clone(), prefix: "test".to_string(), max_file_bytes: warc::DEFAULT_MAX_FILE_BYTES }).unwrap();
+        let listing_url = "https://www.houzz.com/professionals/landscape-contractors/ontario-ca-probr0-bo~t_11812~r_6093943";
+
+        writer.write(&warc::WarcRecord {
+            target_uri: listing_url.to_string(),
+            final_uri: listing_url.to_string(),
+            date: chrono::Utc::now(),
+            selector: Some(warc::HOUZZ_LISTING_SELECTOR.to_string()),
+            html: data::test_generate_houzz_html(),
+        }).unwrap();
+
+        let storage = MemoryStorage::new();
+        let paths: Vec<String> = std::fs::read_dir(&directory).unwrap().map(|entry| entry.unwrap().path().display().to_string()).collect();
+        run_warc_import(&storage, &paths).await.unwrap();
+
+        // Detail pages match the links the listing produced.
+        let detail_url = storage.tables().unwrap().links_to_record_details[0].link.clone();
+        for url in [detail_url.as_str(), "https://mcfeeconstruction.com/"] {
+            writer.write(&warc::WarcRecord {
+                target_uri: url.to_string(),
+                final_uri: url.to_string(),
+                date: chrono::Utc::now(),
+                selector: None,
+                html: data::test_generate_houzz_record_html(),
+            }).unwrap();
+        }
+
+        let storage = MemoryStorage::new();
+        run_warc_import(&storage, &paths).await.unwrap();
+
+        let tables = storage.tables().unwrap();
+        assert_eq!(tables.pages_with_all_records.len(), 1);
+        assert_eq!(tables.links_to_record_details.len(), 6);
+        assert_eq!(tables.records_html.len(), 1);
+        assert_eq!(tables.links_to_record_details.iter().filter(|link| link.visited == 1).count(), 1);
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn should_replay_detail_pages_from_stored_pages() {
+        let detail_url = "https://www.houzz.com/professionals/general-contractors/mcfee-construction";
+
+        let previous_run = Arc::new(MemoryStorage::new());
+        previous_run.upsert_link(&link(1, "McFee Construction", detail_url)).await.unwrap();
+        let stored_link = previous_run.get_link_by_url(detail_url).await.unwrap().unwrap();
+        let record_html = RecordsHtml { id: 0, link_to_record_details_id: stored_link.id, html: data::test_generate_houzz_record_html(), processed: 0 };
+        previous_run.save_record_html(&record_html, &stored_link).await.unwrap();
+
+        let storage = Arc::new(MemoryStorage::new());
+        storage.upsert_link(&link(1, "McFee Construction", detail_url)).await.unwrap();
+        let scheduler = scheduler::Scheduler::replay(Arc::new(replay::StoredPages::new(previous_run)), 2);
+        let urls = vec![
+            UrlDataLinks { url: detail_url.to_string(), link_to_record_details_id: 1 },
+        ];
+
+        get_all_records_html_from_links(Arc::new(Semaphore::new(2)), Arc::new(Mutex::new(scheduler)), storage.clone(), urls).await.unwrap();
+
+        let tables = storage.tables().unwrap();
+        assert_eq!(tables.records_html.len(), 1);
+        assert_eq!(tables.records_html[0].html, data::test_generate_houzz_record_html());
+        assert_eq!(tables.links_to_record_details[0].visited, 1);
+    }
+
+    #[tokio::test]
+    async fn should_populate_records_data_once_per_phone() {
+        let storage = MemoryStorage::new();
+        storage.upsert_link(&link(1, "McFee Construction", "https://www.houzz.com/professionals/general-contractors/mcfee-construction")).await.unwrap();
+        storage.upsert_link(&link(1, "McFee Construction Ltd", "https://www.houzz.com/professionals/general-contractors/mcfee-construction-ltd")).await.unwrap();
+
+        for link_to_record_details_id in [1, 2] {
+            let record_html = RecordsHtml {
+                id: 0,
+                link_to_record_details_id,
+                html: data::test_generate_houzz_record_html(),
+                processed: 0,
+            };
+            storage.upsert_records_html(&record_html).await.unwrap();
+        }
+
+        populate_records_data_from_records_html(&storage).await.unwrap();
+
+        let tables = storage.tables().unwrap();
+        assert_eq!(tables.records_data.len(), 1);
+        assert_eq!(tables.records_data[0].phone, "(905) 713-1230");
+        assert_eq!(tables.records_data[0].website, "www.mcfees.com");
+        assert_eq!(tables.records_data[0].company, "McFee Construction");
+
+        let rule_ids: Vec<&str> = tables.field_provenance.iter().map(|provenance| provenance.rule_id.as_str()).collect();
+        assert_eq!(rule_ids, vec!["houzz.details.phone", "houzz.details.website", "directory.company"]);
+        assert_eq!(tables.extraction_fill_rates.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn should_update_and_version_fields_of_recrawled_records() {
+        let storage = MemoryStorage::new();
+        storage.upsert_link(&link(1, "McFee Construction", "https://www.houzz.com/professionals/general-contractors/mcfee-construction")).await.unwrap();
+        let stored_link = storage.get_link_by_id(1).await.unwrap();
+
+        let record_html = RecordsHtml { id: 0, link_to_record_details_id: 1, html: data::test_generate_houzz_record_html(), processed: 0 };
+        storage.save_record_html(&record_html, &stored_link).await.unwrap();
+        populate_records_data_from_records_html(&storage).await.unwrap();
+        assert!(storage.get_unprocessed_records_html(0, BATCH_SIZE).await.unwrap().is_empty());
+
+        // The business changed its number between crawls.
+        let recrawled = RecordsHtml { html: data::test_generate_houzz_record_html().replace("(905) 713-1230", "(905) 713-9999"), ..record_html.clone() };
+        assert!(storage.refresh_record_html(&recrawled, &stored_link).await.unwrap());
+        assert_eq!(storage.get_unprocessed_records_html(0, BATCH_SIZE).await.unwrap().len(), 1);
+        populate_records_data_from_records_html(&storage).await.unwrap();
+        assert!(storage.get_unprocessed_records_html(0, BATCH_SIZE).await.unwrap().is_empty());
+
+        // An unchanged page is not extracted again.
+        assert!(!storage.refresh_record_html(&recrawled, &stored_link).await.unwrap());
+        assert!(storage.get_unprocessed_records_html(0, BATCH_SIZE).await.unwrap().is_empty());
+        populate_records_data_from_records_html(&storage).await.unwrap();
+
+        let tables = storage.tables().unwrap();
+        assert_eq!(tables.records_data.len(), 1);
+        assert_eq!(tables.records_data[0].phone, "(905) 713-9999");
+        assert_eq!(tables.records_data[0].website, "www.mcfees.com");
+
+        let versions: Vec<(&str, &str)> = tables.record_field_history.iter().map(|version| (version.field.as_str(), version.value.as_str())).collect();
+        assert_eq!(versions, vec![("phone", "(905) 713-1230"), ("website", "www.mcfees.com"), ("phone", "(905) 713-9999")]);
+    }
+
+    #[tokio::test]
+    async fn should_store_verified_emails_from_website_pages() {
+        let storage = MemoryStorage::new();
+        let record_data = RecordsData {
+            id: 0,
+            records_html_id: 1,
+            email: "".to_string(),
+            phone: "(905) 713-1230".to_string(),
+            website: "https://www.mcfees.com".to_string(),
+            contact_us_link: Some("https://www.mcfees.com/contact".to_string()),
+            company: "McFee Construction".to_string(),
+            company_verified: None,
+            extractor_version: EXTRACTOR_VERSION.to_string(),
+        };
+        let (records_data_id, _) = storage.upsert_records_data(&record_data).await.unwrap();
+
+        let website_html = WebsitesHtml {
+            id: 0,
+            records_data_id,
+            website: "https://www.mcfees.com".to_string(),
+            main_page_html: "<html><body><a href=\"mailto:john@mcfees.com\">Email John</a></body></html>".to_string(),
+            contact_page_html: "<html><body><p>Write to info@mcfees.com or quotes@mailinator.com</p></body></html>".to_string(),
+            final_url: None,
+        };
+        storage.create_website(&website_html).await.unwrap();
+
+        let mut email_verifier = EmailVerifier::new(StubMxLookup::new().with_mx("mcfees.com", "mx1.mcfees.com"));
+        update_record_data_email(&storage, &mut email_verifier).await.unwrap();
+        // A second run finds the same addresses and stores nothing new.
+        update_record_data_email(&storage, &mut email_verifier).await.unwrap();
+
+        let record_emails = storage.get_record_emails(records_data_id).await.unwrap();
+        let emails: Vec<&str> = record_emails.iter().map(|record_email| record_email.email.as_str()).collect();
+        assert_eq!(emails, vec!["john@mcfees.com", "info@mcfees.com"]);
+        assert_eq!(record_emails[1].source_page, "https://www.mcfees.com/contact");
+
+        let tables = storage.tables().unwrap();
+        assert_eq!(tables.field_provenance.len(), 2);
+        assert!(tables.field_provenance.iter().all(|provenance| provenance.target_table == "record_emails"));
+        // One version for both runs: the set of addresses did not change.
+        assert_eq!(tables.record_field_history.len(), 1);
+        assert_eq!(tables.record_field_history[0].value, "info@mcfees.com, john@mcfees.com");
+    }
+}
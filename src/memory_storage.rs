@@ -0,0 +1,433 @@
+use anyhow::{anyhow, Error};
+use async_trait::async_trait;
+use chrono::Utc;
+use std::sync::{Mutex, MutexGuard};
+use crate::extraction_fill_rates::ExtractionFillRates;
+use crate::extractor;
+use crate::field_provenance::FieldProvenance;
+use crate::links_to_record_details::LinksToRecordDetails;
+use crate::pages_with_all_records::PagesWithAllRecords;
+use crate::record_emails::RecordEmails;
+use crate::records_data::RecordsData;
+use crate::record_field_history::RecordFieldHistory;
+use crate::records_html::RecordsHtml;
+use crate::storage::{
+    ExtractionLogRepository, LinksRepository, PagesRepository, RecordEmailsRepository, RecordsDataRepository, RecordsHtmlRepository,
+    WebsitesRepository,
+};
+use crate::urls;
+use crate::website_pages::WebsitePages;
+use crate::websites_html::WebsitesHtml;
+
+#[derive(Default)]
+pub struct MemoryTables {
+    pub pages_with_all_records: Vec<PagesWithAllRecords>,
+    pub links_to_record_details: Vec<LinksToRecordDetails>,
+    pub records_html: Vec<RecordsHtml>,
+    pub records_data: Vec<RecordsData>,
+    pub websites_html: Vec<WebsitesHtml>,
+    pub website_pages: Vec<WebsitePages>,
+    pub record_emails: Vec<RecordEmails>,
+    pub field_provenance: Vec<FieldProvenance>,
+    pub extraction_fill_rates: Vec<ExtractionFillRates>,
+    pub record_field_history: Vec<RecordFieldHistory>,
+}
+
+// Keeps every table in a Vec, for tests and offline runs. Ids are assigned like
+// AUTO_INCREMENT: one past the last row of the table.
+#[derive(Default)]
+pub struct MemoryStorage {
+    tables: Mutex<MemoryTables>,
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn tables(&self) -> Result<MutexGuard<'_, MemoryTables>, Error> {
+        self.tables.lock().map_err(|_| anyhow!("Memory storage lock poisoned"))
+    }
+}
+
+fn next_id(ids: impl Iterator<Item = i32>) -> i32 {
+    ids.max().unwrap_or(0) + 1
+}
+
+// WHERE filter AND id > after_id ORDER BY id LIMIT limit
+fn page_after<T: Clone>(rows: &[T], id: impl Fn(&T) -> i32, after_id: i32, limit: i64, filter: impl Fn(&T) -> bool) -> Vec<T> {
+    let mut rows: Vec<T> = rows.iter().filter(|row| id(row) > after_id && filter(row)).cloned().collect();
+    rows.sort_by_key(|row| id(row));
+    rows.truncate(limit.max(0) as usize);
+    rows
+}
+
+#[async_trait]
+impl PagesRepository for MemoryStorage {
+    async fn create_page(&self, page: &PagesWithAllRecords) -> Result<(), Error> {
+        let mut tables = self.tables()?;
+        let id = next_id(tables.pages_with_all_records.iter().map(|page| page.id));
+        tables.pages_with_all_records.push(PagesWithAllRecords { id, processed: Some(0), ..page.clone() });
+
+        Ok(())
+    }
+
+    async fn get_unprocessed_pages(&self, after_id: i32, limit: i64) -> Result<Vec<PagesWithAllRecords>, Error> {
+        let tables = self.tables()?;
+
+        Ok(page_after(&tables.pages_with_all_records, |page| page.id, after_id, limit, |page| page.processed == Some(0)))
+    }
+
+    async fn get_page_by_query(&self, query: &str) -> Result<Option<PagesWithAllRecords>, Error> {
+        let tables = self.tables()?;
+
+        Ok(tables.pages_with_all_records.iter().rev().find(|page| page.query.as_deref() == Some(query)).cloned())
+    }
+
+    async fn mark_page_processed(&self, page: &PagesWithAllRecords) -> Result<(), Error> {
+        let mut tables = self.tables()?;
+        for stored in tables.pages_with_all_records.iter_mut().filter(|stored| stored.id == page.id) {
+            stored.processed = Some(1);
+        }
+
+        Ok(())
+    }
+
+    // Nothing can fail halfway here; the calls only need to happen in order.
+    async fn save_page_links(&self, page: &PagesWithAllRecords, links: &[LinksToRecordDetails]) -> Result<usize, Error> {
+        let mut inserted = 0;
+
+        for link in links {
+            if self.upsert_link(link).await? {
+                inserted += 1;
+            }
+        }
+
+        self.mark_page_processed(page).await?;
+
+        Ok(inserted)
+    }
+}
+
+#[async_trait]
+impl LinksRepository for MemoryStorage {
+    async fn upsert_link(&self, link: &LinksToRecordDetails) -> Result<bool, Error> {
+        let mut tables = self.tables()?;
+        if tables.links_to_record_details.iter().any(|stored| stored.link == link.link) {
+            return Ok(false);
+        }
+
+        let id = next_id(tables.links_to_record_details.iter().map(|link| link.id));
+        tables.links_to_record_details.push(LinksToRecordDetails { id, visited: 0, ..link.clone() });
+
+        Ok(true)
+    }
+
+    async fn get_link_by_id(&self, id: i32) -> Result<LinksToRecordDetails, Error> {
+        let tables = self.tables()?;
+
+        tables
+            .links_to_record_details
+            .iter()
+            .find(|link| link.id == id)
+            .cloned()
+            .ok_or_else(|| anyhow!("No links_to_record_details row with id {}", id))
+    }
+
+    async fn get_link_by_url(&self, link: &str) -> Result<Option<LinksToRecordDetails>, Error> {
+        let tables = self.tables()?;
+
+        Ok(tables.links_to_record_details.iter().find(|stored| stored.link == link).cloned())
+    }
+
+    async fn get_unvisited_links(&self) -> Result<Vec<LinksToRecordDetails>, Error> {
+        let tables = self.tables()?;
+
+        Ok(tables.links_to_record_details.iter().filter(|link| link.visited == 0).cloned().collect())
+    }
+
+    async fn mark_link_visited(&self, link: &LinksToRecordDetails) -> Result<(), Error> {
+        let mut tables = self.tables()?;
+        for stored in tables.links_to_record_details.iter_mut().filter(|stored| stored.link == link.link) {
+            stored.visited = 1;
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl RecordsHtmlRepository for MemoryStorage {
+    async fn upsert_records_html(&self, record: &RecordsHtml) -> Result<bool, Error> {
+        let mut tables = self.tables()?;
+        if tables.records_html.iter().any(|stored| stored.link_to_record_details_id == record.link_to_record_details_id) {
+            return Ok(false);
+        }
+
+        let id = next_id(tables.records_html.iter().map(|record| record.id));
+        tables.records_html.push(RecordsHtml { id, processed: 0, ..record.clone() });
+
+        Ok(true)
+    }
+
+    async fn records_html_exists(&self, link_to_record_details_id: i32) -> Result<bool, Error> {
+        let tables = self.tables()?;
+
+        Ok(tables.records_html.iter().any(|record| record.link_to_record_details_id == link_to_record_details_id))
+    }
+
+    async fn get_unprocessed_records_html(&self, after_id: i32, limit: i64) -> Result<Vec<RecordsHtml>, Error> {
+        let tables = self.tables()?;
+
+        Ok(page_after(&tables.records_html, |record| record.id, after_id, limit, |record| record.processed == 0))
+    }
+
+    async fn get_records_html_by_link(&self, link: &str) -> Result<Option<RecordsHtml>, Error> {
+        let tables = self.tables()?;
+        let link_id = match tables.links_to_record_details.iter().find(|stored| stored.link == link) {
+            Some(stored) => stored.id,
+            None => return Ok(None),
+        };
+
+        Ok(tables.records_html.iter().find(|record| record.link_to_record_details_id == link_id).cloned())
+    }
+
+    async fn mark_records_html_processed(&self, id: i32) -> Result<(), Error> {
+        let mut tables = self.tables()?;
+        for stored in tables.records_html.iter_mut().filter(|stored| stored.id == id) {
+            stored.processed = 1;
+        }
+
+        Ok(())
+    }
+
+    async fn save_record_html(&self, record: &RecordsHtml, link: &LinksToRecordDetails) -> Result<bool, Error> {
+        let inserted = self.upsert_records_html(record).await?;
+        self.mark_link_visited(link).await?;
+
+        Ok(inserted)
+    }
+
+    async fn refresh_record_html(&self, record: &RecordsHtml, link: &LinksToRecordDetails) -> Result<bool, Error> {
+        let changed = {
+            let mut tables = self.tables()?;
+            match tables.records_html.iter_mut().find(|stored| stored.link_to_record_details_id == record.link_to_record_details_id) {
+                Some(stored) => {
+                    let changed = stored.html != record.html;
+                    if changed {
+                        stored.html = record.html.clone();
+                        stored.processed = 0;
+                    }
+                    Some(changed)
+                },
+                None => None,
+            }
+        };
+
+        match changed {
+            Some(changed) => {
+                self.mark_link_visited(link).await?;
+                Ok(changed)
+            },
+            None => self.save_record_html(record, link).await,
+        }
+    }
+}
+
+#[async_trait]
+impl RecordsDataRepository for MemoryStorage {
+    async fn upsert_records_data(&self, record: &RecordsData) -> Result<(i32, bool), Error> {
+        self.mark_records_html_processed(record.records_html_id).await?;
+        let mut tables = self.tables()?;
+        let canonical_domain = urls::canonical_domain(&record.website);
+        let canonical_phone = extractor::canonical_phone(&record.phone);
+        let existing = tables.records_data.iter().find(|stored| {
+            (canonical_domain.is_some() && urls::canonical_domain(&stored.website) == canonical_domain)
+                || (canonical_phone.is_some() && extractor::canonical_phone(&stored.phone) == canonical_phone)
+        });
+
+        if let Some(existing) = existing {
+            return Ok((existing.id, false));
+        }
+
+        let id = next_id(tables.records_data.iter().map(|record| record.id));
+        tables.records_data.push(RecordsData { id, ..record.clone() });
+
+        Ok((id, true))
+    }
+
+    async fn records_data_exists_by_website(&self, website: &str) -> Result<bool, Error> {
+        let canonical_domain = match urls::canonical_domain(website) {
+            Some(canonical_domain) => canonical_domain,
+            None => return Ok(false),
+        };
+        let tables = self.tables()?;
+
+        Ok(tables
+            .records_data
+            .iter()
+            .any(|record| urls::canonical_domain(&record.website).as_deref() == Some(canonical_domain.as_str())))
+    }
+
+    async fn get_records_data_by_id(&self, id: i32) -> Result<RecordsData, Error> {
+        let tables = self.tables()?;
+
+        tables
+            .records_data
+            .iter()
+            .find(|record| record.id == id)
+            .cloned()
+            .ok_or_else(|| anyhow!("No records_data row with id {}", id))
+    }
+
+    async fn get_records_data_by_records_html_id(&self, records_html_id: i32) -> Result<Option<RecordsData>, Error> {
+        let tables = self.tables()?;
+
+        Ok(tables.records_data.iter().find(|record| record.records_html_id == records_html_id).cloned())
+    }
+
+    async fn update_records_data_fields(&self, record: &RecordsData) -> Result<(), Error> {
+        self.mark_records_html_processed(record.records_html_id).await?;
+        let mut tables = self.tables()?;
+        for stored in tables.records_data.iter_mut().filter(|stored| stored.id == record.id) {
+            stored.phone = record.phone.clone();
+            stored.website = record.website.clone();
+            stored.extractor_version = record.extractor_version.clone();
+        }
+
+        Ok(())
+    }
+
+    async fn save_field_version(&self, version: &RecordFieldHistory) -> Result<bool, Error> {
+        let mut tables = self.tables()?;
+        let latest = tables
+            .record_field_history
+            .iter()
+            .rev()
+            .find(|stored| stored.record_id == version.record_id && stored.field == version.field);
+
+        if latest.map(|latest| latest.value.as_str()) == Some(version.value.as_str()) {
+            return Ok(false);
+        }
+
+        let id = next_id(tables.record_field_history.iter().map(|version| version.id));
+        tables.record_field_history.push(RecordFieldHistory { id, observed_at: Some(Utc::now().naive_utc()), ..version.clone() });
+
+        Ok(true)
+    }
+}
+
+#[async_trait]
+impl WebsitesRepository for MemoryStorage {
+    async fn create_website(&self, website: &WebsitesHtml) -> Result<i32, Error> {
+        let mut tables = self.tables()?;
+        let id = next_id(tables.websites_html.iter().map(|website| website.id));
+        tables.websites_html.push(WebsitesHtml { id, ..website.clone() });
+
+        Ok(id)
+    }
+
+    async fn get_websites(&self, after_id: i32, limit: i64) -> Result<Vec<WebsitesHtml>, Error> {
+        let tables = self.tables()?;
+
+        Ok(page_after(&tables.websites_html, |website| website.id, after_id, limit, |_| true))
+    }
+
+    async fn get_website_by_url(&self, website: &str) -> Result<Option<WebsitesHtml>, Error> {
+        let tables = self.tables()?;
+
+        Ok(tables.websites_html.iter().rev().find(|stored| stored.website == website).cloned())
+    }
+
+    async fn create_website_page(&self, page: &WebsitePages) -> Result<(), Error> {
+        let mut tables = self.tables()?;
+        let id = next_id(tables.website_pages.iter().map(|page| page.id));
+        tables.website_pages.push(WebsitePages { id, ..page.clone() });
+
+        Ok(())
+    }
+
+    async fn get_website_pages(&self, websites_html_id: i32) -> Result<Vec<WebsitePages>, Error> {
+        let tables = self.tables()?;
+        let mut website_pages: Vec<WebsitePages> = tables.website_pages.iter().filter(|page| page.websites_html_id == websites_html_id).cloned().collect();
+        website_pages.sort_by(|a, b| a.depth.cmp(&b.depth).then(b.score.cmp(&a.score)));
+
+        Ok(website_pages)
+    }
+
+    async fn get_website_page_by_url(&self, url: &str) -> Result<Option<WebsitePages>, Error> {
+        let tables = self.tables()?;
+
+        Ok(tables.website_pages.iter().rev().find(|page| page.url == url).cloned())
+    }
+}
+
+#[async_trait]
+impl RecordEmailsRepository for MemoryStorage {
+    async fn create_record_email(&self, record: &RecordEmails) -> Result<i32, Error> {
+        let mut tables = self.tables()?;
+        if tables.record_emails.iter().any(|stored| stored.record_id == record.record_id && stored.email == record.email) {
+            return Err(anyhow!("Duplicate entry for record_emails ({}, {})", record.record_id, record.email));
+        }
+
+        let id = next_id(tables.record_emails.iter().map(|record| record.id));
+        tables.record_emails.push(RecordEmails {
+            id,
+            first_seen: Some(Utc::now().naive_utc()),
+            ..record.clone()
+        });
+
+        Ok(id)
+    }
+
+    async fn record_email_exists(&self, record_id: i32, email: &str) -> Result<bool, Error> {
+        let tables = self.tables()?;
+
+        Ok(tables.record_emails.iter().any(|record| record.record_id == record_id && record.email == email))
+    }
+
+    async fn get_record_emails(&self, record_id: i32) -> Result<Vec<RecordEmails>, Error> {
+        let tables = self.tables()?;
+        let mut record_emails: Vec<RecordEmails> = tables.record_emails.iter().filter(|record| record.record_id == record_id).cloned().collect();
+        record_emails.sort_by(|a, b| b.confidence.partial_cmp(&a.confidence).unwrap_or(std::cmp::Ordering::Equal).then(a.id.cmp(&b.id)));
+
+        Ok(record_emails)
+    }
+
+    // There is no view to refresh; get_record_emails is always current.
+    async fn refresh_email_view(&self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ExtractionLogRepository for MemoryStorage {
+    async fn create_field_provenance(&self, record: &FieldProvenance) -> Result<(), Error> {
+        let mut tables = self.tables()?;
+        let id = next_id(tables.field_provenance.iter().map(|record| record.id));
+        tables.field_provenance.push(FieldProvenance { id, ..record.clone() });
+
+        Ok(())
+    }
+
+    async fn create_fill_rate(&self, record: &ExtractionFillRates) -> Result<(), Error> {
+        let mut tables = self.tables()?;
+        let id = next_id(tables.extraction_fill_rates.iter().map(|record| record.id));
+        tables.extraction_fill_rates.push(ExtractionFillRates { id, ..record.clone() });
+
+        Ok(())
+    }
+
+    async fn get_fill_rate_history(&self, stage: &str, field: &str, limit: i64) -> Result<Vec<f32>, Error> {
+        let tables = self.tables()?;
+
+        Ok(tables
+            .extraction_fill_rates
+            .iter()
+            .rev()
+            .filter(|record| record.stage == stage && record.field == field && record.halted == 0)
+            .take(limit.max(0) as usize)
+            .map(|record| record.fill_rate)
+            .collect())
+    }
+}
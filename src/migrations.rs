@@ -0,0 +1,209 @@
+use anyhow::{anyhow, Error};
+use sqlx::{query, query_as, MySqlPool};
+
+pub struct Migration {
+    pub version: i64,
+    pub name: &'static str,
+    pub up: &'static str,
+    pub down: &'static str,
+}
+
+// Embedded in the binary, applied in version order. Never edit one that has shipped; add a new one.
+pub const MIGRATIONS: [Migration; 7] = [
+    Migration {
+        version: 1,
+        name: "create_pipeline_tables",
+        up: include_str!("../migrations/0001_create_pipeline_tables.up.sql"),
+        down: include_str!("../migrations/0001_create_pipeline_tables.down.sql"),
+    },
+    Migration {
+        version: 2,
+        name: "add_canonical_domain_and_version_columns",
+        up: include_str!("../migrations/0002_add_canonical_domain_and_version_columns.up.sql"),
+        down: include_str!("../migrations/0002_add_canonical_domain_and_version_columns.down.sql"),
+    },
+    Migration {
+        version: 3,
+        name: "create_website_contact_tables",
+        up: include_str!("../migrations/0003_create_website_contact_tables.up.sql"),
+        down: include_str!("../migrations/0003_create_website_contact_tables.down.sql"),
+    },
+    Migration {
+        version: 4,
+        name: "create_profile_and_extraction_tables",
+        up: include_str!("../migrations/0004_create_profile_and_extraction_tables.up.sql"),
+        down: include_str!("../migrations/0004_create_profile_and_extraction_tables.down.sql"),
+    },
+    Migration {
+        version: 5,
+        name: "add_unique_keys_for_upserts",
+        up: include_str!("../migrations/0005_add_unique_keys_for_upserts.up.sql"),
+        down: include_str!("../migrations/0005_add_unique_keys_for_upserts.down.sql"),
+    },
+    Migration {
+        version: 6,
+        name: "create_page_blobs",
+        up: include_str!("../migrations/0006_create_page_blobs.up.sql"),
+        down: include_str!("../migrations/0006_create_page_blobs.down.sql"),
+    },
+    Migration {
+        version: 7,
+        name: "add_recrawl_and_field_history",
+        up: include_str!("../migrations/0007_add_recrawl_and_field_history.up.sql"),
+        down: include_str!("../migrations/0007_add_recrawl_and_field_history.down.sql"),
+    },
+];
+
+async fn create_migrations_table(pool: &MySqlPool) -> Result<(), Error> {
+    query("CREATE TABLE IF NOT EXISTS schema_migrations (version BIGINT NOT NULL PRIMARY KEY, name VARCHAR(255) NOT NULL, applied_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP)")
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn applied_versions(pool: &MySqlPool) -> Result<Vec<i64>, Error> {
+    create_migrations_table(pool).await?;
+
+    let versions: Vec<(i64,)> = query_as("SELECT version FROM schema_migrations ORDER BY version")
+        .fetch_all(pool)
+        .await?;
+
+    Ok(versions.into_iter().map(|version| version.0).collect())
+}
+
+// Applies every pending migration. Called at startup, so a fresh database is usable right away.
+pub async fn migrate(pool: &MySqlPool) -> Result<(), Error> {
+    let applied = applied_versions(pool).await?;
+
+    for migration in MIGRATIONS.iter().filter(|migration| !applied.contains(&migration.version)) {
+        println!("Applying migration {} {}", migration.version, migration.name);
+
+        // MySQL commits DDL implicitly, so a failing statement leaves the earlier ones applied.
+        // Statements are run one by one to make the failing one obvious.
+        for statement in split_statements(migration.up) {
+            query(&statement)
+                .execute(pool)
+                .await
+                .map_err(|e| anyhow!("Migration {} failed on `{}`: {}", migration.version, statement, e))?;
+        }
+
+        query("INSERT INTO schema_migrations (version, name) VALUES (?, ?)")
+            .bind(migration.version)
+            .bind(migration.name)
+            .execute(pool)
+            .await?;
+    }
+
+    Ok(())
+}
+
+// Reverts the last `steps` applied migrations, newest first.
+pub async fn rollback(pool: &MySqlPool, steps: usize) -> Result<(), Error> {
+    let applied = applied_versions(pool).await?;
+
+    for version in applied.iter().rev().take(steps) {
+        let migration = MIGRATIONS
+            .iter()
+            .find(|migration| migration.version == *version)
+            .ok_or_else(|| anyhow!("Migration {} is applied but not embedded in this binary", version))?;
+
+        println!("Reverting migration {} {}", migration.version, migration.name);
+
+        for statement in split_statements(migration.down) {
+            query(&statement)
+                .execute(pool)
+                .await
+                .map_err(|e| anyhow!("Rollback of {} failed on `{}`: {}", migration.version, statement, e))?;
+        }
+
+        query("DELETE FROM schema_migrations WHERE version = ?")
+            .bind(migration.version)
+            .execute(pool)
+            .await?;
+    }
+
+    Ok(())
+}
+
+// migrate [status | down [steps]]
+pub async fn run_migrate(pool: &MySqlPool, args: &[String]) -> Result<(), Error> {
+    match args.first().map(|arg| arg.as_str()) {
+        Some("down") => {
+            let steps = args.get(1).and_then(|steps| steps.parse::<usize>().ok()).unwrap_or(1);
+            rollback(pool, steps).await?;
+        }
+        Some("status") | None => {}
+        Some(other) => return Err(anyhow!("Unknown migrate command: {}", other)),
+    }
+
+    let applied = applied_versions(pool).await?;
+    for migration in MIGRATIONS.iter() {
+        let state = if applied.contains(&migration.version) { "applied" } else { "pending" };
+        println!("{:>4} {:<48} {}", migration.version, migration.name, state);
+    }
+
+    Ok(())
+}
+
+// Splits a migration file into statements. Comment lines are dropped; semicolons
+// are only expected at the end of statements, never inside string literals.
+pub fn split_statements(sql: &str) -> Vec<String> {
+    let without_comments = sql
+        .lines()
+        .filter(|line| !line.trim_start().starts_with("--"))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    without_comments
+        .split(';')
+        .map(|statement| statement.trim().to_string())
+        .filter(|statement| !statement.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_split_statements() {
+        let statements = split_statements("-- header\nCREATE TABLE a (id INT);\n\nALTER TABLE a\n    ADD COLUMN b INT;\n");
+
+        assert_eq!(statements, vec!["CREATE TABLE a (id INT)", "ALTER TABLE a\n    ADD COLUMN b INT"]);
+    }
+
+    #[test]
+    fn should_have_ordered_versions_and_both_directions() {
+        for (index, migration) in MIGRATIONS.iter().enumerate() {
+            assert_eq!(migration.version, index as i64 + 1);
+            assert!(!split_statements(migration.up).is_empty());
+            assert!(!split_statements(migration.down).is_empty());
+        }
+    }
+
+    #[test]
+    fn should_create_every_model_table() {
+        let up = MIGRATIONS.iter().map(|migration| migration.up).collect::<Vec<_>>().join("\n");
+        let tables = [
+            "pages_with_all_records",
+            "links_to_record_details",
+            "records_html",
+            "records_data",
+            "websites_html",
+            "invalid_websites",
+            "website_pages",
+            "record_phones",
+            "record_emails",
+            "record_social_profiles",
+            "records_profile",
+            "field_provenance",
+            "extraction_fill_rates",
+            "record_field_history",
+        ];
+
+        for table in tables.iter() {
+            assert!(up.contains(&format!("CREATE TABLE IF NOT EXISTS {} (", table)), "missing table {}", table);
+        }
+    }
+}
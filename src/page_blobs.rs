@@ -0,0 +1,345 @@
+use anyhow::anyhow;
+use sha2::{Digest, Sha256};
+use sqlx::{FromRow, Error, Executor, MySql, query, query_as};
+use sqlx::mysql::MySqlPool;
+use std::collections::HashMap;
+use std::fmt;
+use crate::storage::BATCH_SIZE;
+
+pub const CODEC_ZSTD: &str = "zstd";
+const ZSTD_LEVEL: i32 = 9;
+
+// Every HTML column that can point at a blob: (table, html column, hash column).
+pub const HTML_COLUMNS: [(&str, &str, &str); 4] = [
+    ("pages_with_all_records", "html", "html_hash"),
+    ("records_html", "html", "html_hash"),
+    ("websites_html", "main_page_html", "main_page_html_hash"),
+    ("websites_html", "contact_page_html", "contact_page_html_hash"),
+];
+
+// One distinct page, compressed and keyed by the SHA-256 of its uncompressed HTML.
+#[derive(Clone, Debug, FromRow)]
+pub struct PageBlobs {
+    pub hash: String,
+    pub codec: String,
+    pub data: Vec<u8>,
+    pub original_size: i64,
+    pub compressed_size: i64,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct PageBlobStats {
+    pub blobs: i64,
+    pub blob_bytes: i64,
+    pub stored_bytes: i64,
+    pub references: i64,
+    pub referenced_bytes: i64,
+}
+
+pub fn content_hash(html: &str) -> String {
+    Sha256::digest(html.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+// Empty HTML gets no blob; the row keeps '' and a NULL hash.
+pub fn html_hash(html: &str) -> Option<String> {
+    if html.is_empty() {
+        return None;
+    }
+
+    Some(content_hash(html))
+}
+
+pub fn decompress(codec: &str, data: &[u8]) -> Result<String, Error> {
+    let bytes = match codec {
+        CODEC_ZSTD => zstd::decode_all(data)?,
+        other => return Err(Error::Decode(format!("Unknown page blob codec: {}", other).into())),
+    };
+
+    String::from_utf8(bytes).map_err(|e| Error::Decode(Box::new(e)))
+}
+
+impl PageBlobs {
+    pub fn from_html(html: &str) -> Result<Option<PageBlobs>, Error> {
+        let hash = match html_hash(html) {
+            Some(hash) => hash,
+            None => return Ok(None),
+        };
+
+        let data = zstd::encode_all(html.as_bytes(), ZSTD_LEVEL)?;
+
+        Ok(Some(PageBlobs {
+            hash,
+            codec: CODEC_ZSTD.to_string(),
+            original_size: html.len() as i64,
+            compressed_size: data.len() as i64,
+            data,
+        }))
+    }
+
+    // Identical pages share one row. True when the blob is new.
+    pub async fn upsert_record<'e, E: Executor<'e, Database = MySql>>(executor: E, blob: &PageBlobs) -> Result<bool, Error> {
+        let result = query("INSERT INTO page_blobs (hash, codec, data, original_size, compressed_size) VALUES (?, ?, ?, ?, ?) ON DUPLICATE KEY UPDATE seen_count = seen_count + 1")
+            .bind(&blob.hash)
+            .bind(&blob.codec)
+            .bind(&blob.data)
+            .bind(&blob.original_size)
+            .bind(&blob.compressed_size)
+            .execute(executor)
+            .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    // Stores the blob for `html` and returns the hash the row should reference.
+    pub async fn store_html<'e, E: Executor<'e, Database = MySql>>(executor: E, html: &str) -> Result<Option<String>, Error> {
+        let blob = match PageBlobs::from_html(html)? {
+            Some(blob) => blob,
+            None => return Ok(None),
+        };
+
+        PageBlobs::upsert_record(executor, &blob).await?;
+
+        Ok(Some(blob.hash))
+    }
+
+    // HTML of the rows in `ids` whose hash column points at a blob, by row id.
+    // Table and column names come from HTML_COLUMNS, never from input.
+    pub async fn get_html_for_rows(pool: &MySqlPool, table: &str, hash_column: &str, ids: &[i32]) -> Result<HashMap<i32, String>, Error> {
+        if ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let sql = format!(
+            "SELECT {table}.id, page_blobs.codec, page_blobs.data FROM {table} JOIN page_blobs ON page_blobs.hash = {table}.{hash_column} WHERE {table}.id IN ({placeholders})",
+            table = table,
+            hash_column = hash_column,
+            placeholders = vec!["?"; ids.len()].join(", "),
+        );
+
+        let mut statement = query_as::<_, (i32, String, Vec<u8>)>(&sql);
+        for id in ids {
+            statement = statement.bind(id);
+        }
+
+        let mut html = HashMap::new();
+        for (id, codec, data) in statement.fetch_all(pool).await? {
+            html.insert(id, decompress(&codec, &data)?);
+        }
+
+        Ok(html)
+    }
+
+    // Distinct blobs against what the rows reference, so the dedupe ratio counts
+    // rows that share a page and not repeated writes of the same fetch.
+    pub async fn get_stats(pool: &MySqlPool) -> Result<PageBlobStats, Error> {
+        let (blobs, blob_bytes, stored_bytes): (i64, i64, i64) = query_as("SELECT COUNT(*), CAST(COALESCE(SUM(original_size), 0) AS SIGNED), CAST(COALESCE(SUM(compressed_size), 0) AS SIGNED) FROM page_blobs")
+            .fetch_one(pool)
+            .await?;
+
+        let references_sql = HTML_COLUMNS
+            .iter()
+            .map(|(table, _, hash_column)| format!("SELECT {hash_column} AS hash FROM {table} WHERE {hash_column} IS NOT NULL", table = table, hash_column = hash_column))
+            .collect::<Vec<_>>()
+            .join(" UNION ALL ");
+
+        let (references, referenced_bytes): (i64, i64) = query_as(&format!("SELECT COUNT(*), CAST(COALESCE(SUM(page_blobs.original_size), 0) AS SIGNED) FROM ( {} ) refs JOIN page_blobs ON page_blobs.hash = refs.hash", references_sql))
+            .fetch_one(pool)
+            .await?;
+
+        Ok(PageBlobStats { blobs, blob_bytes, stored_bytes, references, referenced_bytes })
+    }
+
+    // Moves one HTML column into blobs, a batch at a time. Returns rows moved and blobs created.
+    pub async fn compact_column(pool: &MySqlPool, table: &str, html_column: &str, hash_column: &str) -> Result<(usize, usize), Error> {
+        let select_sql = format!(
+            "SELECT id, {html_column} FROM {table} WHERE {hash_column} IS NULL AND {html_column} != '' AND id > ? ORDER BY id LIMIT ?",
+            table = table,
+            html_column = html_column,
+            hash_column = hash_column,
+        );
+        let update_sql = format!("UPDATE {} SET {} = '', {} = ? WHERE id = ?", table, html_column, hash_column);
+        let mut after_id = 0;
+        let mut rows = 0;
+        let mut new_blobs = 0;
+
+        loop {
+            let batch: Vec<(i32, String)> = query_as(&select_sql)
+                .bind(after_id)
+                .bind(BATCH_SIZE)
+                .fetch_all(pool)
+                .await?;
+            let last_id = match batch.last() {
+                Some((id, _)) => *id,
+                None => break,
+            };
+
+            for (id, html) in batch {
+                let blob = match PageBlobs::from_html(&html)? {
+                    Some(blob) => blob,
+                    None => continue,
+                };
+
+                // Blob and row together, so a row never points at a blob that is not there.
+                let mut transaction = pool.begin().await?;
+                if PageBlobs::upsert_record(&mut transaction, &blob).await? {
+                    new_blobs += 1;
+                }
+                query(&update_sql)
+                    .bind(&blob.hash)
+                    .bind(id)
+                    .execute(&mut transaction)
+                    .await?;
+                transaction.commit().await?;
+
+                rows += 1;
+            }
+
+            after_id = last_id;
+        }
+
+        Ok((rows, new_blobs))
+    }
+
+    // Copies blob HTML back into one column and clears the hash. Needed before rolling back the blobs migration.
+    pub async fn expand_column(pool: &MySqlPool, table: &str, html_column: &str, hash_column: &str) -> Result<usize, Error> {
+        let select_sql = format!(
+            "SELECT id FROM {table} WHERE {hash_column} IS NOT NULL AND id > ? ORDER BY id LIMIT ?",
+            table = table,
+            hash_column = hash_column,
+        );
+        let update_sql = format!("UPDATE {} SET {} = ?, {} = NULL WHERE id = ?", table, html_column, hash_column);
+        let mut after_id = 0;
+        let mut rows = 0;
+
+        loop {
+            let ids: Vec<i32> = query_as::<_, (i32,)>(&select_sql)
+                .bind(after_id)
+                .bind(BATCH_SIZE)
+                .fetch_all(pool)
+                .await?
+                .into_iter()
+                .map(|row| row.0)
+                .collect();
+            let last_id = match ids.last() {
+                Some(id) => *id,
+                None => break,
+            };
+
+            for (id, html) in PageBlobs::get_html_for_rows(pool, table, hash_column, &ids).await? {
+                query(&update_sql)
+                    .bind(html)
+                    .bind(id)
+                    .execute(pool)
+                    .await?;
+                rows += 1;
+            }
+
+            after_id = last_id;
+        }
+
+        Ok(rows)
+    }
+}
+
+impl PageBlobStats {
+    // Bytes of distinct HTML per byte stored.
+    pub fn compression_ratio(&self) -> f64 {
+        ratio(self.blob_bytes, self.stored_bytes)
+    }
+
+    // Bytes of HTML the rows reference per byte of distinct HTML.
+    pub fn dedupe_ratio(&self) -> f64 {
+        ratio(self.referenced_bytes, self.blob_bytes)
+    }
+}
+
+impl fmt::Display for PageBlobStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "blobs             {:>14}", self.blobs)?;
+        writeln!(f, "references        {:>14}", self.references)?;
+        writeln!(f, "referenced bytes  {:>14}", self.referenced_bytes)?;
+        writeln!(f, "distinct bytes    {:>14}", self.blob_bytes)?;
+        writeln!(f, "stored bytes      {:>14}", self.stored_bytes)?;
+        writeln!(f, "dedupe ratio      {:>13.2}x", self.dedupe_ratio())?;
+        writeln!(f, "compression ratio {:>13.2}x", self.compression_ratio())?;
+        write!(f, "overall ratio     {:>13.2}x", ratio(self.referenced_bytes, self.stored_bytes))
+    }
+}
+
+fn ratio(numerator: i64, denominator: i64) -> f64 {
+    if denominator == 0 {
+        return 0.0;
+    }
+
+    numerator as f64 / denominator as f64
+}
+
+// `blobs compact` moves inline HTML into page_blobs, `blobs expand` moves it
+// back, and `blobs stats` (the default) reports storage size and dedupe ratio.
+pub async fn run_blobs(pool: &MySqlPool, args: &[String]) -> Result<(), anyhow::Error> {
+    match args.first().map(|arg| arg.as_str()) {
+        Some("compact") => {
+            for (table, html_column, hash_column) in HTML_COLUMNS {
+                let (rows, new_blobs) = PageBlobs::compact_column(pool, table, html_column, hash_column).await?;
+                println!("{}.{}: moved {} rows into {} new blobs", table, html_column, rows, new_blobs);
+            }
+        }
+        Some("expand") => {
+            for (table, html_column, hash_column) in HTML_COLUMNS {
+                let rows = PageBlobs::expand_column(pool, table, html_column, hash_column).await?;
+                println!("{}.{}: restored {} rows", table, html_column, rows);
+            }
+        }
+        Some("stats") | None => {}
+        Some(other) => return Err(anyhow!("Unknown blobs command: {}", other)),
+    }
+
+    println!("{}", PageBlobs::get_stats(pool).await?);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_round_trip_compressed_html() {
+        let html = format!("<html><body>{}</body></html>", "<div class=\"pro\">Acme Builders</div>".repeat(200));
+        let blob = PageBlobs::from_html(&html).unwrap().unwrap();
+
+        assert_eq!(blob.codec, CODEC_ZSTD);
+        assert_eq!(blob.original_size, html.len() as i64);
+        assert!(blob.compressed_size < blob.original_size / 10);
+        assert_eq!(decompress(&blob.codec, &blob.data).unwrap(), html);
+    }
+
+    #[test]
+    fn should_key_blobs_by_content() {
+        let first = PageBlobs::from_html("<html>a</html>").unwrap().unwrap();
+        let again = PageBlobs::from_html("<html>a</html>").unwrap().unwrap();
+        let other = PageBlobs::from_html("<html>b</html>").unwrap().unwrap();
+
+        assert_eq!(first.hash, again.hash);
+        assert_ne!(first.hash, other.hash);
+        assert_eq!(first.hash.len(), 64);
+        assert!(PageBlobs::from_html("").unwrap().is_none());
+    }
+
+    #[test]
+    fn should_reject_unknown_codecs() {
+        assert!(decompress("brotli", b"").is_err());
+    }
+
+    #[test]
+    fn should_report_ratios() {
+        let stats = PageBlobStats { blobs: 2, blob_bytes: 1_000, stored_bytes: 100, references: 6, referenced_bytes: 3_000 };
+
+        assert_eq!(stats.compression_ratio(), 10.0);
+        assert_eq!(stats.dedupe_ratio(), 3.0);
+        assert_eq!(PageBlobStats::default().dedupe_ratio(), 0.0);
+    }
+}
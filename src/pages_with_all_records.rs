@@ -1,7 +1,8 @@
-use sqlx::{MySqlPool, Row};
+use sqlx::{Executor, MySql, MySqlPool, Row};
 use anyhow::Result;
 use sqlx::query_as;
 use std::env;
+use crate::page_blobs::PageBlobs;
 #[derive(Clone, Debug)]
 pub struct PagesWithAllRecords {
     pub id: i32,
@@ -15,53 +16,84 @@ pub struct PagesWithAllRecords {
 impl PagesWithAllRecords {
     pub async fn create_record(page: &PagesWithAllRecords, pool: &MySqlPool) -> Result<()> {
         println!("Creating page: {:?}", page);
-        sqlx::query("INSERT INTO pages_with_all_records (page, district, query, html) VALUES (?, ?, ?, ?)")
+        let mut transaction = pool.begin().await?;
+        let html_hash = PageBlobs::store_html(&mut transaction, page.html.as_deref().unwrap_or_default()).await?;
+
+        sqlx::query("INSERT INTO pages_with_all_records (page, district, query, html, html_hash) VALUES (?, ?, ?, ?, ?)")
             .bind(&page.page)
             .bind(&page.district)
             .bind(&page.query)
-            .bind(&page.html)
-            .execute(pool)
+            .bind(page.html.as_ref().map(|_| ""))
+            .bind(html_hash)
+            .execute(&mut transaction)
             .await?;
+        transaction.commit().await?;
 
         Ok(())
     }
 
-    pub async fn mark_record_as_processed(page: &PagesWithAllRecords, pool: &MySqlPool) -> Result<()> {
+    pub async fn mark_record_as_processed<'e, E: Executor<'e, Database = MySql>>(page: &PagesWithAllRecords, executor: E) -> Result<()> {
         println!("Marking page as processed: {:?}", page);
         sqlx::query("UPDATE pages_with_all_records SET processed = 1 WHERE id = ?")
             .bind(&page.id)
-            .execute(pool)
+            .execute(executor)
             .await?;
     
         Ok(())
     }
 
-    pub async fn get_all_records(pool: &MySqlPool) -> Result<Vec<PagesWithAllRecords>> {
-        let mut pages_with_all_records = Vec::new();
-
-            
+    pub async fn get_records_after(pool: &MySqlPool, after_id: i32, limit: i64) -> Result<Vec<PagesWithAllRecords>> {
         let pages: Vec<PagesWithAllRecords> = query_as!(
             PagesWithAllRecords,
-            "SELECT * FROM pages_with_all_records"
+            "SELECT id, page, district, query, html, processed FROM pages_with_all_records WHERE id > ? ORDER BY id LIMIT ?",
+            after_id,
+            limit
         )
         .fetch_all(pool)
         .await?;
 
-
-        pages_with_all_records.extend(pages);
-
-        Ok(pages_with_all_records)
+        PagesWithAllRecords::with_blob_html(pool, pages).await
     }
 
-    pub async fn get_all_unprocessed_records(pool: &MySqlPool) -> Result<Vec<PagesWithAllRecords>> {
+    // One keyset page of unprocessed pages: ids after `after_id`, at most `limit` rows.
+    pub async fn get_unprocessed_records_after(pool: &MySqlPool, after_id: i32, limit: i64) -> Result<Vec<PagesWithAllRecords>> {
         let records: Vec<PagesWithAllRecords> = query_as!(
             PagesWithAllRecords,
-            "SELECT * FROM pages_with_all_records WHERE processed = 0"
+            "SELECT id, page, district, query, html, processed FROM pages_with_all_records WHERE processed = 0 AND id > ? ORDER BY id LIMIT ?",
+            after_id,
+            limit
         )
         .fetch_all(pool)
         .await?;
     
-        Ok(records)
+        PagesWithAllRecords::with_blob_html(pool, records).await
+    }
+
+    // Latest fetch of a listing URL.
+    pub async fn get_record_by_query(pool: &MySqlPool, query: &str) -> Result<Option<PagesWithAllRecords>> {
+        let page: Option<PagesWithAllRecords> = query_as!(
+            PagesWithAllRecords,
+            "SELECT id, page, district, query, html, processed FROM pages_with_all_records WHERE query = ? ORDER BY id DESC LIMIT 1",
+            query
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(PagesWithAllRecords::with_blob_html(pool, page.into_iter().collect()).await?.pop())
+    }
+
+    // Rows written since page_blobs keep '' inline; their HTML comes from the blob.
+    async fn with_blob_html(pool: &MySqlPool, mut pages: Vec<PagesWithAllRecords>) -> Result<Vec<PagesWithAllRecords>> {
+        let ids: Vec<i32> = pages.iter().map(|page| page.id).collect();
+        let mut html = PageBlobs::get_html_for_rows(pool, "pages_with_all_records", "html_hash", &ids).await?;
+
+        for page in pages.iter_mut() {
+            if let Some(blob_html) = html.remove(&page.id) {
+                page.html = Some(blob_html);
+            }
+        }
+
+        Ok(pages)
     }
 
     // Similar changes can be made to other methods...
@@ -0,0 +1,58 @@
+use sqlx::{FromRow, Error, query, query_as};
+use sqlx::mysql::MySqlPool;
+use chrono::NaiveDateTime;
+
+#[derive(Clone, Debug, FromRow)]
+pub struct RecordEmails {
+    pub id: i32,
+    pub record_id: i32,
+    pub email: String,
+    pub source_page: String,
+    pub source_type: String,
+    pub first_seen: Option<NaiveDateTime>,
+    pub confidence: f32,
+}
+
+impl RecordEmails {
+    pub async fn create_record(pool: &MySqlPool, record: &RecordEmails) -> Result<i32, Error> {
+        println!("Creating email: {:?}", record);
+        let result = query("INSERT INTO record_emails (record_id, email, source_page, source_type, first_seen, confidence) VALUES (?, ?, ?, ?, NOW(), ?)")
+            .bind(&record.record_id)
+            .bind(&record.email)
+            .bind(&record.source_page)
+            .bind(&record.source_type)
+            .bind(&record.confidence)
+            .execute(pool)
+            .await?;
+
+        Ok(result.last_insert_id() as i32)
+    }
+
+    pub async fn record_exists(pool: &MySqlPool, record_id: i32, email: &str) -> Result<bool, Error> {
+        let exists: (i32,) = query_as("SELECT EXISTS( SELECT 1 FROM record_emails WHERE record_id = ? AND email = ? )")
+            .bind(record_id)
+            .bind(email)
+            .fetch_one(pool)
+            .await?;
+
+        Ok(exists.0 == 1)
+    }
+
+    pub async fn get_records_by_record_id(pool: &MySqlPool, record_id: i32) -> Result<Vec<RecordEmails>, Error> {
+        let record_emails: Vec<RecordEmails> = query_as("SELECT * FROM record_emails WHERE record_id = ? ORDER BY confidence DESC, id")
+            .bind(record_id)
+            .fetch_all(pool)
+            .await?;
+
+        Ok(record_emails)
+    }
+
+    // Old exports read a comma-joined records_data.email; this view keeps that shape.
+    pub async fn create_compat_view(pool: &MySqlPool) -> Result<(), Error> {
+        query("CREATE OR REPLACE VIEW view_records_data_emails AS SELECT records_data.id, records_data.records_html_id, COALESCE(GROUP_CONCAT(record_emails.email ORDER BY record_emails.confidence DESC, record_emails.id SEPARATOR ', '), '') AS email, records_data.phone, records_data.website, records_data.contact_us_link FROM records_data LEFT JOIN record_emails ON record_emails.record_id = records_data.id GROUP BY records_data.id, records_data.records_html_id, records_data.phone, records_data.website, records_data.contact_us_link")
+            .execute(pool)
+            .await?;
+
+        Ok(())
+    }
+}
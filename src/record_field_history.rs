@@ -0,0 +1,96 @@
+use chrono::NaiveDateTime;
+use sqlx::{FromRow, Error, query, query_as};
+use sqlx::mysql::MySqlPool;
+use std::fmt;
+
+// One value a records_data field (or the record's set of website phones) took, and the row it was read from.
+#[derive(Clone, Debug, FromRow)]
+pub struct RecordFieldHistory {
+    pub id: i32,
+    pub record_id: i32,
+    pub field: String,
+    pub value: String,
+    pub source_table: String,
+    pub source_id: i32,
+    pub observed_at: Option<NaiveDateTime>,
+}
+
+// A version next to the one it replaced; `old_value` is None for the first value seen.
+#[derive(Clone, Debug, FromRow, PartialEq)]
+pub struct RecordFieldChange {
+    pub record_id: i32,
+    pub company: String,
+    pub field: String,
+    pub old_value: Option<String>,
+    pub new_value: String,
+    pub observed_at: NaiveDateTime,
+}
+
+impl fmt::Display for RecordFieldChange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.old_value {
+            Some(old_value) => write!(f, "records_data #{} {} {}: {:?} -> {:?} ({})", self.record_id, self.company, self.field, old_value, self.new_value, self.observed_at),
+            None => write!(f, "records_data #{} {} {}: first seen {:?} ({})", self.record_id, self.company, self.field, self.new_value, self.observed_at),
+        }
+    }
+}
+
+impl RecordFieldHistory {
+    pub fn new(record_id: i32, field: &str, value: &str, source_table: &str, source_id: i32) -> RecordFieldHistory {
+        RecordFieldHistory {
+            id: 0,
+            record_id,
+            field: field.to_string(),
+            value: value.to_string(),
+            source_table: source_table.to_string(),
+            source_id,
+            observed_at: None,
+        }
+    }
+
+    // Writes the value as a new version unless it is already the latest one. True when written.
+    pub async fn save_version(pool: &MySqlPool, version: &RecordFieldHistory) -> Result<bool, Error> {
+        let mut transaction = pool.begin().await?;
+
+        let latest: Option<(String,)> = query_as("SELECT value FROM record_field_history WHERE record_id = ? AND field = ? ORDER BY id DESC LIMIT 1 FOR UPDATE")
+            .bind(&version.record_id)
+            .bind(&version.field)
+            .fetch_optional(&mut transaction)
+            .await?;
+
+        if latest.map(|latest| latest.0).as_deref() == Some(version.value.as_str()) {
+            return Ok(false);
+        }
+
+        query("INSERT INTO record_field_history (record_id, field, value, source_table, source_id) VALUES (?, ?, ?, ?, ?)")
+            .bind(&version.record_id)
+            .bind(&version.field)
+            .bind(&version.value)
+            .bind(&version.source_table)
+            .bind(&version.source_id)
+            .execute(&mut transaction)
+            .await?;
+        transaction.commit().await?;
+
+        Ok(true)
+    }
+
+    pub async fn get_records_by_record_id(pool: &MySqlPool, record_id: i32) -> Result<Vec<RecordFieldHistory>, Error> {
+        let record_field_history: Vec<RecordFieldHistory> = query_as("SELECT * FROM record_field_history WHERE record_id = ? ORDER BY field, id")
+            .bind(record_id)
+            .fetch_all(pool)
+            .await?;
+
+        Ok(record_field_history)
+    }
+
+    // Every version written since `since`, each with the value it replaced.
+    pub async fn get_changes_since(pool: &MySqlPool, since: NaiveDateTime) -> Result<Vec<RecordFieldChange>, Error> {
+        let changes: Vec<RecordFieldChange> = query_as("SELECT history.record_id, COALESCE(records_data.company, '') AS company, history.field, ( SELECT previous.value FROM record_field_history AS previous WHERE previous.record_id = history.record_id AND previous.field = history.field AND previous.id < history.id ORDER BY previous.id DESC LIMIT 1 ) AS old_value, history.value AS new_value, history.observed_at FROM record_field_history AS history LEFT JOIN records_data ON records_data.id = history.record_id WHERE history.observed_at >= ? ORDER BY history.record_id, history.field, history.id")
+            .bind(since)
+            .fetch_all(pool)
+            .await?;
+
+        Ok(changes)
+    }
+}
@@ -0,0 +1,57 @@
+use sqlx::{FromRow, Error, query, query_as};
+use sqlx::mysql::MySqlPool;
+
+#[derive(Clone, Debug, FromRow)]
+pub struct RecordPhones {
+    pub id: i32,
+    pub records_data_id: i32,
+    pub phone: String,
+    pub source_page: String,
+    pub source_type: String,
+    pub extractor_version: String,
+}
+
+impl RecordPhones {
+    pub async fn create_record(pool: &MySqlPool, record: &RecordPhones) -> Result<i32, Error> {
+        println!("Creating phone: {:?}", record);
+        let result = query("INSERT INTO record_phones (records_data_id, phone, source_page, source_type, extractor_version) VALUES (?, ?, ?, ?, ?)")
+            .bind(&record.records_data_id)
+            .bind(&record.phone)
+            .bind(&record.source_page)
+            .bind(&record.source_type)
+            .bind(&record.extractor_version)
+            .execute(pool)
+            .await?;
+
+        Ok(result.last_insert_id() as i32)
+    }
+
+    pub async fn record_exists(pool: &MySqlPool, records_data_id: i32, phone: &str) -> Result<bool, Error> {
+        let exists: (i32,) = query_as("SELECT EXISTS( SELECT 1 FROM record_phones WHERE records_data_id = ? AND phone = ? )")
+            .bind(records_data_id)
+            .bind(phone)
+            .fetch_one(pool)
+            .await?;
+
+        Ok(exists.0 == 1)
+    }
+
+    pub async fn get_records_by_records_data_id(pool: &MySqlPool, records_data_id: i32) -> Result<Vec<RecordPhones>, Error> {
+        let record_phones: Vec<RecordPhones> = query_as("SELECT * FROM record_phones WHERE records_data_id = ?")
+            .bind(records_data_id)
+            .fetch_all(pool)
+            .await?;
+
+        Ok(record_phones)
+    }
+
+    pub async fn delete_record(pool: &MySqlPool, id: i32) -> Result<(), Error> {
+        println!("Deleting phone: {}", id);
+        query("DELETE FROM record_phones WHERE id = ?")
+            .bind(id)
+            .execute(pool)
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,36 @@
+use sqlx::{FromRow, Error, query};
+use sqlx::mysql::MySqlPool;
+
+#[derive(Clone, Debug, FromRow)]
+pub struct RecordSocialProfiles {
+    pub record_id: i32,
+    pub network: String,
+    pub url: String,
+    pub source_page: String,
+}
+
+impl RecordSocialProfiles {
+    // Keeps the first row for a record's profile URL. Returns the id of the new or
+    // existing row, and whether it is new.
+    pub async fn upsert_record(pool: &MySqlPool, record: &RecordSocialProfiles) -> Result<(i32, bool), Error> {
+        println!("Upserting social profile: {:?}", record);
+        let result = query("INSERT INTO record_social_profiles (record_id, network, url, source_page) VALUES (?, ?, ?, ?) ON DUPLICATE KEY UPDATE id = LAST_INSERT_ID(id), seen_count = seen_count + 1")
+            .bind(&record.record_id)
+            .bind(&record.network)
+            .bind(&record.url)
+            .bind(&record.source_page)
+            .execute(pool)
+            .await?;
+
+        Ok((result.last_insert_id() as i32, result.rows_affected() == 1))
+    }
+
+    // One row per record with a column per network, for joining into CSV exports.
+    pub async fn create_export_view(pool: &MySqlPool) -> Result<(), Error> {
+        query("CREATE OR REPLACE VIEW view_records_social_profiles AS SELECT record_id, MIN(CASE WHEN network = 'facebook' THEN url END) AS facebook, MIN(CASE WHEN network = 'instagram' THEN url END) AS instagram, MIN(CASE WHEN network = 'linkedin' THEN url END) AS linkedin, MIN(CASE WHEN network = 'youtube' THEN url END) AS youtube, MIN(CASE WHEN network = 'x' THEN url END) AS x FROM record_social_profiles GROUP BY record_id")
+            .execute(pool)
+            .await?;
+
+        Ok(())
+    }
+}
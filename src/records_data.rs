@@ -1,6 +1,11 @@
-use sqlx::{Row, FromRow, Error, MySql, query, query_as};
-use sqlx::mysql::MySqlPool;
+use sqlx::{Row, FromRow, Error, Executor, MySql, query, query_as};
+use sqlx::mysql::{MySqlConnection, MySqlDatabaseError, MySqlPool};
 use anyhow::Result;
+use crate::extractor::canonical_phone;
+use crate::urls;
+
+// MySQL's duplicate entry error for a unique key.
+const ER_DUP_ENTRY: u16 = 1062;
 
 #[derive(Clone, Debug, FromRow)]
 pub struct RecordsData {
@@ -10,20 +15,29 @@ pub struct RecordsData {
     pub phone: String,
     pub website: String,
     pub contact_us_link: Option<String>,
+    pub company: String,
+    pub company_verified: Option<i32>,
+    pub extractor_version: String,
 }
 
 impl RecordsData {
-    pub async fn create_record(pool: &MySqlPool, record: &RecordsData) -> Result<(), Error> {
-        println!("Creating record: {:?}", record);
-        query("INSERT INTO records_data (records_html_id, email, phone, website) VALUES (?, ?, ?, ?)")
+    // Keeps the first record for a canonical website or phone. Returns the id of
+    // the new or existing row, and whether it is new.
+    pub async fn upsert_record<'e, E: Executor<'e, Database = MySql>>(executor: E, record: &RecordsData) -> Result<(i32, bool), Error> {
+        println!("Upserting record: {:?}", record);
+        let result = query("INSERT INTO records_data (records_html_id, email, phone, website, canonical_domain, canonical_phone, company, extractor_version) VALUES (?, ?, ?, ?, ?, ?, ?, ?) ON DUPLICATE KEY UPDATE id = LAST_INSERT_ID(id), seen_count = seen_count + 1")
             .bind(&record.records_html_id)
             .bind(&record.email)
             .bind(&record.phone)
             .bind(&record.website)
-            .execute(pool)
+            .bind(urls::canonical_domain(&record.website))
+            .bind(canonical_phone(&record.phone))
+            .bind(&record.company)
+            .bind(&record.extractor_version)
+            .execute(executor)
             .await?;
 
-        Ok(())
+        Ok((result.last_insert_id() as i32, result.rows_affected() == 1))
     }
 
     pub async fn record_exists(pool: &MySqlPool, records_html_id: i32) -> Result<bool, Error> {
@@ -36,17 +50,13 @@ impl RecordsData {
     }
 
     pub async fn record_exists_by_website(pool: &MySqlPool, website: &str) -> Result<bool, Error> {
-        let exists: (i32,) = query_as("SELECT EXISTS( SELECT 1 FROM records_data WHERE website = ? )")
-            .bind(website)
-            .fetch_one(pool)
-            .await?;
-
-        Ok(exists.0 == 1)
-    }
+        let canonical_domain = match urls::canonical_domain(website) {
+            Some(canonical_domain) => canonical_domain,
+            None => return Ok(false),
+        };
 
-    pub async fn record_exists_by_phone(pool: &MySqlPool, phone: &str) -> Result<bool, Error> {
-        let exists: (i32,) = query_as("SELECT EXISTS( SELECT 1 FROM records_data WHERE phone = ? )")
-            .bind(phone)
+        let exists: (i32,) = query_as("SELECT EXISTS( SELECT 1 FROM records_data WHERE canonical_domain = ? )")
+            .bind(canonical_domain)
             .fetch_one(pool)
             .await?;
 
@@ -95,11 +105,14 @@ impl RecordsData {
 
     pub async fn update_website(pool: &MySqlPool, record: &RecordsData) -> Result<(), Error> {
         println!("Updating website: {:?}", record);
-        query("UPDATE records_data SET website = ? WHERE id = ?")
+        let mut transaction = pool.begin().await?;
+        query("UPDATE records_data SET website = ?, canonical_domain = NULL WHERE id = ?")
             .bind(&record.website)
             .bind(&record.id)
-            .execute(pool)
+            .execute(&mut transaction)
             .await?;
+        RecordsData::claim_key(&mut transaction, record, "canonical_domain", urls::canonical_domain(&record.website)).await?;
+        transaction.commit().await?;
 
         Ok(())
     }
@@ -115,8 +128,135 @@ impl RecordsData {
         Ok(())
     }
 
+    // Values a re-extraction produced, stamped with the extractor version that produced them.
+    // The keys are released and claimed again, so run it inside a transaction.
+    pub async fn update_extracted_fields(connection: &mut MySqlConnection, record: &RecordsData) -> Result<(), Error> {
+        println!("Updating extracted fields: {:?}", record);
+        query("UPDATE records_data SET phone = ?, website = ?, canonical_domain = NULL, canonical_phone = NULL, extractor_version = ? WHERE id = ?")
+            .bind(&record.phone)
+            .bind(&record.website)
+            .bind(&record.extractor_version)
+            .bind(&record.id)
+            .execute(&mut *connection)
+            .await?;
+        RecordsData::claim_key(&mut *connection, record, "canonical_domain", urls::canonical_domain(&record.website)).await?;
+        RecordsData::claim_key(connection, record, "canonical_phone", canonical_phone(&record.phone)).await?;
+
+        Ok(())
+    }
+
+    // Gives the record one of its dedupe keys. The unique key settles who owns it:
+    // a website or phone that moved to a known business is stored, but the duplicate
+    // key error leaves the key with the record that has it.
+    async fn claim_key(connection: &mut MySqlConnection, record: &RecordsData, column: &str, key: Option<String>) -> Result<(), Error> {
+        let key = match key {
+            Some(key) => key,
+            None => return Ok(()),
+        };
+
+        let claimed = query(&format!("UPDATE records_data SET {} = ? WHERE id = ?", column))
+            .bind(&key)
+            .bind(&record.id)
+            .execute(connection)
+            .await;
+
+        match claimed {
+            Err(Error::Database(e)) if e.try_downcast_ref::<MySqlDatabaseError>().map(|e| e.number()) == Some(ER_DUP_ENTRY) => {
+                println!("{} {} of records_data #{} belongs to another record, not claiming it", column, key, record.id);
+                Ok(())
+            },
+            claimed => claimed.map(|_| ()),
+        }
+    }
+
+    // Recomputes the key from the website. The oldest record wins it: later records
+    // holding it give it up. Returns the key the record ended up with.
+    pub async fn update_canonical_domain(pool: &MySqlPool, record: &RecordsData) -> Result<Option<String>, Error> {
+        let mut transaction = pool.begin().await?;
+        let canonical_domain = urls::canonical_domain(&record.website);
+
+        if let Some(canonical_domain) = &canonical_domain {
+            let released = query("UPDATE records_data SET canonical_domain = NULL WHERE canonical_domain = ? AND id > ?")
+                .bind(canonical_domain)
+                .bind(&record.id)
+                .execute(&mut transaction)
+                .await?;
+
+            if released.rows_affected() > 0 {
+                println!("records_data #{} takes {} back from a later record", record.id, canonical_domain);
+            }
+
+            let owner: Option<(i32,)> = query_as("SELECT id FROM records_data WHERE canonical_domain = ? AND id < ?")
+                .bind(canonical_domain)
+                .bind(&record.id)
+                .fetch_optional(&mut transaction)
+                .await?;
+
+            if let Some((owner_id,)) = owner {
+                println!("records_data #{} shares {} with records_data #{}, not claiming it", record.id, canonical_domain, owner_id);
+                query("UPDATE records_data SET canonical_domain = NULL WHERE id = ?")
+                    .bind(&record.id)
+                    .execute(&mut transaction)
+                    .await?;
+                transaction.commit().await?;
+
+                return Ok(None);
+            }
+        }
+
+        query("UPDATE records_data SET canonical_domain = ? WHERE id = ?")
+            .bind(&canonical_domain)
+            .bind(&record.id)
+            .execute(&mut transaction)
+            .await?;
+        transaction.commit().await?;
+
+        Ok(canonical_domain)
+    }
+
+    // One keyset page: ids after `after_id`, at most `limit` rows.
+    pub async fn get_records_after(pool: &MySqlPool, after_id: i32, limit: i64) -> Result<Vec<RecordsData>, Error> {
+        let records_data: Vec<RecordsData> = query_as("SELECT * FROM records_data WHERE id > ? ORDER BY id LIMIT ?")
+            .bind(after_id)
+            .bind(limit)
+            .fetch_all(pool)
+            .await?;
+
+        Ok(records_data)
+    }
+
+    pub async fn get_record_by_records_html_id(pool: &MySqlPool, records_html_id: i32) -> Result<Option<RecordsData>, Error> {
+        let record: Option<RecordsData> = query_as("SELECT * FROM records_data WHERE records_html_id = ?")
+            .bind(records_html_id)
+            .fetch_optional(pool)
+            .await?;
+
+        Ok(record)
+    }
+
+    pub async fn update_company(pool: &MySqlPool, record: &RecordsData) -> Result<(), Error> {
+        println!("Updating company: {:?}", record.company);
+        query("UPDATE records_data SET company = ? WHERE id = ?")
+            .bind(&record.company)
+            .bind(&record.id)
+            .execute(pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn update_company_verified(pool: &MySqlPool, record: &RecordsData) -> Result<(), Error> {
+        query("UPDATE records_data SET company_verified = ? WHERE id = ?")
+            .bind(&record.company_verified)
+            .bind(&record.id)
+            .execute(pool)
+            .await?;
+
+        Ok(())
+    }
+
     pub async fn get_all_records_houzz(pool: &MySqlPool) -> Result<Vec<RecordsData>, Error> {
-        let records_data: Vec<RecordsData> = query_as("SELECT records_data.email, records_data.id, records_data.website, records_data.contact_us_link, records_data.phone, records_data.records_html_id FROM records_data INNER JOIN records_html ON records_data.records_html_id = records_html.id INNER JOIN links_to_record_details ON records_html.link_to_record_details_id = links_to_record_details.id INNER JOIN pages_with_all_records ON pages_with_all_records.id = links_to_record_details.pages_with_all_records_id WHERE pages_with_all_records.district LIKE '%General Contractors in Ontario - Houzz%' AND records_data.website != ''")
+        let records_data: Vec<RecordsData> = query_as("SELECT records_data.email, records_data.id, records_data.website, records_data.contact_us_link, records_data.phone, records_data.records_html_id, records_data.company, records_data.company_verified, records_data.extractor_version FROM records_data INNER JOIN records_html ON records_data.records_html_id = records_html.id INNER JOIN links_to_record_details ON records_html.link_to_record_details_id = links_to_record_details.id INNER JOIN pages_with_all_records ON pages_with_all_records.id = links_to_record_details.pages_with_all_records_id WHERE pages_with_all_records.district LIKE '%General Contractors in Ontario - Houzz%' AND records_data.website != ''")
             .fetch_all(pool)
             .await?;
 
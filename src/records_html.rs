@@ -1,7 +1,8 @@
-use sqlx::{Row, FromRow, Error, MySql, query, query_as};
+use sqlx::{Row, FromRow, Error, Executor, MySql, Transaction, query, query_as};
 use sqlx::mysql::MySqlPool;
 use anyhow::Result;
 use super::links_to_record_details::LinksToRecordDetails;
+use crate::page_blobs::{self, PageBlobs};
 
 #[derive(Clone, Debug, FromRow)]
 pub struct RecordsHtml {
@@ -12,38 +13,97 @@ pub struct RecordsHtml {
 }
 
 impl RecordsHtml {
-    pub async fn create_record(pool: &MySqlPool, record: &RecordsHtml) -> Result<(), Error> {
-        println!("Creating record");
-        query("INSERT INTO records_html (link_to_record_details_id, html) VALUES (?, ?)")
+    // Keeps the first page fetched for a link. True when the row is new. The HTML
+    // itself goes to page_blobs; store it with PageBlobs::store_html first.
+    pub async fn upsert_record<'e, E: Executor<'e, Database = MySql>>(executor: E, record: &RecordsHtml) -> Result<bool, Error> {
+        println!("Upserting record");
+        let result = query("INSERT INTO records_html (link_to_record_details_id, html, html_hash) VALUES (?, '', ?) ON DUPLICATE KEY UPDATE seen_count = seen_count + 1")
             .bind(&record.link_to_record_details_id)
-            .bind(&record.html)
-            .execute(pool)
+            .bind(page_blobs::html_hash(&record.html))
+            .execute(executor)
+            .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    // Replaces the page of a link fetched again by a re-crawl. A changed page is
+    // queued for extraction again. True when the HTML changed.
+    pub async fn refresh_record(transaction: &mut Transaction<'_, MySql>, record: &RecordsHtml) -> Result<bool, Error> {
+        let html_hash = page_blobs::html_hash(&record.html);
+        let previous_hash: Option<(Option<String>,)> = query_as("SELECT html_hash FROM records_html WHERE link_to_record_details_id = ? FOR UPDATE")
+            .bind(&record.link_to_record_details_id)
+            .fetch_optional(&mut *transaction)
+            .await?;
+
+        let previous_hash = match previous_hash {
+            Some(previous_hash) => previous_hash.0,
+            None => return RecordsHtml::upsert_record(&mut *transaction, record).await,
+        };
+        let changed = previous_hash != html_hash;
+
+        query("UPDATE records_html SET html = '', html_hash = ?, processed = IF(?, 0, processed), fetched_at = NOW(), seen_count = seen_count + 1 WHERE link_to_record_details_id = ?")
+            .bind(&html_hash)
+            .bind(changed)
+            .bind(&record.link_to_record_details_id)
+            .execute(&mut *transaction)
+            .await?;
+
+        Ok(changed)
+    }
+
+    pub async fn mark_record_as_processed<'e, E: Executor<'e, Database = MySql>>(executor: E, id: i32) -> Result<(), Error> {
+        query("UPDATE records_html SET processed = 1 WHERE id = ?")
+            .bind(id)
+            .execute(executor)
             .await?;
 
         Ok(())
     }
 
-    pub async fn get_all_records(pool: &MySqlPool) -> Result<Vec<RecordsHtml>, Error> {
-        let records_html: Vec<RecordsHtml> = query_as("SELECT * FROM records_html")
+    // Keyset pages: ids after `after_id`, at most `limit` rows. Every row carries a
+    // whole page of HTML, so the tables are never read in one go.
+    pub async fn get_records_after(pool: &MySqlPool, after_id: i32, limit: i64) -> Result<Vec<RecordsHtml>, Error> {
+        let records_html: Vec<RecordsHtml> = query_as("SELECT * FROM records_html WHERE id > ? ORDER BY id LIMIT ?")
+            .bind(after_id)
+            .bind(limit)
             .fetch_all(pool)
             .await?;
 
-        Ok(records_html)
+        RecordsHtml::with_blob_html(pool, records_html).await
+    }
+
+    pub async fn get_record_by_id(pool: &MySqlPool, id: i32) -> Result<RecordsHtml, Error> {
+        let record_html: RecordsHtml = query_as("SELECT * FROM records_html WHERE id = ?")
+            .bind(id)
+            .fetch_one(pool)
+            .await?;
+
+        Ok(RecordsHtml::with_blob_html(pool, vec![record_html]).await?.remove(0))
+    }
+
+    pub async fn get_record_by_link(pool: &MySqlPool, link: &str) -> Result<Option<RecordsHtml>, Error> {
+        let record_html: Option<RecordsHtml> = query_as("SELECT records_html.* FROM records_html JOIN links_to_record_details ON links_to_record_details.id = records_html.link_to_record_details_id WHERE links_to_record_details.link = ?")
+            .bind(link)
+            .fetch_optional(pool)
+            .await?;
+
+        Ok(RecordsHtml::with_blob_html(pool, record_html.into_iter().collect()).await?.pop())
     }
 
-    pub async fn get_all_unprocessed_records(pool: &MySqlPool) -> Result<Vec<RecordsHtml>, Error> {
-        let records_html: Vec<RecordsHtml> = query_as("SELECT * FROM records_html WHERE processed = 0")
+    pub async fn get_unprocessed_records_after(pool: &MySqlPool, after_id: i32, limit: i64) -> Result<Vec<RecordsHtml>, Error> {
+        let records_html: Vec<RecordsHtml> = query_as("SELECT * FROM records_html WHERE processed = 0 AND id > ? ORDER BY id LIMIT ?")
+            .bind(after_id)
+            .bind(limit)
             .fetch_all(pool)
             .await?;
 
-        Ok(records_html)
+        RecordsHtml::with_blob_html(pool, records_html).await
     }
 
     pub async fn delete_record(pool: &MySqlPool, record: &RecordsHtml) -> Result<(), Error> {
         println!("Deleting record: {:?}", record);
-        query("DELETE FROM records_html WHERE link_to_record_details_id = ? AND html = ?")
-            .bind(&record.link_to_record_details_id)
-            .bind(&record.html)
+        query("DELETE FROM records_html WHERE id = ?")
+            .bind(&record.id)
             .execute(pool)
             .await?;
 
@@ -58,4 +118,18 @@ impl RecordsHtml {
 
         Ok(exists.0 == 1)
     }
-}
\ No newline at end of file
+
+    // Rows written since page_blobs keep '' inline; their HTML comes from the blob.
+    async fn with_blob_html(pool: &MySqlPool, mut records_html: Vec<RecordsHtml>) -> Result<Vec<RecordsHtml>, Error> {
+        let ids: Vec<i32> = records_html.iter().map(|record_html| record_html.id).collect();
+        let mut html = PageBlobs::get_html_for_rows(pool, "records_html", "html_hash", &ids).await?;
+
+        for record_html in records_html.iter_mut() {
+            if let Some(blob_html) = html.remove(&record_html.id) {
+                record_html.html = blob_html;
+            }
+        }
+
+        Ok(records_html)
+    }
+}
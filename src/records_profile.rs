@@ -0,0 +1,69 @@
+use sqlx::{FromRow, Error, query, query_as};
+use sqlx::mysql::MySqlPool;
+
+#[derive(Clone, Debug, FromRow)]
+pub struct RecordsProfile {
+    pub id: i32,
+    pub records_html_id: i32,
+    pub rating: Option<f32>,
+    pub review_count: Option<i32>,
+    pub badges: String,
+    pub license_number: String,
+    pub years_in_business: Option<i32>,
+    pub service_areas: String,
+    pub extractor_version: String,
+}
+
+impl RecordsProfile {
+    pub async fn create_record(pool: &MySqlPool, record: &RecordsProfile) -> Result<i32, Error> {
+        println!("Creating profile: {:?}", record);
+        let result = query("INSERT INTO records_profile (records_html_id, rating, review_count, badges, license_number, years_in_business, service_areas, extractor_version) VALUES (?, ?, ?, ?, ?, ?, ?, ?)")
+            .bind(&record.records_html_id)
+            .bind(&record.rating)
+            .bind(&record.review_count)
+            .bind(&record.badges)
+            .bind(&record.license_number)
+            .bind(&record.years_in_business)
+            .bind(&record.service_areas)
+            .bind(&record.extractor_version)
+            .execute(pool)
+            .await?;
+
+        Ok(result.last_insert_id() as i32)
+    }
+
+    pub async fn record_exists(pool: &MySqlPool, records_html_id: i32) -> Result<bool, Error> {
+        let exists: (i32,) = query_as("SELECT EXISTS( SELECT 1 FROM records_profile WHERE records_html_id = ? )")
+            .bind(records_html_id)
+            .fetch_one(pool)
+            .await?;
+
+        Ok(exists.0 == 1)
+    }
+
+    pub async fn update_record(pool: &MySqlPool, record: &RecordsProfile) -> Result<(), Error> {
+        println!("Updating profile: {:?}", record);
+        query("UPDATE records_profile SET rating = ?, review_count = ?, badges = ?, license_number = ?, years_in_business = ?, service_areas = ?, extractor_version = ? WHERE id = ?")
+            .bind(&record.rating)
+            .bind(&record.review_count)
+            .bind(&record.badges)
+            .bind(&record.license_number)
+            .bind(&record.years_in_business)
+            .bind(&record.service_areas)
+            .bind(&record.extractor_version)
+            .bind(&record.id)
+            .execute(pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_record_by_records_html_id(pool: &MySqlPool, records_html_id: i32) -> Result<Option<RecordsProfile>, Error> {
+        let records_profile: Option<RecordsProfile> = query_as("SELECT * FROM records_profile WHERE records_html_id = ?")
+            .bind(records_html_id)
+            .fetch_optional(pool)
+            .await?;
+
+        Ok(records_profile)
+    }
+}
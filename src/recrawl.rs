@@ -0,0 +1,143 @@
+use anyhow::{anyhow, Error};
+use chrono::NaiveDateTime;
+use sqlx::{query, query_as, MySqlPool};
+use std::env;
+use crate::record_field_history::{RecordFieldChange, RecordFieldHistory};
+
+pub const DEFAULT_DETAILS_MAX_AGE_DAYS: i32 = 90;
+pub const DEFAULT_WEBSITES_MAX_AGE_DAYS: i32 = 30;
+
+// How old a fetched page may get before a re-crawl fetches it again, per stage.
+// Houzz detail pages (phone, website) change less often than business websites (emails).
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecrawlConfig {
+    pub details_max_age_days: i32,
+    pub websites_max_age_days: i32,
+}
+
+impl Default for RecrawlConfig {
+    fn default() -> Self {
+        Self {
+            details_max_age_days: DEFAULT_DETAILS_MAX_AGE_DAYS,
+            websites_max_age_days: DEFAULT_WEBSITES_MAX_AGE_DAYS,
+        }
+    }
+}
+
+impl RecrawlConfig {
+    // RECRAWL_DETAILS_MAX_AGE_DAYS and RECRAWL_WEBSITES_MAX_AGE_DAYS override the defaults.
+    pub fn from_env() -> RecrawlConfig {
+        let default = RecrawlConfig::default();
+
+        RecrawlConfig {
+            details_max_age_days: days_from_env("RECRAWL_DETAILS_MAX_AGE_DAYS").unwrap_or(default.details_max_age_days),
+            websites_max_age_days: days_from_env("RECRAWL_WEBSITES_MAX_AGE_DAYS").unwrap_or(default.websites_max_age_days),
+        }
+    }
+}
+
+fn days_from_env(name: &str) -> Option<i32> {
+    env::var(name).ok().and_then(|days| days.parse::<i32>().ok()).filter(|days| *days >= 0)
+}
+
+// Records the run so `changes` can report from its start. Returns that start time.
+pub async fn start_run(pool: &MySqlPool, config: &RecrawlConfig, links_requeued: u64) -> Result<NaiveDateTime, Error> {
+    let result = query("INSERT INTO recrawl_runs (details_max_age_days, websites_max_age_days, links_requeued) VALUES (?, ?, ?)")
+        .bind(&config.details_max_age_days)
+        .bind(&config.websites_max_age_days)
+        .bind(links_requeued)
+        .execute(pool)
+        .await?;
+
+    let started_at: (NaiveDateTime,) = query_as("SELECT started_at FROM recrawl_runs WHERE id = ?")
+        .bind(result.last_insert_id())
+        .fetch_one(pool)
+        .await?;
+
+    Ok(started_at.0)
+}
+
+pub async fn get_last_run_started_at(pool: &MySqlPool) -> Result<Option<NaiveDateTime>, Error> {
+    let started_at: Option<(NaiveDateTime,)> = query_as("SELECT started_at FROM recrawl_runs ORDER BY id DESC LIMIT 1")
+        .fetch_optional(pool)
+        .await?;
+
+    Ok(started_at.map(|started_at| started_at.0))
+}
+
+// Changes are what the report is for; first values only say a field was seen.
+pub fn print_changes(changes: &[RecordFieldChange]) {
+    let first_seen = changes.iter().filter(|change| change.old_value.is_none()).count();
+
+    for change in changes.iter().filter(|change| change.old_value.is_some()) {
+        println!("{}", change);
+    }
+
+    println!("{} changed values, {} values seen for the first time", changes.len() - first_seen, first_seen);
+}
+
+// changes [--since "YYYY-MM-DD HH:MM:SS" | --record <records_data id>]
+// Without arguments: everything that changed since the last recrawl started.
+pub async fn run_changes_report(pool: &MySqlPool, args: &[String]) -> Result<(), Error> {
+    match args.first().map(|arg| arg.as_str()) {
+        Some("--record") => {
+            let record_id = args
+                .get(1)
+                .and_then(|record_id| record_id.parse::<i32>().ok())
+                .ok_or_else(|| anyhow!("--record takes a records_data id"))?;
+
+            for version in RecordFieldHistory::get_records_by_record_id(pool, record_id).await? {
+                println!("{:<10} {:?} ({}, {} #{})", version.field, version.value, version.observed_at.map(|observed_at| observed_at.to_string()).unwrap_or_default(), version.source_table, version.source_id);
+            }
+
+            Ok(())
+        }
+        Some("--since") => {
+            let since = args.get(1).ok_or_else(|| anyhow!("--since takes a date"))?;
+            let since = NaiveDateTime::parse_from_str(since, "%Y-%m-%d %H:%M:%S")?;
+
+            print_changes(&RecordFieldHistory::get_changes_since(pool, since).await?);
+            Ok(())
+        }
+        None => {
+            let since = get_last_run_started_at(pool).await?.ok_or_else(|| anyhow!("No recrawl has run yet; pass --since"))?;
+            println!("Changes since the recrawl started at {}", since);
+
+            print_changes(&RecordFieldHistory::get_changes_since(pool, since).await?);
+            Ok(())
+        }
+        Some(other) => Err(anyhow!("Unknown changes option: {}", other)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn should_read_max_ages_from_env() {
+        env::set_var("RECRAWL_DETAILS_MAX_AGE_DAYS", "7");
+        env::set_var("RECRAWL_WEBSITES_MAX_AGE_DAYS", "not a number");
+
+        let config = RecrawlConfig::from_env();
+
+        env::remove_var("RECRAWL_DETAILS_MAX_AGE_DAYS");
+        env::remove_var("RECRAWL_WEBSITES_MAX_AGE_DAYS");
+        assert_eq!(config, RecrawlConfig { details_max_age_days: 7, websites_max_age_days: DEFAULT_WEBSITES_MAX_AGE_DAYS });
+    }
+
+    #[test]
+    fn should_show_old_and_new_value() {
+        let change = RecordFieldChange {
+            record_id: 12,
+            company: "McFee Construction".to_string(),
+            field: "phone".to_string(),
+            old_value: Some("(905) 555-0100".to_string()),
+            new_value: "(905) 555-0199".to_string(),
+            observed_at: NaiveDate::from_ymd_opt(2024, 3, 1).unwrap().and_hms_opt(12, 0, 0).unwrap(),
+        };
+
+        assert_eq!(change.to_string(), "records_data #12 McFee Construction phone: \"(905) 555-0100\" -> \"(905) 555-0199\" (2024-03-01 12:00:00)");
+    }
+}
@@ -0,0 +1,525 @@
+use anyhow::Error;
+use sqlx::MySqlPool;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::io::{self, BufRead, Write};
+use crate::extractor::{ExtractedField, Extractor, EXTRACTOR_VERSION};
+use crate::field_provenance::FieldProvenance;
+use crate::links_to_record_details::LinksToRecordDetails;
+use crate::pages_with_all_records::PagesWithAllRecords;
+use crate::record_field_history::RecordFieldHistory;
+use crate::record_phones::RecordPhones;
+use crate::records_data::RecordsData;
+use crate::records_html::RecordsHtml;
+use crate::records_profile::RecordsProfile;
+use crate::storage::BATCH_SIZE;
+use crate::urls::UrlResolver;
+use crate::website_pages::WebsitePages;
+use crate::websites_html::WebsitesHtml;
+
+// Extractors that can be re-run over stored HTML, and the table each one derives.
+pub const EXTRACTORS: [(&str, &str); 4] = [
+    ("links", "pages_with_all_records -> links_to_record_details"),
+    ("records_data", "records_html -> records_data"),
+    ("records_profile", "records_html -> records_profile"),
+    ("phones", "websites_html -> record_phones"),
+];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FieldChange {
+    pub table: String,
+    pub row_id: i32,
+    pub field: String,
+    pub old_value: String,
+    pub new_value: String,
+    pub old_version: String,
+}
+
+impl fmt::Display for FieldChange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let old_version = if self.old_version == "" { "unversioned" } else { &self.old_version };
+        write!(
+            f,
+            "{} #{} {}: {:?} -> {:?} ({} -> {})",
+            self.table, self.row_id, self.field, self.old_value, self.new_value, old_version, EXTRACTOR_VERSION
+        )
+    }
+}
+
+enum PendingWrite {
+    UpdateLink(LinksToRecordDetails),
+    UpdateRecordsData(RecordsData),
+    UpdateRecordsProfile(RecordsProfile),
+    InsertPhone(RecordPhones),
+    DeletePhone(RecordPhones),
+}
+
+// A write to commit, with what produced it so provenance can be saved next to the new value.
+struct PendingUpdate {
+    write: PendingWrite,
+    source_table: String,
+    source_id: i32,
+    page_url: String,
+    fields: Vec<ExtractedField>,
+}
+
+#[derive(Default)]
+pub struct Reextraction {
+    pub changes: Vec<FieldChange>,
+    updates: Vec<PendingUpdate>,
+}
+
+impl Reextraction {
+    fn push(&mut self, changes: Vec<FieldChange>, update: PendingUpdate) {
+        if changes.is_empty() {
+            return;
+        }
+
+        self.changes.extend(changes);
+        self.updates.push(update);
+    }
+}
+
+// Field by field differences between what is stored and what the current extractor produces.
+pub fn diff_fields(table: &str, row_id: i32, old_version: &str, old: &[(&str, String)], new: &[(&str, String)]) -> Vec<FieldChange> {
+    let mut changes = Vec::new();
+
+    for (field, new_value) in new {
+        let old_value = old
+            .iter()
+            .find(|(old_field, _)| old_field == field)
+            .map(|(_, value)| value.clone())
+            .unwrap_or_default();
+
+        if old_value != *new_value {
+            changes.push(FieldChange {
+                table: table.to_string(),
+                row_id,
+                field: field.to_string(),
+                old_value,
+                new_value: new_value.clone(),
+                old_version: old_version.to_string(),
+            });
+        }
+    }
+
+    changes
+}
+
+pub async fn reextract_links(pool: &MySqlPool) -> Result<Reextraction, Error> {
+    let mut reextraction = Reextraction::default();
+    let mut after_id = 0;
+
+    loop {
+        let pages_with_all_records = PagesWithAllRecords::get_records_after(pool, after_id, BATCH_SIZE).await?;
+        let last_id = match pages_with_all_records.last() {
+            Some(page_with_all_records) => page_with_all_records.id,
+            None => break,
+        };
+
+        for page_with_all_records in pages_with_all_records {
+            let html = page_with_all_records.html.clone().unwrap_or_default();
+            let page_url = page_with_all_records.query.clone().unwrap_or_default();
+            let url_resolver = UrlResolver::new(&page_url, &html);
+            let extractor = Extractor::new(html);
+
+            let links = LinksToRecordDetails::get_records_by_pages_with_all_records_id(pool, page_with_all_records.id).await?;
+
+            for company_info in extractor.get_company_info_houzz() {
+                let link_url = match url_resolver.as_ref().and_then(|resolver| resolver.resolve(&company_info.link)) {
+                    Some(link) => link,
+                    None => company_info.link,
+                };
+
+                let mut link = match links.iter().find(|link| link.link == link_url) {
+                    Some(link) => link.clone(),
+                    None => continue,
+                };
+
+                let changes = diff_fields(
+                    "links_to_record_details",
+                    link.id,
+                    &link.extractor_version,
+                    &[("company", link.company.clone())],
+                    &[("company", company_info.company.clone())],
+                );
+
+                link.company = company_info.company;
+                link.extractor_version = EXTRACTOR_VERSION.to_string();
+
+                reextraction.push(changes, PendingUpdate {
+                    fields: vec![ExtractedField::new("company", &link.company, "directory.company")],
+                    write: PendingWrite::UpdateLink(link),
+                    source_table: "pages_with_all_records".to_string(),
+                    source_id: page_with_all_records.id,
+                    page_url: page_url.clone(),
+                });
+            }
+        }
+
+        after_id = last_id;
+    }
+
+    Ok(reextraction)
+}
+
+pub async fn reextract_records_data(pool: &MySqlPool) -> Result<Reextraction, Error> {
+    let mut reextraction = Reextraction::default();
+    let mut after_id = 0;
+
+    loop {
+        let records_html = RecordsHtml::get_records_after(pool, after_id, BATCH_SIZE).await?;
+        let last_id = match records_html.last() {
+            Some(record_html) => record_html.id,
+            None => break,
+        };
+
+        for record_html in records_html {
+            let mut record_data = match RecordsData::get_record_by_records_html_id(pool, record_html.id).await? {
+                Some(record_data) => record_data,
+                None => continue,
+            };
+
+            let page_url = LinksToRecordDetails::get_record_by_id(pool, record_html.link_to_record_details_id).await?.link;
+            let company_details = Extractor::new(record_html.html).get_company_details_houzz();
+
+            let changes = diff_fields(
+                "records_data",
+                record_data.id,
+                &record_data.extractor_version,
+                &[("phone", record_data.phone.clone()), ("website", record_data.website.clone())],
+                &[("phone", company_details.phone.clone()), ("website", company_details.website.clone())],
+            );
+
+            record_data.phone = company_details.phone;
+            record_data.website = company_details.website;
+            record_data.extractor_version = EXTRACTOR_VERSION.to_string();
+
+            reextraction.push(changes, PendingUpdate {
+                write: PendingWrite::UpdateRecordsData(record_data),
+                source_table: "records_html".to_string(),
+                source_id: record_html.id,
+                page_url,
+                fields: company_details.fields,
+            });
+        }
+
+        after_id = last_id;
+    }
+
+    Ok(reextraction)
+}
+
+pub async fn reextract_records_profile(pool: &MySqlPool) -> Result<Reextraction, Error> {
+    let mut reextraction = Reextraction::default();
+    let mut after_id = 0;
+
+    loop {
+        let records_html = RecordsHtml::get_records_after(pool, after_id, BATCH_SIZE).await?;
+        let last_id = match records_html.last() {
+            Some(record_html) => record_html.id,
+            None => break,
+        };
+
+        for record_html in records_html {
+            let mut records_profile = match RecordsProfile::get_record_by_records_html_id(pool, record_html.id).await? {
+                Some(records_profile) => records_profile,
+                None => continue,
+            };
+
+            let page_url = LinksToRecordDetails::get_record_by_id(pool, record_html.link_to_record_details_id).await?.link;
+            let company_profile = Extractor::new(record_html.html).get_company_profile_houzz();
+            let badges = company_profile.badges.join(", ");
+
+            let changes = diff_fields(
+                "records_profile",
+                records_profile.id,
+                &records_profile.extractor_version,
+                &[
+                    ("rating", optional_value(records_profile.rating)),
+                    ("review_count", optional_value(records_profile.review_count)),
+                    ("badges", records_profile.badges.clone()),
+                    ("license_number", records_profile.license_number.clone()),
+                    ("years_in_business", optional_value(records_profile.years_in_business)),
+                    ("service_areas", records_profile.service_areas.clone()),
+                ],
+                &[
+                    ("rating", optional_value(company_profile.rating)),
+                    ("review_count", optional_value(company_profile.review_count)),
+                    ("badges", badges.clone()),
+                    ("license_number", company_profile.license_number.clone()),
+                    ("years_in_business", optional_value(company_profile.years_in_business)),
+                    ("service_areas", company_profile.service_areas.clone()),
+                ],
+            );
+
+            records_profile.rating = company_profile.rating;
+            records_profile.review_count = company_profile.review_count;
+            records_profile.badges = badges;
+            records_profile.license_number = company_profile.license_number;
+            records_profile.years_in_business = company_profile.years_in_business;
+            records_profile.service_areas = company_profile.service_areas;
+            records_profile.extractor_version = EXTRACTOR_VERSION.to_string();
+
+            reextraction.push(changes, PendingUpdate {
+                write: PendingWrite::UpdateRecordsProfile(records_profile),
+                source_table: "records_html".to_string(),
+                source_id: record_html.id,
+                page_url,
+                fields: company_profile.fields,
+            });
+        }
+
+        after_id = last_id;
+    }
+
+    Ok(reextraction)
+}
+
+// Phones are a set per record: new numbers are inserted, numbers no page yields any more are removed.
+pub async fn reextract_phones(pool: &MySqlPool) -> Result<Reextraction, Error> {
+    let mut reextraction = Reextraction::default();
+    let mut after_id = 0;
+
+    loop {
+        let websites_html = WebsitesHtml::get_websites_after(pool, after_id, BATCH_SIZE).await?;
+        let last_id = match websites_html.last() {
+            Some(website_html) => website_html.id,
+            None => break,
+        };
+
+        for website_html in websites_html {
+            let stored_phones = RecordPhones::get_records_by_records_data_id(pool, website_html.records_data_id).await?;
+            let mut found_phones: HashSet<String> = HashSet::new();
+
+            for (source_page, html) in WebsitePages::get_pages_for_website(pool, &website_html).await? {
+                if html == "" {
+                    continue;
+                }
+
+                for found_phone in Extractor::new(html).find_phones() {
+                    if !found_phones.insert(found_phone.phone.clone()) {
+                        continue;
+                    }
+
+                    if stored_phones.iter().any(|stored_phone| stored_phone.phone == found_phone.phone) {
+                        continue;
+                    }
+
+                    let changes = diff_fields("record_phones", 0, "", &[], &[("phone", found_phone.phone.clone())]);
+                    let phone_field = ExtractedField::new("phone", &found_phone.phone, &format!("phone.{}", found_phone.source_type));
+
+                    reextraction.push(changes, PendingUpdate {
+                        write: PendingWrite::InsertPhone(RecordPhones {
+                            id: 0,
+                            records_data_id: website_html.records_data_id,
+                            phone: found_phone.phone,
+                            source_page: source_page.clone(),
+                            source_type: found_phone.source_type,
+                            extractor_version: EXTRACTOR_VERSION.to_string(),
+                        }),
+                        source_table: "websites_html".to_string(),
+                        source_id: website_html.id,
+                        page_url: source_page.clone(),
+                        fields: vec![phone_field],
+                    });
+                }
+            }
+
+            for stored_phone in stored_phones.iter().filter(|stored_phone| !found_phones.contains(&stored_phone.phone)) {
+                let changes = diff_fields(
+                    "record_phones",
+                    stored_phone.id,
+                    &stored_phone.extractor_version,
+                    &[("phone", stored_phone.phone.clone())],
+                    &[("phone", String::new())],
+                );
+
+                reextraction.push(changes, PendingUpdate {
+                    write: PendingWrite::DeletePhone(stored_phone.clone()),
+                    source_table: "websites_html".to_string(),
+                    source_id: website_html.id,
+                    page_url: stored_phone.source_page.clone(),
+                    fields: Vec::new(),
+                });
+            }
+        }
+
+        after_id = last_id;
+    }
+
+    Ok(reextraction)
+}
+
+// Changed values are versioned like a recrawl's, so they show up in the changes report.
+pub async fn commit(pool: &MySqlPool, reextraction: Reextraction) -> Result<(), Error> {
+    // Records from before the history existed get their stored phones as the first version.
+    let mut phone_sources: BTreeMap<i32, (String, i32)> = BTreeMap::new();
+    for update in &reextraction.updates {
+        if let PendingWrite::InsertPhone(RecordPhones { records_data_id, .. }) | PendingWrite::DeletePhone(RecordPhones { records_data_id, .. }) = &update.write {
+            phone_sources.insert(*records_data_id, (update.source_table.clone(), update.source_id));
+        }
+    }
+    for (records_data_id, (source_table, source_id)) in &phone_sources {
+        save_website_phones_version(pool, *records_data_id, source_table, *source_id).await?;
+    }
+
+    for update in reextraction.updates {
+        let (target_table, target_id) = match update.write {
+            PendingWrite::UpdateLink(link) => {
+                LinksToRecordDetails::update_company(pool, &link).await?;
+                ("links_to_record_details", link.id)
+            }
+            PendingWrite::UpdateRecordsData(record_data) => {
+                let stored = RecordsData::get_record_data_by_records_data_id(pool, record_data.id).await?;
+                save_records_data_versions(pool, &stored, "records_html", stored.records_html_id).await?;
+
+                let mut transaction = pool.begin().await?;
+                RecordsData::update_extracted_fields(&mut transaction, &record_data).await?;
+                transaction.commit().await?;
+
+                save_records_data_versions(pool, &record_data, &update.source_table, update.source_id).await?;
+                ("records_data", record_data.id)
+            }
+            PendingWrite::UpdateRecordsProfile(records_profile) => {
+                RecordsProfile::update_record(pool, &records_profile).await?;
+                ("records_profile", records_profile.id)
+            }
+            PendingWrite::InsertPhone(record_phone) => {
+                let record_phone_id = RecordPhones::create_record(pool, &record_phone).await?;
+                ("record_phones", record_phone_id)
+            }
+            PendingWrite::DeletePhone(record_phone) => {
+                RecordPhones::delete_record(pool, record_phone.id).await?;
+                ("record_phones", record_phone.id)
+            }
+        };
+
+        FieldProvenance::save_fields(pool, target_table, target_id, &update.source_table, update.source_id, &update.page_url, &update.fields).await;
+    }
+
+    for (records_data_id, (source_table, source_id)) in &phone_sources {
+        save_website_phones_version(pool, *records_data_id, source_table, *source_id).await?;
+    }
+
+    Ok(())
+}
+
+async fn save_records_data_versions(pool: &MySqlPool, record_data: &RecordsData, source_table: &str, source_id: i32) -> Result<(), Error> {
+    for (field, value) in [("phone", &record_data.phone), ("website", &record_data.website)] {
+        RecordFieldHistory::save_version(pool, &RecordFieldHistory::new(record_data.id, field, value, source_table, source_id)).await?;
+    }
+
+    Ok(())
+}
+
+// A record's website phones are a set, versioned as one sorted, comma separated value.
+async fn save_website_phones_version(pool: &MySqlPool, records_data_id: i32, source_table: &str, source_id: i32) -> Result<(), Error> {
+    let mut phones: Vec<String> = RecordPhones::get_records_by_records_data_id(pool, records_data_id)
+        .await?
+        .into_iter()
+        .map(|record_phone| record_phone.phone)
+        .collect();
+    phones.sort();
+
+    RecordFieldHistory::save_version(pool, &RecordFieldHistory::new(records_data_id, "website_phones", &phones.join(", "), source_table, source_id)).await?;
+
+    Ok(())
+}
+
+// reextract <extractor>... [--dry-run] [--yes]
+// Prints every changed value, then asks before writing unless --yes is given.
+pub async fn run_reextract(pool: &MySqlPool, args: &[String]) -> Result<(), Error> {
+    let dry_run = args.iter().any(|arg| arg == "--dry-run");
+    let assume_yes = args.iter().any(|arg| arg == "--yes");
+    let extractors: Vec<&str> = args.iter().filter(|arg| !arg.starts_with("--")).map(|arg| arg.as_str()).collect();
+
+    if extractors.is_empty() {
+        println!("Usage: reextract <extractor>... [--dry-run] [--yes]");
+        for (name, description) in EXTRACTORS.iter() {
+            println!("  {:<16} {}", name, description);
+        }
+        return Ok(());
+    }
+
+    for extractor in extractors {
+        let reextraction = match extractor {
+            "links" => reextract_links(pool).await?,
+            "records_data" => reextract_records_data(pool).await?,
+            "records_profile" => reextract_records_profile(pool).await?,
+            "phones" => reextract_phones(pool).await?,
+            _ => {
+                eprintln!("Unknown extractor: {}", extractor);
+                continue;
+            }
+        };
+
+        for change in &reextraction.changes {
+            println!("{}", change);
+        }
+        println!("{}: {} changed values in {} rows", extractor, reextraction.changes.len(), reextraction.updates.len());
+
+        if dry_run || reextraction.updates.is_empty() {
+            continue;
+        }
+
+        if !assume_yes && !confirm(&format!("Commit {} changes from {}?", reextraction.updates.len(), extractor))? {
+            println!("Skipped {}", extractor);
+            continue;
+        }
+
+        commit(pool, reextraction).await?;
+        println!("Committed {}", extractor);
+    }
+
+    Ok(())
+}
+
+fn confirm(question: &str) -> Result<bool, Error> {
+    print!("{} [y/N] ", question);
+    io::stdout().flush()?;
+
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+
+    Ok(answer.trim().eq_ignore_ascii_case("y") || answer.trim().eq_ignore_ascii_case("yes"))
+}
+
+fn optional_value<T: ToString>(value: Option<T>) -> String {
+    value.map(|value| value.to_string()).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data;
+
+    #[test]
+    fn should_diff_only_changed_fields() {
+        let changes = diff_fields(
+            "records_data",
+            12,
+            "1.0.0",
+            &[("phone", "<span>(905) 713-1230</span>".to_string()), ("website", "www.mcfees.com".to_string())],
+            &[("phone", "(905) 713-1230".to_string()), ("website", "www.mcfees.com".to_string())],
+        );
+
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].field, "phone");
+        assert_eq!(changes[0].old_value, "<span>(905) 713-1230</span>");
+        assert_eq!(changes[0].new_value, "(905) 713-1230");
+        assert_eq!(
+            changes[0].to_string(),
+            format!("records_data #12 phone: \"<span>(905) 713-1230</span>\" -> \"(905) 713-1230\" (1.0.0 -> {})", EXTRACTOR_VERSION)
+        );
+    }
+
+    #[test]
+    fn should_not_report_unchanged_fixture_values() {
+        let company_details = Extractor::new(data::test_generate_houzz_record_html()).get_company_details_houzz();
+        let stored = [("phone", "(905) 713-1230".to_string()), ("website", "www.mcfees.com".to_string())];
+        let extracted = [("phone", company_details.phone.clone()), ("website", company_details.website.clone())];
+
+        assert!(diff_fields("records_data", 1, EXTRACTOR_VERSION, &stored, &extracted).is_empty());
+    }
+}
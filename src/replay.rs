@@ -0,0 +1,245 @@
+use anyhow::{anyhow, Error};
+use async_trait::async_trait;
+use scraper::{Html, Selector};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use crate::storage::Storage;
+use crate::urls;
+use chrono::{DateTime, Utc};
+use crate::warc;
+
+// A page fetched on an earlier run. `selector` is set when only that element
+// was kept, as get_all_pages_houzz does for listings.
+#[derive(Clone, Debug)]
+pub struct StoredPage {
+    pub final_url: String,
+    pub html: String,
+    pub selector: Option<String>,
+}
+
+// Where a replay session finds pages instead of the browser.
+#[async_trait]
+pub trait PageSource: Send + Sync {
+    async fn find_page(&self, url: &str, selector: Option<&str>) -> Result<Option<StoredPage>, Error>;
+}
+
+// Answers one fetch from the source: the URL the page ended up on and the HTML,
+// narrowed to `selector` when the stored page holds more than that element.
+pub async fn replay(source: &dyn PageSource, url: &str, selector: Option<&str>) -> Result<(String, String), Error> {
+    let page = source
+        .find_page(url, selector)
+        .await?
+        .ok_or_else(|| anyhow!("No stored page for {}", url))?;
+
+    let html = match selector {
+        Some(selector) if page.selector.as_deref() != Some(selector) => select_element(&page.html, selector)?,
+        _ => page.html,
+    };
+
+    Ok((page.final_url, html))
+}
+
+// Same errors as the browser: nothing matching is a failed fetch, not an empty page.
+fn select_element(html: &str, selector: &str) -> Result<String, Error> {
+    let parsed = Selector::parse(selector).map_err(|e| anyhow!("Invalid selector {}: {:?}", selector, e))?;
+    let document = Html::parse_document(html);
+
+    document
+        .select(&parsed)
+        .next()
+        .map(|element| element.html())
+        .ok_or_else(|| anyhow!("no element found matching selector {}", selector))
+}
+
+// Pages from WARC files written with WARC_DIR. Only where each record starts is
+// kept in memory; the page is read from its file when a stage asks for it.
+// The latest fetch of a URL wins.
+pub struct ArchivePages {
+    index: HashMap<String, ArchivedPage>,
+}
+
+struct ArchivedPage {
+    path: PathBuf,
+    offset: u64,
+    date: DateTime<Utc>,
+}
+
+impl ArchivePages {
+    // `path` is a single WARC file or a directory of them.
+    pub fn open(path: &Path) -> Result<ArchivePages, Error> {
+        let mut paths = Vec::new();
+        if path.is_dir() {
+            for entry in fs::read_dir(path)? {
+                let entry_path = entry?.path();
+                if entry_path.extension().and_then(|extension| extension.to_str()) == Some("warc") {
+                    paths.push(entry_path);
+                }
+            }
+            paths.sort();
+        } else {
+            paths.push(path.to_path_buf());
+        }
+
+        let mut index: HashMap<String, ArchivedPage> = HashMap::new();
+        for path in paths {
+            let mut reader = warc::read_file(&path)?;
+
+            while let Some(entry) = reader.next_index_entry()? {
+                let newer = match index.get(&entry.target_uri) {
+                    Some(existing) => entry.date >= existing.date,
+                    None => true,
+                };
+                if newer {
+                    index.insert(entry.target_uri, ArchivedPage { path: path.clone(), offset: entry.offset, date: entry.date });
+                }
+            }
+        }
+
+        Ok(ArchivePages { index })
+    }
+}
+
+#[async_trait]
+impl PageSource for ArchivePages {
+    async fn find_page(&self, url: &str, _selector: Option<&str>) -> Result<Option<StoredPage>, Error> {
+        let page = match self.index.get(url) {
+            Some(page) => page,
+            None => return Ok(None),
+        };
+
+        let record = warc::read_record_at(&page.path, page.offset)?;
+        Ok(Some(StoredPage {
+            final_url: record.final_uri,
+            html: record.html,
+            selector: record.selector,
+        }))
+    }
+}
+
+// Pages already in the database: listings by query, detail pages by link,
+// crawled website pages by URL and website main pages by website. Contact pages
+// are stored without their URL, so that stage cannot be replayed from here.
+pub struct StoredPages {
+    storage: Arc<dyn Storage>,
+}
+
+impl StoredPages {
+    pub fn new(storage: Arc<dyn Storage>) -> StoredPages {
+        StoredPages { storage }
+    }
+}
+
+#[async_trait]
+impl PageSource for StoredPages {
+    async fn find_page(&self, url: &str, selector: Option<&str>) -> Result<Option<StoredPage>, Error> {
+        if selector == Some(warc::HOUZZ_LISTING_SELECTOR) {
+            return Ok(self.storage.get_page_by_query(url).await?.map(|page| StoredPage {
+                final_url: url.to_string(),
+                html: page.html.unwrap_or_default(),
+                selector: Some(warc::HOUZZ_LISTING_SELECTOR.to_string()),
+            }));
+        }
+
+        if let Some(record_html) = self.storage.get_records_html_by_link(url).await? {
+            return Ok(Some(StoredPage { final_url: url.to_string(), html: record_html.html, selector: None }));
+        }
+
+        // The crawl stage keeps website pages under the canonical URL it landed on.
+        let canonical_url = urls::canonicalize_url(url).unwrap_or_else(|| url.to_string());
+        for candidate in [url, canonical_url.as_str()] {
+            if let Some(page) = self.storage.get_website_page_by_url(candidate).await? {
+                return Ok(Some(StoredPage { final_url: page.url, html: page.html, selector: None }));
+            }
+        }
+
+        let website = match self.storage.get_website_by_url(url).await? {
+            Some(website) => website,
+            None => return Ok(None),
+        };
+
+        if !website.main_page_html.is_empty() {
+            return Ok(Some(StoredPage {
+                final_url: website.final_url.unwrap_or_else(|| url.to_string()),
+                html: website.main_page_html,
+                selector: None,
+            }));
+        }
+
+        // Crawled websites keep their pages in website_pages; the first one is the start page.
+        Ok(self.storage.get_website_pages(website.id).await?.into_iter().next().map(|page| StoredPage {
+            final_url: page.url,
+            html: page.html,
+            selector: None,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use crate::links_to_record_details::LinksToRecordDetails;
+    use crate::memory_storage::MemoryStorage;
+    use crate::records_html::RecordsHtml;
+    use crate::storage::{LinksRepository, RecordsHtmlRepository};
+    use crate::warc::{WarcConfig, WarcRecord, WarcWriter};
+    use std::env;
+    use uuid::Uuid;
+
+    fn record(url: &str, selector: Option<&str>, html: &str, day: u32) -> WarcRecord {
+        WarcRecord {
+            target_uri: url.to_string(),
+            final_uri: format!("{}/", url),
+            date: Utc.with_ymd_and_hms(2024, 3, day, 0, 0, 0).unwrap(),
+            selector: selector.map(|selector| selector.to_string()),
+            html: html.to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn should_replay_latest_archived_page_and_narrow_to_selector() {
+        let directory = env::temp_dir().join(format!("replay-test-{}", Uuid::new_v4()));
+        let writer = WarcWriter::new(WarcConfig { directory: directory.clone(), prefix: "test".to_string(), max_file_bytes: 1 }).unwrap();
+        writer.write(&record("https://example.com", None, "<body><div class=\"pro-results\">new</div></body>", 2)).unwrap();
+        writer.write(&record("https://example.com", None, "<body><div class=\"pro-results\">old</div></body>", 1)).unwrap();
+        let source = ArchivePages::open(&directory).unwrap();
+
+        let (final_url, html) = replay(&source, "https://example.com", None).await.unwrap();
+        assert_eq!(final_url, "https://example.com/");
+        assert!(html.contains("new"));
+
+        let (_, element) = replay(&source, "https://example.com", Some(".pro-results")).await.unwrap();
+        assert_eq!(element, "<div class=\"pro-results\">new</div>");
+
+        assert!(replay(&source, "https://example.com", Some("#business")).await.is_err());
+        assert!(replay(&source, "https://example.org", None).await.is_err());
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn should_replay_detail_pages_from_storage() {
+        let storage = Arc::new(MemoryStorage::new());
+        let link = LinksToRecordDetails {
+            id: 0,
+            pages_with_all_records_id: 1,
+            company: "Acme".to_string(),
+            link: "https://www.houzz.com/pro/acme".to_string(),
+            visited: 0,
+            extractor_version: "".to_string(),
+        };
+        storage.upsert_link(&link).await.unwrap();
+        let link = storage.get_link_by_url(&link.link).await.unwrap().unwrap();
+        let record_html = RecordsHtml { id: 0, link_to_record_details_id: link.id, html: "<body>Acme</body>".to_string(), processed: 0 };
+        storage.save_record_html(&record_html, &link).await.unwrap();
+
+        let source = StoredPages::new(storage);
+
+        let (final_url, html) = replay(&source, "https://www.houzz.com/pro/acme", None).await.unwrap();
+        assert_eq!(final_url, "https://www.houzz.com/pro/acme");
+        assert_eq!(html, "<body>Acme</body>");
+        assert!(replay(&source, "https://www.houzz.com/pro/other", None).await.is_err());
+    }
+}
@@ -1,17 +1,46 @@
-use std::any::Any;
+use std::sync::Arc;
 
-use fantoccini::{Client, Locator, ClientBuilder};
+use fantoccini::{Client, ClientBuilder};
 use anyhow::Error;
 use serde_json::json;
+use crate::replay::PageSource;
+use crate::warc::WarcWriter;
+
+// A slot of the scheduler: a live browser, or stored pages answering instead of one.
+#[derive(Clone)]
+pub enum Session {
+    Browser(Client),
+    Replay(Arc<dyn PageSource>),
+}
+
+impl Session {
+    pub async fn session_id(&self) -> Option<String> {
+        match self {
+            Session::Browser(client) => client.session_id().await.ok().flatten(),
+            Session::Replay(_) => None,
+        }
+    }
+}
 
 #[derive(Clone)]
 pub struct Scheduler{
-    pub clients: Vec<Client>,
-    pub used: Vec<bool>
+    pub clients: Vec<Session>,
+    pub used: Vec<bool>,
+    // Handed to every Scrapper of the stage; None when WARC_DIR is not set.
+    pub archive: Option<Arc<WarcWriter>>,
 }
 
 impl Scheduler {
     pub fn new(clients: Vec<Client>) -> Self {
+        Scheduler::from_sessions(clients.into_iter().map(Session::Browser).collect())
+    }
+
+    // `slots` replay sessions over one source, so stages keep their concurrency.
+    pub fn replay(source: Arc<dyn PageSource>, slots: usize) -> Self {
+        Scheduler::from_sessions((0..slots).map(|_| Session::Replay(Arc::clone(&source))).collect())
+    }
+
+    fn from_sessions(clients: Vec<Session>) -> Self {
         let mut used = Vec::new();
         for _ in 0..clients.len() {
             used.push(false);
@@ -19,11 +48,17 @@ impl Scheduler {
 
         Self {
             clients: clients,
-            used: used
+            used: used,
+            archive: None,
         }
     }
 
-    pub async fn get_client(&mut self) -> Result<&Client, Error> {
+    pub fn with_archive(mut self, archive: Option<Arc<WarcWriter>>) -> Self {
+        self.archive = archive;
+        self
+    }
+
+    pub async fn get_client(&mut self) -> Result<&Session, Error> {
         for i in 0..self.clients.len() {
             if !self.used[i] {
                 self.used[i] = true;
@@ -35,19 +70,30 @@ impl Scheduler {
         Err(anyhow::anyhow!("No available clients"))
     }
 
-    pub async fn release_client(&mut self, client: &Client) -> Result<(), Error> {
-        let mut index = None;
+    // Replay sessions are interchangeable, so any used slot can be handed back.
+    async fn find_slot(&self, client: &Session) -> Result<Option<usize>, Error> {
+        let client = match client {
+            Session::Browser(client) => client,
+            Session::Replay(_) => return Ok(self.used.iter().position(|used| *used)),
+        };
 
         let client_session_id = client.session_id().await?.unwrap();
 
         for i in 0..self.clients.len() {
-            let current_client_session_id = self.clients[i].session_id().await?.unwrap();
+            if let Session::Browser(current_client) = &self.clients[i] {
+                let current_client_session_id = current_client.session_id().await?.unwrap();
 
-            if current_client_session_id == client_session_id {
-                index = Some(i);
-                break;
+                if current_client_session_id == client_session_id {
+                    return Ok(Some(i));
+                }
             }
         }
+
+        Ok(None)
+    }
+
+    pub async fn release_client(&mut self, client: &Session) -> Result<(), Error> {
+        let index = self.find_slot(client).await?;
         
         match index {
             Some(index) => {
@@ -59,21 +105,16 @@ impl Scheduler {
         }
     }
 
-    pub async fn replace_client(&mut self, client: &Client, headless: bool) -> Result<(), Error> {
+    pub async fn replace_client(&mut self, session: &Session, headless: bool) -> Result<(), Error> {
         let ports = [4444, 4445, 4446, 4447, 4448, 4449, 4450, 4451, 4452, 4453, 4454, 4455];
 
-        let mut index = None;
-
-        let client_session_id = client.session_id().await?.unwrap();
-
-        for i in 0..self.clients.len() {
-            let current_client_session_id = self.clients[i].session_id().await?.unwrap();
+        let index = self.find_slot(session).await?;
 
-            if current_client_session_id == client_session_id {
-                index = Some(i);
-                break;
-            }
-        }
+        // Nothing to reconnect when replaying.
+        let client = match session {
+            Session::Browser(client) => client,
+            Session::Replay(_) => return self.release_client(session).await,
+        };
         
         let mut caps = serde_json::map::Map::new();
 
@@ -108,7 +149,7 @@ impl Scheduler {
 
         match index {
             Some(index) => {
-                self.clients[index] = new_client;
+                self.clients[index] = Session::Browser(new_client);
                 self.used[index] = false;
                 Ok(())
             },
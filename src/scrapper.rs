@@ -1,47 +1,122 @@
-use fantoccini::{Client, Locator};
-use anyhow::Result;
+use fantoccini::Locator;
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use std::sync::{Arc, Mutex};
+use crate::replay;
+use crate::scheduler::Session;
+use crate::warc::{WarcRecord, WarcWriter};
 
 
 pub struct Scrapper<'a> {
-    pub client: &'a Client,
+    pub session: &'a Session,
+    pub archive: Option<Arc<WarcWriter>>,
+    // Where the last replayed page ended up; the browser tracks this itself.
+    replay_url: Mutex<Option<String>>,
 }
 
 impl<'a> Scrapper<'a> {
-    pub fn new(client: &'a Client) -> Self {
+    pub fn new(session: &'a Session) -> Self {
         Self {
-            client,
+            session,
+            archive: None,
+            replay_url: Mutex::new(None),
         }
     }
 
+    // Every page fetched is also appended to the archive.
+    pub fn with_archive(mut self, archive: Option<Arc<WarcWriter>>) -> Self {
+        self.archive = archive;
+        self
+    }
+
     pub async fn close(&self) -> Result<()> {
-        self.client.clone().close().await?;
+        if let Session::Browser(client) = self.session {
+            client.clone().close().await?;
+        }
         Ok(())
     }
 
     pub async fn get_body(&self, url: &str) -> Result<String> {
-        self.client.goto(url).await?;
-        let body = self.client.find(Locator::Css("body")).await?.html(false).await?;
+        let client = match self.session {
+            Session::Browser(client) => client,
+            Session::Replay(source) => return self.replay(source.as_ref(), url, None).await,
+        };
+
+        client.goto(url).await?;
+        let body = client.find(Locator::Css("body")).await?.html(false).await?;
         
         if body != "" {
             println!("Got body");
         } else {
             println!("No body");
         }
+
+        self.archive_page(url, None, &body).await;
         
         Ok(body)
     }
 
+    // The URL the browser ended up on after redirects.
+    pub async fn current_url(&self) -> Result<String> {
+        let client = match self.session {
+            Session::Browser(client) => client,
+            Session::Replay(_) => return self.replay_url()?.ok_or_else(|| anyhow!("No page replayed yet")),
+        };
+
+        let url = client.current_url().await?;
+        Ok(url.to_string())
+    }
+
     pub async fn get_element_html(&self, url: &str, selector: &str) -> Result<String> {
-        self.client.goto(url).await?;
-        let element = self.client.find(Locator::Css(selector)).await?.html(false).await?;
+        let client = match self.session {
+            Session::Browser(client) => client,
+            Session::Replay(source) => return self.replay(source.as_ref(), url, Some(selector)).await,
+        };
+
+        client.goto(url).await?;
+        let element = client.find(Locator::Css(selector)).await?.html(false).await?;
         
         if element != "" {
             println!("Got element");
         } else {
             println!("No element");
         }
+
+        self.archive_page(url, Some(selector), &element).await;
         
         Ok(element)
     }
 
+    // Replayed pages are already stored, so they are not archived again.
+    async fn replay(&self, source: &dyn replay::PageSource, url: &str, selector: Option<&str>) -> Result<String> {
+        let (final_url, html) = replay::replay(source, url, selector).await?;
+        *self.replay_url.lock().map_err(|_| anyhow!("Replay URL lock poisoned"))? = Some(final_url);
+
+        Ok(html)
+    }
+
+    fn replay_url(&self) -> Result<Option<String>> {
+        Ok(self.replay_url.lock().map_err(|_| anyhow!("Replay URL lock poisoned"))?.clone())
+    }
+
+    // Archive failures are logged and never fail the fetch.
+    async fn archive_page(&self, url: &str, selector: Option<&str>, html: &str) {
+        let archive = match &self.archive {
+            Some(archive) => archive,
+            None => return,
+        };
+
+        let record = WarcRecord {
+            target_uri: url.to_string(),
+            final_uri: self.current_url().await.unwrap_or_else(|_| url.to_string()),
+            date: Utc::now(),
+            selector: selector.map(|selector| selector.to_string()),
+            html: html.to_string(),
+        };
+
+        if let Err(e) = archive.write(&record) {
+            eprintln!("Error archiving {}: {:?}", url, e);
+        }
+    }
+
 }   
\ No newline at end of file
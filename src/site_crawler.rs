@@ -0,0 +1,245 @@
+use scraper::{Html, Selector};
+use std::collections::HashSet;
+use url::Url;
+use crate::contact_links;
+use crate::urls::{self, UrlResolver};
+
+// Pages worth visiting on a contractor site, and pages that never have contact details.
+const LINK_KEYWORDS: [(&str, i32); 15] = [
+    ("quote", 70),
+    ("estimate", 60),
+    ("about", 60),
+    ("team", 50),
+    ("staff", 40),
+    ("people", 30),
+    ("location", 30),
+    ("reach", 30),
+    ("blog", -30),
+    ("news", -20),
+    ("privacy", -50),
+    ("terms", -50),
+    ("login", -50),
+    ("cart", -50),
+    ("wp-admin", -100),
+];
+
+const SKIPPED_EXTENSIONS: [&str; 14] = [
+    ".pdf", ".jpg", ".jpeg", ".png", ".gif", ".svg", ".webp", ".zip", ".doc", ".docx", ".mp4", ".mp3", ".css", ".js",
+];
+
+#[derive(Clone, Debug)]
+pub struct CrawlConfig {
+    pub max_depth: u32,
+    pub max_pages: usize,
+}
+
+impl Default for CrawlConfig {
+    fn default() -> Self {
+        Self {
+            max_depth: 2,
+            max_pages: 8,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CrawlCandidate {
+    pub url: String,
+    pub depth: u32,
+    pub score: i32,
+}
+
+// Same-domain frontier for one business site. Highest scoring links are visited first.
+pub struct CrawlFrontier {
+    config: CrawlConfig,
+    host: String,
+    queue: Vec<CrawlCandidate>,
+    seen: HashSet<String>,
+    visited: usize,
+}
+
+impl CrawlFrontier {
+    pub fn new(start_url: &str, config: CrawlConfig) -> Option<Self> {
+        let start_url = urls::canonicalize_url(start_url)?;
+        let url = Url::parse(&start_url).ok()?;
+        let host = site_host(&url)?;
+
+        let mut seen = HashSet::new();
+        seen.insert(seen_key(&url));
+
+        Some(Self {
+            config,
+            host,
+            queue: vec![CrawlCandidate {
+                url: start_url,
+                depth: 0,
+                score: i32::MAX,
+            }],
+            seen,
+            visited: 0,
+        })
+    }
+
+    pub fn next(&mut self) -> Option<CrawlCandidate> {
+        if self.visited >= self.config.max_pages || self.queue.is_empty() {
+            return None;
+        }
+
+        // Stable pick: on equal scores the link found first wins.
+        let mut best = 0;
+        for (index, candidate) in self.queue.iter().enumerate() {
+            if candidate.score > self.queue[best].score {
+                best = index;
+            }
+        }
+
+        self.visited += 1;
+        Some(self.queue.remove(best))
+    }
+
+    pub fn add_links(&mut self, page_url: &str, html: &str, depth: u32) {
+        if depth + 1 > self.config.max_depth {
+            return;
+        }
+
+        let resolver = match UrlResolver::new(page_url, html) {
+            Some(resolver) => resolver,
+            None => return,
+        };
+
+        let document = Html::parse_document(html);
+        let link_selector = Selector::parse("a[href]").unwrap();
+
+        for element in document.select(&link_selector) {
+            let href = element.value().attr("href").unwrap_or_default();
+            let url = match resolver.resolve(href).and_then(|url| Url::parse(&url).ok()) {
+                Some(url) => url,
+                None => continue,
+            };
+
+            if site_host(&url).as_deref() != Some(self.host.as_str()) {
+                continue;
+            }
+
+            let path = url.path().to_lowercase();
+            if SKIPPED_EXTENSIONS.iter().any(|extension| path.ends_with(extension)) {
+                continue;
+            }
+
+            if !self.seen.insert(seen_key(&url)) {
+                continue;
+            }
+            let url = url.to_string();
+
+            let text = element.text().collect::<String>();
+            let score = score_link(&url, &text) - 10 * (depth as i32 + 1);
+
+            self.queue.push(CrawlCandidate {
+                url,
+                depth: depth + 1,
+                score,
+            });
+        }
+    }
+}
+
+pub fn score_link(url: &str, text: &str) -> i32 {
+    let haystack = format!("{} {}", url, text).to_lowercase();
+
+    let keyword_score: i32 = LINK_KEYWORDS
+        .iter()
+        .filter(|(keyword, _)| haystack.contains(keyword))
+        .map(|(_, score)| score)
+        .sum();
+
+    if contact_links::matches_contact_keyword(&haystack) {
+        keyword_score + 100
+    } else {
+        keyword_score
+    }
+}
+
+fn site_host(url: &Url) -> Option<String> {
+    let host = url.host_str()?.to_lowercase();
+    Some(host.trim_start_matches("www.").to_string())
+}
+
+// www and non-www are the same page.
+fn seen_key(url: &Url) -> String {
+    let host = site_host(url).unwrap_or_default();
+    match url.query() {
+        Some(query) => format!("{}{}?{}", host, url.path(), query),
+        None => format!("{}{}", host, url.path()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static HTML_HOME: &str = r#"
+    <nav>
+        <a href="/">Home</a>
+        <a href="/services/">Services</a>
+        <a href="/blog/">Blog</a>
+        <a href="about-us/">About Us</a>
+        <a href="https://www.letslandscape.ca/contact-us/#form">Contact Us</a>
+        <a href="https://letslandscape.ca/contact-us/">Contact</a>
+        <a href="/request-a-consultation/">Get a Quote</a>
+        <a href="/brochure.pdf">Brochure</a>
+        <a href="mailto:info@letslandscape.ca">Email</a>
+        <a href="https://www.facebook.com/letslandscape">Facebook</a>
+    </nav>
+"#;
+
+    static HTML_ABOUT: &str = r#"
+    <a href="/team/">Meet the team</a>
+    <a href="/about-us/">About Us</a>
+"#;
+
+    #[test]
+    fn should_visit_highest_scoring_pages_first() {
+        let mut frontier = CrawlFrontier::new("https://letslandscape.ca/", CrawlConfig::default()).unwrap();
+
+        let home = frontier.next().unwrap();
+        assert_eq!(home.url, "https://letslandscape.ca/");
+        assert_eq!(home.depth, 0);
+
+        frontier.add_links(&home.url, HTML_HOME, home.depth);
+
+        let urls: Vec<String> = std::iter::from_fn(|| frontier.next()).map(|candidate| candidate.url).collect();
+        assert_eq!(urls, vec![
+            "https://www.letslandscape.ca/contact-us/",
+            "https://letslandscape.ca/request-a-consultation/",
+            "https://letslandscape.ca/about-us/",
+            "https://letslandscape.ca/services/",
+            "https://letslandscape.ca/blog/",
+        ]);
+    }
+
+    #[test]
+    fn should_respect_max_depth_and_max_pages() {
+        let config = CrawlConfig {
+            max_depth: 1,
+            max_pages: 3,
+        };
+        let mut frontier = CrawlFrontier::new("https://letslandscape.ca/", config).unwrap();
+
+        let home = frontier.next().unwrap();
+        frontier.add_links(&home.url, HTML_HOME, home.depth);
+
+        let contact = frontier.next().unwrap();
+        frontier.add_links(&contact.url, HTML_ABOUT, contact.depth);
+
+        assert!(frontier.next().is_some());
+        assert!(frontier.next().is_none());
+        assert!(!frontier.queue.iter().any(|candidate| candidate.url.ends_with("/team/")));
+    }
+
+    #[test]
+    fn should_score_links_by_keywords() {
+        assert!(score_link("https://mcfees.com/contact", "") > score_link("https://mcfees.com/about", ""));
+        assert!(score_link("https://mcfees.com/x", "Get a Quote") > 0);
+        assert!(score_link("https://mcfees.com/privacy-policy", "Privacy") < 0);
+    }
+}
@@ -0,0 +1,177 @@
+use scraper::{Html, Selector};
+use std::collections::HashSet;
+use url::Url;
+use crate::urls::UrlResolver;
+
+// Paths that belong to share buttons, embeds and posts rather than to the company's profile.
+const FACEBOOK_SKIPPED: [&str; 10] = [
+    "sharer", "sharer.php", "share", "share.php", "dialog", "plugins", "tr", "login", "events", "photo.php",
+];
+const INSTAGRAM_SKIPPED: [&str; 6] = ["p", "reel", "reels", "explore", "accounts", "stories"];
+const LINKEDIN_SKIPPED: [&str; 4] = ["sharearticle", "sharing", "feed", "login"];
+const YOUTUBE_SKIPPED: [&str; 5] = ["watch", "embed", "results", "playlist", "shorts"];
+const X_SKIPPED: [&str; 6] = ["intent", "share", "home", "hashtag", "search", "i"];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SocialProfile {
+    pub network: String,
+    pub url: String,
+}
+
+pub fn find_social_profiles(html: &str, page_url: &str) -> Vec<SocialProfile> {
+    let document = Html::parse_document(html);
+    let link_selector = Selector::parse("a[href]").unwrap();
+    let resolver = UrlResolver::new(page_url, html);
+
+    let mut profiles = Vec::new();
+    let mut seen = HashSet::new();
+
+    for element in document.select(&link_selector) {
+        let href = element.value().attr("href").unwrap_or_default();
+        let href = match resolver.as_ref().and_then(|resolver| resolver.resolve(href)) {
+            Some(href) => href,
+            None => href.to_string(),
+        };
+
+        if let Some(profile) = normalize_social_url(&href) {
+            if seen.insert(profile.url.clone()) {
+                profiles.push(profile);
+            }
+        }
+    }
+
+    profiles
+}
+
+// Canonical profile URL for a social link, or None for share buttons, posts and non-social links.
+pub fn normalize_social_url(href: &str) -> Option<SocialProfile> {
+    let url = Url::parse(href.trim()).ok()?;
+
+    if url.scheme() != "http" && url.scheme() != "https" {
+        return None;
+    }
+
+    let host = url.host_str()?.to_lowercase();
+    let host = host.trim_start_matches("www.").trim_start_matches("m.").trim_start_matches("mobile.");
+    let segments: Vec<&str> = url
+        .path_segments()
+        .map(|segments| segments.filter(|segment| !segment.is_empty()).collect())
+        .unwrap_or_default();
+    let first = segments.first().copied().unwrap_or_default();
+
+    match host {
+        "facebook.com" | "fb.com" | "business.facebook.com" => {
+            if first == "profile.php" {
+                let id = url.query_pairs().find(|(key, _)| key == "id")?.1.into_owned();
+                return Some(profile("facebook", format!("https://www.facebook.com/profile.php?id={}", id)));
+            }
+
+            if first.is_empty() || FACEBOOK_SKIPPED.contains(&first.to_lowercase().as_str()) {
+                return None;
+            }
+
+            let path = if first == "pages" || first == "pg" { segments.join("/") } else { first.to_string() };
+            Some(profile("facebook", format!("https://www.facebook.com/{}", path)))
+        }
+        "instagram.com" => {
+            if first.is_empty() || INSTAGRAM_SKIPPED.contains(&first.to_lowercase().as_str()) {
+                return None;
+            }
+
+            Some(profile("instagram", format!("https://www.instagram.com/{}", first.to_lowercase())))
+        }
+        "linkedin.com" | "ca.linkedin.com" => {
+            if LINKEDIN_SKIPPED.contains(&first.to_lowercase().as_str()) {
+                return None;
+            }
+
+            match (first, segments.get(1)) {
+                ("company", Some(name)) | ("in", Some(name)) | ("school", Some(name)) => {
+                    Some(profile("linkedin", format!("https://www.linkedin.com/{}/{}", first, name.to_lowercase())))
+                }
+                _ => None,
+            }
+        }
+        "youtube.com" => {
+            if first.is_empty() || YOUTUBE_SKIPPED.contains(&first.to_lowercase().as_str()) {
+                return None;
+            }
+
+            if first.starts_with('@') {
+                return Some(profile("youtube", format!("https://www.youtube.com/{}", first)));
+            }
+
+            match (first, segments.get(1)) {
+                ("channel", Some(name)) | ("c", Some(name)) | ("user", Some(name)) => {
+                    Some(profile("youtube", format!("https://www.youtube.com/{}/{}", first, name)))
+                }
+                _ => None,
+            }
+        }
+        "twitter.com" | "x.com" => {
+            if first.is_empty() || X_SKIPPED.contains(&first.to_lowercase().as_str()) || first.ends_with(".php") {
+                return None;
+            }
+
+            Some(profile("x", format!("https://x.com/{}", first.to_lowercase())))
+        }
+        _ => None,
+    }
+}
+
+fn profile(network: &str, url: String) -> SocialProfile {
+    SocialProfile {
+        network: network.to_string(),
+        url,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static HTML_SOCIAL: &str = r#"
+    <footer>
+        <a href="https://www.facebook.com/McFeeLandscaping/?ref=page_internal&utm_source=site">Facebook</a>
+        <a href="https://m.facebook.com/McFeeLandscaping">Facebook mobile</a>
+        <a href="https://www.facebook.com/sharer/sharer.php?u=https%3A%2F%2Fmcfees.com">Share</a>
+        <a href="https://instagram.com/McFeesLandscape/">Instagram</a>
+        <a href="https://www.instagram.com/p/CxY12ab/">Latest post</a>
+        <a href="https://ca.linkedin.com/company/mcfee-construction/about/?trk=x">LinkedIn</a>
+        <a href="https://www.linkedin.com/shareArticle?mini=true&url=https://mcfees.com">Share on LinkedIn</a>
+        <a href="https://www.youtube.com/@mcfeelandscaping">YouTube</a>
+        <a href="https://www.youtube.com/watch?v=dQw4w9WgXcQ">Project video</a>
+        <a href="https://twitter.com/intent/tweet?url=https://mcfees.com">Tweet</a>
+        <a href="https://twitter.com/McFeeLandscape?lang=en">Twitter</a>
+        <a href="/contact">Contact</a>
+    </footer>
+"#;
+
+    #[test]
+    fn should_extract_social_profiles() {
+        let profiles = find_social_profiles(HTML_SOCIAL, "https://mcfees.com/");
+        let profiles: Vec<(&str, &str)> = profiles.iter().map(|p| (p.network.as_str(), p.url.as_str())).collect();
+
+        assert_eq!(profiles, vec![
+            ("facebook", "https://www.facebook.com/McFeeLandscaping"),
+            ("instagram", "https://www.instagram.com/mcfeeslandscape"),
+            ("linkedin", "https://www.linkedin.com/company/mcfee-construction"),
+            ("youtube", "https://www.youtube.com/@mcfeelandscaping"),
+            ("x", "https://x.com/mcfeelandscape"),
+        ]);
+    }
+
+    #[test]
+    fn should_skip_share_buttons() {
+        assert_eq!(normalize_social_url("https://www.facebook.com/sharer.php?u=x"), None);
+        assert_eq!(normalize_social_url("https://x.com/share?text=hi"), None);
+        assert_eq!(normalize_social_url("https://www.facebook.com/"), None);
+        assert_eq!(normalize_social_url("https://mcfees.com/facebook"), None);
+    }
+
+    #[test]
+    fn should_keep_numeric_facebook_profiles() {
+        let profile = normalize_social_url("https://www.facebook.com/profile.php?id=100063&sk=about").unwrap();
+        assert_eq!(profile.url, "https://www.facebook.com/profile.php?id=100063");
+    }
+}
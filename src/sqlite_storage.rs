@@ -0,0 +1,876 @@
+use anyhow::{anyhow, Error};
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use std::sync::Mutex;
+use crate::extraction_fill_rates::ExtractionFillRates;
+use crate::extractor::canonical_phone;
+use crate::field_provenance::FieldProvenance;
+use crate::links_to_record_details::LinksToRecordDetails;
+use crate::pages_with_all_records::PagesWithAllRecords;
+use crate::record_emails::RecordEmails;
+use crate::records_data::RecordsData;
+use crate::record_field_history::RecordFieldHistory;
+use crate::records_html::RecordsHtml;
+use crate::storage::{
+    ExtractionLogRepository, LinksRepository, PagesRepository, RecordEmailsRepository, RecordsDataRepository, RecordsHtmlRepository,
+    WebsitesRepository,
+};
+use crate::urls;
+use crate::website_pages::WebsitePages;
+use crate::websites_html::WebsitesHtml;
+
+const SCHEMA: &str = include_str!("../migrations/sqlite/schema.sql");
+
+// Storage in a single SQLite file, for small crawls and tests without a MySQL server.
+// rusqlite is blocking; every call is short, so it runs inline under a mutex.
+pub struct SqliteStorage {
+    connection: Mutex<Connection>,
+}
+
+impl SqliteStorage {
+    pub fn open(path: &str) -> Result<Self, Error> {
+        SqliteStorage::from_connection(Connection::open(path)?)
+    }
+
+    #[cfg(test)]
+    pub fn open_in_memory() -> Result<Self, Error> {
+        SqliteStorage::from_connection(Connection::open_in_memory()?)
+    }
+
+    fn from_connection(connection: Connection) -> Result<Self, Error> {
+        connection.execute_batch(SCHEMA)?;
+
+        Ok(Self {
+            connection: Mutex::new(connection),
+        })
+    }
+
+    fn with_connection<T>(&self, f: impl FnOnce(&Connection) -> rusqlite::Result<T>) -> Result<T, Error> {
+        let connection = self.connection.lock().map_err(|_| anyhow!("SQLite connection lock poisoned"))?;
+
+        Ok(f(&connection)?)
+    }
+}
+
+fn page_from_row(row: &Row) -> rusqlite::Result<PagesWithAllRecords> {
+    Ok(PagesWithAllRecords {
+        id: row.get("id")?,
+        page: row.get("page")?,
+        district: row.get("district")?,
+        query: row.get("query")?,
+        html: row.get("html")?,
+        processed: row.get("processed")?,
+    })
+}
+
+fn link_from_row(row: &Row) -> rusqlite::Result<LinksToRecordDetails> {
+    Ok(LinksToRecordDetails {
+        id: row.get("id")?,
+        pages_with_all_records_id: row.get("pages_with_all_records_id")?,
+        company: row.get("company")?,
+        link: row.get("link")?,
+        visited: row.get("visited")?,
+        extractor_version: row.get("extractor_version")?,
+    })
+}
+
+fn records_html_from_row(row: &Row) -> rusqlite::Result<RecordsHtml> {
+    Ok(RecordsHtml {
+        id: row.get("id")?,
+        link_to_record_details_id: row.get("link_to_record_details_id")?,
+        html: row.get("html")?,
+        processed: row.get("processed")?,
+    })
+}
+
+fn records_data_from_row(row: &Row) -> rusqlite::Result<RecordsData> {
+    Ok(RecordsData {
+        id: row.get("id")?,
+        records_html_id: row.get("records_html_id")?,
+        email: row.get("email")?,
+        phone: row.get("phone")?,
+        website: row.get("website")?,
+        contact_us_link: row.get("contact_us_link")?,
+        company: row.get("company")?,
+        company_verified: row.get("company_verified")?,
+        extractor_version: row.get("extractor_version")?,
+    })
+}
+
+fn website_from_row(row: &Row) -> rusqlite::Result<WebsitesHtml> {
+    Ok(WebsitesHtml {
+        id: row.get("id")?,
+        records_data_id: row.get("records_data_id")?,
+        website: row.get("website")?,
+        main_page_html: row.get("main_page_html")?,
+        contact_page_html: row.get::<_, Option<String>>("contact_page_html")?.unwrap_or_default(),
+        final_url: row.get("final_url")?,
+    })
+}
+
+fn website_page_from_row(row: &Row) -> rusqlite::Result<WebsitePages> {
+    Ok(WebsitePages {
+        id: row.get("id")?,
+        websites_html_id: row.get("websites_html_id")?,
+        url: row.get("url")?,
+        depth: row.get("depth")?,
+        score: row.get("score")?,
+        html: row.get("html")?,
+    })
+}
+
+fn record_email_from_row(row: &Row) -> rusqlite::Result<RecordEmails> {
+    let first_seen: Option<String> = row.get("first_seen")?;
+
+    Ok(RecordEmails {
+        id: row.get("id")?,
+        record_id: row.get("record_id")?,
+        email: row.get("email")?,
+        source_page: row.get("source_page")?,
+        source_type: row.get("source_type")?,
+        first_seen: first_seen.and_then(|first_seen| NaiveDateTime::parse_from_str(&first_seen, "%Y-%m-%d %H:%M:%S").ok()),
+        confidence: row.get("confidence")?,
+    })
+}
+
+// Statements shared by the single-row calls and the transactional units of work.
+fn mark_page_processed(connection: &Connection, page: &PagesWithAllRecords) -> rusqlite::Result<()> {
+    connection.execute("UPDATE pages_with_all_records SET processed = 1 WHERE id = ?", params![page.id])?;
+    Ok(())
+}
+
+fn upsert_link(connection: &Connection, link: &LinksToRecordDetails) -> rusqlite::Result<bool> {
+    let seen_count: i32 = connection.query_row(
+        "INSERT INTO links_to_record_details (pages_with_all_records_id, company, link, extractor_version) VALUES (?, ?, ?, ?) ON CONFLICT (link) DO UPDATE SET seen_count = seen_count + 1 RETURNING seen_count",
+        params![link.pages_with_all_records_id, link.company, link.link, link.extractor_version],
+        |row| row.get(0),
+    )?;
+    Ok(seen_count == 1)
+}
+
+fn mark_link_visited(connection: &Connection, link: &LinksToRecordDetails) -> rusqlite::Result<()> {
+    connection.execute("UPDATE links_to_record_details SET visited = 1, visited_at = datetime('now') WHERE link = ?", params![link.link])?;
+    Ok(())
+}
+
+fn refresh_records_html(connection: &Connection, record: &RecordsHtml) -> rusqlite::Result<bool> {
+    let previous_html: Option<String> = connection
+        .query_row("SELECT html FROM records_html WHERE link_to_record_details_id = ?", params![record.link_to_record_details_id], |row| row.get(0))
+        .optional()?;
+
+    let changed = match previous_html {
+        Some(previous_html) => previous_html != record.html,
+        None => return upsert_records_html(connection, record),
+    };
+
+    connection.execute(
+        "UPDATE records_html SET html = ?, processed = CASE WHEN ? THEN 0 ELSE processed END, fetched_at = datetime('now'), seen_count = seen_count + 1 WHERE link_to_record_details_id = ?",
+        params![record.html, changed, record.link_to_record_details_id],
+    )?;
+    Ok(changed)
+}
+
+fn mark_records_html_processed(connection: &Connection, id: i32) -> rusqlite::Result<()> {
+    connection.execute("UPDATE records_html SET processed = 1 WHERE id = ?", params![id])?;
+    Ok(())
+}
+
+// A website or phone another record owns is stored, but the key stays with that record.
+// Calls are serialized on the one connection, so the check and the write cannot interleave.
+fn unclaimed_key(connection: &Connection, record: &RecordsData, column: &str, key: Option<String>) -> rusqlite::Result<Option<String>> {
+    let key = match key {
+        Some(key) => key,
+        None => return Ok(None),
+    };
+
+    let owner: Option<i32> = connection
+        .query_row(&format!("SELECT id FROM records_data WHERE {} = ? AND id <> ?", column), params![key, record.id], |row| row.get(0))
+        .optional()?;
+
+    match owner {
+        Some(owner_id) => {
+            println!("{} {} of records_data #{} belongs to records_data #{}, not claiming it", column, key, record.id, owner_id);
+            Ok(None)
+        },
+        None => Ok(Some(key)),
+    }
+}
+
+fn upsert_records_html(connection: &Connection, record: &RecordsHtml) -> rusqlite::Result<bool> {
+    let seen_count: i32 = connection.query_row(
+        "INSERT INTO records_html (link_to_record_details_id, html) VALUES (?, ?) ON CONFLICT (link_to_record_details_id) DO UPDATE SET seen_count = seen_count + 1 RETURNING seen_count",
+        params![record.link_to_record_details_id, record.html],
+        |row| row.get(0),
+    )?;
+    Ok(seen_count == 1)
+}
+
+#[async_trait]
+impl PagesRepository for SqliteStorage {
+    async fn create_page(&self, page: &PagesWithAllRecords) -> Result<(), Error> {
+        self.with_connection(|connection| {
+            connection.execute(
+                "INSERT INTO pages_with_all_records (page, district, query, html) VALUES (?, ?, ?, ?)",
+                params![page.page, page.district, page.query, page.html],
+            )?;
+            Ok(())
+        })
+    }
+
+    async fn get_unprocessed_pages(&self, after_id: i32, limit: i64) -> Result<Vec<PagesWithAllRecords>, Error> {
+        self.with_connection(|connection| {
+            let mut statement = connection.prepare("SELECT * FROM pages_with_all_records WHERE processed = 0 AND id > ? ORDER BY id LIMIT ?")?;
+            let pages = statement.query_map(params![after_id, limit], page_from_row)?.collect();
+            pages
+        })
+    }
+
+    async fn get_page_by_query(&self, query: &str) -> Result<Option<PagesWithAllRecords>, Error> {
+        self.with_connection(|connection| {
+            connection
+                .query_row("SELECT * FROM pages_with_all_records WHERE query = ? ORDER BY id DESC LIMIT 1", params![query], page_from_row)
+                .optional()
+        })
+    }
+
+    async fn mark_page_processed(&self, page: &PagesWithAllRecords) -> Result<(), Error> {
+        self.with_connection(|connection| mark_page_processed(connection, page))
+    }
+
+    async fn save_page_links(&self, page: &PagesWithAllRecords, links: &[LinksToRecordDetails]) -> Result<usize, Error> {
+        self.with_connection(|connection| {
+            let transaction = connection.unchecked_transaction()?;
+            let mut inserted = 0;
+
+            for link in links {
+                if upsert_link(&transaction, link)? {
+                    inserted += 1;
+                }
+            }
+
+            mark_page_processed(&transaction, page)?;
+            transaction.commit()?;
+
+            Ok(inserted)
+        })
+    }
+}
+
+#[async_trait]
+impl LinksRepository for SqliteStorage {
+    async fn upsert_link(&self, link: &LinksToRecordDetails) -> Result<bool, Error> {
+        self.with_connection(|connection| upsert_link(connection, link))
+    }
+
+    async fn get_link_by_id(&self, id: i32) -> Result<LinksToRecordDetails, Error> {
+        self.with_connection(|connection| {
+            connection.query_row("SELECT * FROM links_to_record_details WHERE id = ?", params![id], link_from_row)
+        })
+    }
+
+    async fn get_link_by_url(&self, link: &str) -> Result<Option<LinksToRecordDetails>, Error> {
+        self.with_connection(|connection| {
+            connection
+                .query_row("SELECT * FROM links_to_record_details WHERE link = ?", params![link], link_from_row)
+                .optional()
+        })
+    }
+
+    async fn get_unvisited_links(&self) -> Result<Vec<LinksToRecordDetails>, Error> {
+        self.with_connection(|connection| {
+            let mut statement = connection.prepare("SELECT * FROM links_to_record_details WHERE visited = 0")?;
+            let links = statement.query_map([], link_from_row)?.collect();
+            links
+        })
+    }
+
+    async fn mark_link_visited(&self, link: &LinksToRecordDetails) -> Result<(), Error> {
+        self.with_connection(|connection| mark_link_visited(connection, link))
+    }
+}
+
+#[async_trait]
+impl RecordsHtmlRepository for SqliteStorage {
+    async fn upsert_records_html(&self, record: &RecordsHtml) -> Result<bool, Error> {
+        self.with_connection(|connection| upsert_records_html(connection, record))
+    }
+
+    async fn records_html_exists(&self, link_to_record_details_id: i32) -> Result<bool, Error> {
+        self.with_connection(|connection| {
+            connection.query_row(
+                "SELECT EXISTS( SELECT 1 FROM records_html WHERE link_to_record_details_id = ? )",
+                params![link_to_record_details_id],
+                |row| row.get(0),
+            )
+        })
+    }
+
+    async fn get_unprocessed_records_html(&self, after_id: i32, limit: i64) -> Result<Vec<RecordsHtml>, Error> {
+        self.with_connection(|connection| {
+            let mut statement = connection.prepare("SELECT * FROM records_html WHERE processed = 0 AND id > ? ORDER BY id LIMIT ?")?;
+            let records_html = statement.query_map(params![after_id, limit], records_html_from_row)?.collect();
+            records_html
+        })
+    }
+
+    async fn get_records_html_by_link(&self, link: &str) -> Result<Option<RecordsHtml>, Error> {
+        self.with_connection(|connection| {
+            connection
+                .query_row(
+                    "SELECT records_html.* FROM records_html JOIN links_to_record_details ON links_to_record_details.id = records_html.link_to_record_details_id WHERE links_to_record_details.link = ?",
+                    params![link],
+                    records_html_from_row,
+                )
+                .optional()
+        })
+    }
+
+    async fn save_record_html(&self, record: &RecordsHtml, link: &LinksToRecordDetails) -> Result<bool, Error> {
+        self.with_connection(|connection| {
+            let transaction = connection.unchecked_transaction()?;
+            let inserted = upsert_records_html(&transaction, record)?;
+            mark_link_visited(&transaction, link)?;
+            transaction.commit()?;
+
+            Ok(inserted)
+        })
+    }
+
+    async fn refresh_record_html(&self, record: &RecordsHtml, link: &LinksToRecordDetails) -> Result<bool, Error> {
+        self.with_connection(|connection| {
+            let transaction = connection.unchecked_transaction()?;
+            let changed = refresh_records_html(&transaction, record)?;
+            mark_link_visited(&transaction, link)?;
+            transaction.commit()?;
+
+            Ok(changed)
+        })
+    }
+
+    async fn mark_records_html_processed(&self, id: i32) -> Result<(), Error> {
+        self.with_connection(|connection| mark_records_html_processed(connection, id))
+    }
+}
+
+#[async_trait]
+impl RecordsDataRepository for SqliteStorage {
+    async fn upsert_records_data(&self, record: &RecordsData) -> Result<(i32, bool), Error> {
+        self.with_connection(|connection| {
+            let transaction = connection.unchecked_transaction()?;
+            let (id, seen_count): (i32, i32) = transaction.query_row(
+                "INSERT INTO records_data (records_html_id, email, phone, website, canonical_domain, canonical_phone, company, extractor_version) VALUES (?, ?, ?, ?, ?, ?, ?, ?) ON CONFLICT (canonical_domain) DO UPDATE SET seen_count = seen_count + 1 ON CONFLICT (canonical_phone) DO UPDATE SET seen_count = seen_count + 1 RETURNING id, seen_count",
+                params![
+                    record.records_html_id,
+                    record.email,
+                    record.phone,
+                    record.website,
+                    urls::canonical_domain(&record.website),
+                    canonical_phone(&record.phone),
+                    record.company,
+                    record.extractor_version
+                ],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )?;
+            mark_records_html_processed(&transaction, record.records_html_id)?;
+            transaction.commit()?;
+
+            Ok((id, seen_count == 1))
+        })
+    }
+
+    async fn records_data_exists_by_website(&self, website: &str) -> Result<bool, Error> {
+        let canonical_domain = match urls::canonical_domain(website) {
+            Some(canonical_domain) => canonical_domain,
+            None => return Ok(false),
+        };
+
+        self.with_connection(|connection| {
+            connection.query_row(
+                "SELECT EXISTS( SELECT 1 FROM records_data WHERE canonical_domain = ? )",
+                params![canonical_domain],
+                |row| row.get(0),
+            )
+        })
+    }
+
+    async fn get_records_data_by_id(&self, id: i32) -> Result<RecordsData, Error> {
+        self.with_connection(|connection| {
+            connection.query_row("SELECT * FROM records_data WHERE id = ?", params![id], records_data_from_row)
+        })
+    }
+
+    async fn get_records_data_by_records_html_id(&self, records_html_id: i32) -> Result<Option<RecordsData>, Error> {
+        self.with_connection(|connection| {
+            connection
+                .query_row("SELECT * FROM records_data WHERE records_html_id = ?", params![records_html_id], records_data_from_row)
+                .optional()
+        })
+    }
+
+    async fn update_records_data_fields(&self, record: &RecordsData) -> Result<(), Error> {
+        self.with_connection(|connection| {
+            let transaction = connection.unchecked_transaction()?;
+            let canonical_domain = unclaimed_key(&transaction, record, "canonical_domain", urls::canonical_domain(&record.website))?;
+            let canonical_phone = unclaimed_key(&transaction, record, "canonical_phone", canonical_phone(&record.phone))?;
+
+            transaction.execute(
+                "UPDATE records_data SET phone = ?, website = ?, canonical_domain = ?, canonical_phone = ?, extractor_version = ? WHERE id = ?",
+                params![record.phone, record.website, canonical_domain, canonical_phone, record.extractor_version, record.id],
+            )?;
+            mark_records_html_processed(&transaction, record.records_html_id)?;
+            transaction.commit()?;
+            Ok(())
+        })
+    }
+
+    async fn save_field_version(&self, version: &RecordFieldHistory) -> Result<bool, Error> {
+        self.with_connection(|connection| {
+            let transaction = connection.unchecked_transaction()?;
+            let latest: Option<String> = transaction
+                .query_row(
+                    "SELECT value FROM record_field_history WHERE record_id = ? AND field = ? ORDER BY id DESC LIMIT 1",
+                    params![version.record_id, version.field],
+                    |row| row.get(0),
+                )
+                .optional()?;
+
+            if latest.as_deref() == Some(version.value.as_str()) {
+                return Ok(false);
+            }
+
+            transaction.execute(
+                "INSERT INTO record_field_history (record_id, field, value, source_table, source_id) VALUES (?, ?, ?, ?, ?)",
+                params![version.record_id, version.field, version.value, version.source_table, version.source_id],
+            )?;
+            transaction.commit()?;
+
+            Ok(true)
+        })
+    }
+}
+
+#[async_trait]
+impl WebsitesRepository for SqliteStorage {
+    async fn create_website(&self, website: &WebsitesHtml) -> Result<i32, Error> {
+        self.with_connection(|connection| {
+            connection.execute(
+                "INSERT INTO websites_html (records_data_id, website, main_page_html, contact_page_html, final_url, canonical_domain) VALUES (?, ?, ?, ?, ?, ?)",
+                params![
+                    website.records_data_id,
+                    website.website,
+                    website.main_page_html,
+                    website.contact_page_html,
+                    website.final_url,
+                    website.canonical_domain()
+                ],
+            )?;
+            Ok(connection.last_insert_rowid() as i32)
+        })
+    }
+
+    async fn get_websites(&self, after_id: i32, limit: i64) -> Result<Vec<WebsitesHtml>, Error> {
+        self.with_connection(|connection| {
+            let mut statement = connection.prepare("SELECT * FROM websites_html WHERE id > ? ORDER BY id LIMIT ?")?;
+            let websites_html = statement.query_map(params![after_id, limit], website_from_row)?.collect();
+            websites_html
+        })
+    }
+
+    async fn get_website_by_url(&self, website: &str) -> Result<Option<WebsitesHtml>, Error> {
+        self.with_connection(|connection| {
+            connection
+                .query_row("SELECT * FROM websites_html WHERE website = ? ORDER BY id DESC LIMIT 1", params![website], website_from_row)
+                .optional()
+        })
+    }
+
+    async fn create_website_page(&self, page: &WebsitePages) -> Result<(), Error> {
+        self.with_connection(|connection| {
+            connection.execute(
+                "INSERT INTO website_pages (websites_html_id, url, depth, score, html) VALUES (?, ?, ?, ?, ?)",
+                params![page.websites_html_id, page.url, page.depth, page.score, page.html],
+            )?;
+            Ok(())
+        })
+    }
+
+    async fn get_website_pages(&self, websites_html_id: i32) -> Result<Vec<WebsitePages>, Error> {
+        self.with_connection(|connection| {
+            let mut statement = connection.prepare("SELECT * FROM website_pages WHERE websites_html_id = ? ORDER BY depth, score DESC")?;
+            let website_pages = statement.query_map(params![websites_html_id], website_page_from_row)?.collect();
+            website_pages
+        })
+    }
+
+    async fn get_website_page_by_url(&self, url: &str) -> Result<Option<WebsitePages>, Error> {
+        self.with_connection(|connection| {
+            connection
+                .query_row("SELECT * FROM website_pages WHERE url = ? ORDER BY id DESC LIMIT 1", params![url], website_page_from_row)
+                .optional()
+        })
+    }
+}
+
+#[async_trait]
+impl RecordEmailsRepository for SqliteStorage {
+    async fn create_record_email(&self, record: &RecordEmails) -> Result<i32, Error> {
+        self.with_connection(|connection| {
+            connection.execute(
+                "INSERT INTO record_emails (record_id, email, source_page, source_type, first_seen, confidence) VALUES (?, ?, ?, ?, datetime('now'), ?)",
+                params![record.record_id, record.email, record.source_page, record.source_type, record.confidence],
+            )?;
+            Ok(connection.last_insert_rowid() as i32)
+        })
+    }
+
+    async fn record_email_exists(&self, record_id: i32, email: &str) -> Result<bool, Error> {
+        self.with_connection(|connection| {
+            connection.query_row(
+                "SELECT EXISTS( SELECT 1 FROM record_emails WHERE record_id = ? AND email = ? )",
+                params![record_id, email],
+                |row| row.get(0),
+            )
+        })
+    }
+
+    async fn get_record_emails(&self, record_id: i32) -> Result<Vec<RecordEmails>, Error> {
+        self.with_connection(|connection| {
+            let mut statement = connection.prepare("SELECT * FROM record_emails WHERE record_id = ? ORDER BY confidence DESC, id")?;
+            let record_emails = statement.query_map(params![record_id], record_email_from_row)?.collect();
+            record_emails
+        })
+    }
+
+    // The view is created with the schema and reads record_emails live.
+    async fn refresh_email_view(&self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ExtractionLogRepository for SqliteStorage {
+    async fn create_field_provenance(&self, record: &FieldProvenance) -> Result<(), Error> {
+        self.with_connection(|connection| {
+            connection.execute(
+                "INSERT INTO field_provenance (target_table, target_id, field, value, source_table, source_id, page_url, rule_id, extractor_version, confidence) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                params![
+                    record.target_table,
+                    record.target_id,
+                    record.field,
+                    record.value,
+                    record.source_table,
+                    record.source_id,
+                    record.page_url,
+                    record.rule_id,
+                    record.extractor_version,
+                    record.confidence
+                ],
+            )?;
+            Ok(())
+        })
+    }
+
+    async fn create_fill_rate(&self, record: &ExtractionFillRates) -> Result<(), Error> {
+        self.with_connection(|connection| {
+            connection.execute(
+                "INSERT INTO extraction_fill_rates (batch_id, stage, field, total, filled, fill_rate, failing_ids, extractor_version, halted) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                params![
+                    record.batch_id,
+                    record.stage,
+                    record.field,
+                    record.total,
+                    record.filled,
+                    record.fill_rate,
+                    record.failing_ids,
+                    record.extractor_version,
+                    record.halted
+                ],
+            )?;
+            Ok(())
+        })
+    }
+
+    async fn get_fill_rate_history(&self, stage: &str, field: &str, limit: i64) -> Result<Vec<f32>, Error> {
+        self.with_connection(|connection| {
+            let mut statement = connection.prepare("SELECT fill_rate FROM extraction_fill_rates WHERE stage = ? AND field = ? AND halted = 0 ORDER BY id DESC LIMIT ?")?;
+            let fill_rates = statement.query_map(params![stage, field, limit], |row| row.get(0))?.collect();
+            fill_rates
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extractor::EXTRACTOR_VERSION;
+    use crate::storage::{Storage, BATCH_SIZE};
+
+    fn records_data(records_html_id: i32, phone: &str, website: &str) -> RecordsData {
+        RecordsData {
+            id: 0,
+            records_html_id,
+            email: "".to_string(),
+            phone: phone.to_string(),
+            website: website.to_string(),
+            contact_us_link: None,
+            company: "Acme Builders".to_string(),
+            company_verified: None,
+            extractor_version: EXTRACTOR_VERSION.to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn should_round_trip_pages_links_and_records_html() {
+        let storage = SqliteStorage::open_in_memory().unwrap();
+
+        let page = PagesWithAllRecords {
+            id: 0,
+            page: Some("1".to_string()),
+            district: Some("General Contractors in Ontario - Houzz".to_string()),
+            query: Some("https://www.houzz.com/professionals/general-contractor".to_string()),
+            html: Some("<div class=\"pro-results\"></div>".to_string()),
+            processed: Some(0),
+        };
+        storage.create_page(&page).await.unwrap();
+
+        let pages = storage.get_unprocessed_pages(0, BATCH_SIZE).await.unwrap();
+        assert_eq!(pages.len(), 1);
+        storage.mark_page_processed(&pages[0]).await.unwrap();
+        assert!(storage.get_unprocessed_pages(0, BATCH_SIZE).await.unwrap().is_empty());
+
+        let link = LinksToRecordDetails {
+            id: 0,
+            pages_with_all_records_id: pages[0].id,
+            company: "Acme Builders".to_string(),
+            link: "https://www.houzz.com/professionals/general-contractors/acme".to_string(),
+            visited: 0,
+            extractor_version: EXTRACTOR_VERSION.to_string(),
+        };
+        assert!(storage.upsert_link(&link).await.unwrap());
+        assert!(!storage.upsert_link(&link).await.unwrap());
+
+        let links = storage.get_unvisited_links().await.unwrap();
+        assert_eq!(links.len(), 1);
+        assert_eq!(storage.get_link_by_id(links[0].id).await.unwrap().company, "Acme Builders");
+
+        assert!(!storage.records_html_exists(links[0].id).await.unwrap());
+        let record_html = RecordsHtml { id: 0, link_to_record_details_id: links[0].id, html: "<html></html>".to_string(), processed: 0 };
+        assert!(storage.upsert_records_html(&record_html).await.unwrap());
+        assert!(!storage.upsert_records_html(&record_html).await.unwrap());
+        storage.mark_link_visited(&links[0]).await.unwrap();
+
+        assert!(storage.records_html_exists(links[0].id).await.unwrap());
+        assert!(storage.get_unvisited_links().await.unwrap().is_empty());
+        assert_eq!(storage.get_unprocessed_records_html(0, BATCH_SIZE).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn should_refresh_recrawled_pages_and_version_fields() {
+        let storage = SqliteStorage::open_in_memory().unwrap();
+        let link = LinksToRecordDetails {
+            id: 0,
+            pages_with_all_records_id: 1,
+            company: "Acme Builders".to_string(),
+            link: "https://www.houzz.com/professionals/general-contractors/acme".to_string(),
+            visited: 0,
+            extractor_version: EXTRACTOR_VERSION.to_string(),
+        };
+        storage.upsert_link(&link).await.unwrap();
+        let link = storage.get_unvisited_links().await.unwrap().remove(0);
+
+        let record_html = RecordsHtml { id: 0, link_to_record_details_id: link.id, html: "<p>(905) 555-0100</p>".to_string(), processed: 0 };
+        assert!(storage.save_record_html(&record_html, &link).await.unwrap());
+        let stored = storage.get_unprocessed_records_html(0, BATCH_SIZE).await.unwrap().remove(0);
+        storage.with_connection(|connection| connection.execute("UPDATE records_html SET processed = 1", [])).unwrap();
+
+        // Same page again: nothing to extract.
+        assert!(!storage.refresh_record_html(&record_html, &link).await.unwrap());
+        assert!(storage.get_unprocessed_records_html(0, BATCH_SIZE).await.unwrap().is_empty());
+
+        let changed = RecordsHtml { html: "<p>(905) 555-0199</p>".to_string(), ..record_html };
+        assert!(storage.refresh_record_html(&changed, &link).await.unwrap());
+        let refreshed = storage.get_unprocessed_records_html(0, BATCH_SIZE).await.unwrap().remove(0);
+        assert_eq!(refreshed.id, stored.id);
+        assert_eq!(refreshed.html, "<p>(905) 555-0199</p>");
+
+        let visited_at: Option<String> = storage
+            .with_connection(|connection| connection.query_row("SELECT visited_at FROM links_to_record_details WHERE id = ?", params![link.id], |row| row.get(0)))
+            .unwrap();
+        assert!(visited_at.is_some());
+
+        assert!(storage.save_field_version(&RecordFieldHistory::new(1, "phone", "(905) 555-0100", "records_html", stored.id)).await.unwrap());
+        assert!(!storage.save_field_version(&RecordFieldHistory::new(1, "phone", "(905) 555-0100", "records_html", stored.id)).await.unwrap());
+        assert!(storage.save_field_version(&RecordFieldHistory::new(1, "phone", "(905) 555-0199", "records_html", stored.id)).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn should_page_unprocessed_pages_by_id() {
+        let storage = SqliteStorage::open_in_memory().unwrap();
+
+        for number in 1..=5 {
+            let page = PagesWithAllRecords {
+                id: 0,
+                page: Some(number.to_string()),
+                district: None,
+                query: None,
+                html: Some("<html></html>".to_string()),
+                processed: Some(0),
+            };
+            storage.create_page(&page).await.unwrap();
+        }
+
+        let third = storage.get_unprocessed_pages(2, 1).await.unwrap().remove(0);
+        storage.mark_page_processed(&third).await.unwrap();
+
+        let first = storage.get_unprocessed_pages(0, 2).await.unwrap();
+        assert_eq!(first.iter().map(|page| page.id).collect::<Vec<_>>(), vec![1, 2]);
+
+        let second = storage.get_unprocessed_pages(2, 2).await.unwrap();
+        assert_eq!(second.iter().map(|page| page.id).collect::<Vec<_>>(), vec![4, 5]);
+
+        assert!(storage.get_unprocessed_pages(5, 2).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn should_roll_back_page_links_when_one_fails() {
+        let storage = SqliteStorage::open_in_memory().unwrap();
+        storage
+            .with_connection(|connection| {
+                connection.execute_batch("CREATE TRIGGER fail_link BEFORE INSERT ON links_to_record_details WHEN NEW.link = 'broken' BEGIN SELECT RAISE(ABORT, 'broken link'); END;")
+            })
+            .unwrap();
+
+        let page = PagesWithAllRecords { id: 0, page: None, district: None, query: None, html: None, processed: Some(0) };
+        storage.create_page(&page).await.unwrap();
+        let page = storage.get_unprocessed_pages(0, BATCH_SIZE).await.unwrap().remove(0);
+
+        let link = |link: &str| LinksToRecordDetails {
+            id: 0,
+            pages_with_all_records_id: page.id,
+            company: "".to_string(),
+            link: link.to_string(),
+            visited: 0,
+            extractor_version: EXTRACTOR_VERSION.to_string(),
+        };
+
+        assert!(storage.save_page_links(&page, &[link("https://www.houzz.com/pro/a"), link("broken")]).await.is_err());
+        assert!(storage.get_unvisited_links().await.unwrap().is_empty());
+        assert_eq!(storage.get_unprocessed_pages(0, BATCH_SIZE).await.unwrap().len(), 1);
+
+        assert_eq!(storage.save_page_links(&page, &[link("https://www.houzz.com/pro/a"), link("https://www.houzz.com/pro/b")]).await.unwrap(), 2);
+        assert!(storage.get_unprocessed_pages(0, BATCH_SIZE).await.unwrap().is_empty());
+
+        let stored = storage.get_unvisited_links().await.unwrap().remove(0);
+        let record_html = RecordsHtml { id: 0, link_to_record_details_id: stored.id, html: "<html></html>".to_string(), processed: 0 };
+        assert!(storage.save_record_html(&record_html, &stored).await.unwrap());
+        assert_eq!(storage.get_unvisited_links().await.unwrap().len(), 1);
+        assert!(storage.records_html_exists(stored.id).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn should_upsert_records_data_by_canonical_website_or_phone() {
+        let storage = SqliteStorage::open_in_memory().unwrap();
+
+        let (id, inserted) = storage.upsert_records_data(&records_data(1, "(416) 555-0100", "https://www.acme.ca/")).await.unwrap();
+        assert!(inserted);
+        assert_eq!(storage.upsert_records_data(&records_data(2, "", "http://acme.ca/contact")).await.unwrap(), (id, false));
+        // No website: the phone is the key.
+        let (phone_id, inserted) = storage.upsert_records_data(&records_data(3, "(416) 555-0199", "")).await.unwrap();
+        assert!(inserted);
+        assert!(storage.upsert_records_data(&records_data(4, "(416) 555-0198", "")).await.unwrap().1);
+        assert_eq!(storage.upsert_records_data(&records_data(5, "+1 416.555.0199", "")).await.unwrap(), (phone_id, false));
+        assert_eq!(storage.upsert_records_data(&records_data(6, "(416) 555-0100", "")).await.unwrap(), (id, false));
+
+        assert!(storage.records_data_exists_by_website("http://acme.ca").await.unwrap());
+        assert!(!storage.records_data_exists_by_website("https://other.ca").await.unwrap());
+        assert_eq!(storage.get_records_data_by_id(id).await.unwrap().company, "Acme Builders");
+    }
+
+    #[tokio::test]
+    async fn should_keep_phone_update_when_website_moves_to_a_known_domain() {
+        let storage = SqliteStorage::open_in_memory().unwrap();
+        let (acme_id, _) = storage.upsert_records_data(&records_data(1, "(416) 555-0100", "https://acme.ca/")).await.unwrap();
+        let (other_id, _) = storage.upsert_records_data(&records_data(2, "(416) 555-0199", "https://www.facebook.com/otherbuilders")).await.unwrap();
+
+        let moved = RecordsData { id: other_id, phone: "(416) 555-0198".to_string(), website: "http://www.acme.ca/about".to_string(), ..records_data(2, "", "") };
+        storage.update_records_data_fields(&moved).await.unwrap();
+
+        let stored = storage.get_records_data_by_id(other_id).await.unwrap();
+        assert_eq!(stored.phone, "(416) 555-0198");
+        assert_eq!(stored.website, "http://www.acme.ca/about");
+        let owners: Vec<i32> = storage
+            .with_connection(|connection| {
+                let mut statement = connection.prepare("SELECT id FROM records_data WHERE canonical_domain = 'acme.ca'")?;
+                let owners = statement.query_map([], |row| row.get(0))?.collect::<Result<Vec<i32>, _>>()?;
+                Ok(owners)
+            })
+            .unwrap();
+        assert_eq!(owners, vec![acme_id]);
+    }
+
+    #[tokio::test]
+    async fn should_fall_back_to_website_html_pages_and_join_emails_in_view() {
+        let storage = SqliteStorage::open_in_memory().unwrap();
+
+        let (records_data_id, _) = storage.upsert_records_data(&records_data(1, "", "https://acme.ca")).await.unwrap();
+        let website = WebsitesHtml {
+            id: 0,
+            records_data_id,
+            website: "https://acme.ca".to_string(),
+            main_page_html: "<html>home</html>".to_string(),
+            contact_page_html: "".to_string(),
+            final_url: None,
+        };
+        let websites_html_id = storage.create_website(&website).await.unwrap();
+        let website = storage.get_websites(0, BATCH_SIZE).await.unwrap().remove(0);
+        assert_eq!(website.id, websites_html_id);
+
+        let pages = storage.get_pages_for_website(&website).await.unwrap();
+        assert_eq!(pages[0], ("https://acme.ca".to_string(), "<html>home</html>".to_string()));
+
+        for (email, confidence) in [("office@acme.ca", 0.6), ("info@acme.ca", 0.9)] {
+            let record_email = RecordEmails {
+                id: 0,
+                record_id: records_data_id,
+                email: email.to_string(),
+                source_page: "https://acme.ca".to_string(),
+                source_type: "mailto".to_string(),
+                first_seen: None,
+                confidence,
+            };
+            storage.create_record_email(&record_email).await.unwrap();
+        }
+
+        assert!(storage.record_email_exists(records_data_id, "info@acme.ca").await.unwrap());
+        assert!(storage.get_record_emails(records_data_id).await.unwrap()[0].first_seen.is_some());
+
+        let joined: String = storage
+            .with_connection(|connection| connection.query_row("SELECT email FROM view_records_data_emails WHERE id = ?", params![records_data_id], |row| row.get(0)))
+            .unwrap();
+        assert_eq!(joined, "info@acme.ca, office@acme.ca");
+    }
+
+    #[tokio::test]
+    async fn should_skip_halted_batches_in_fill_rate_history() {
+        let storage = SqliteStorage::open_in_memory().unwrap();
+
+        for (fill_rate, halted) in [(0.9, 0), (0.1, 1), (0.8, 0)] {
+            let extraction_fill_rate = ExtractionFillRates {
+                id: 0,
+                batch_id: "records_data-1".to_string(),
+                stage: "records_data".to_string(),
+                field: "phone".to_string(),
+                total: 10,
+                filled: (fill_rate * 10.0) as i32,
+                fill_rate,
+                failing_ids: "".to_string(),
+                extractor_version: EXTRACTOR_VERSION.to_string(),
+                halted,
+            };
+            storage.create_fill_rate(&extraction_fill_rate).await.unwrap();
+        }
+
+        assert_eq!(storage.get_fill_rate_history("records_data", "phone", 5).await.unwrap(), vec![0.8, 0.9]);
+    }
+}
@@ -0,0 +1,343 @@
+use anyhow::Error;
+use async_trait::async_trait;
+use sqlx::MySqlPool;
+use crate::extraction_fill_rates::ExtractionFillRates;
+use crate::extractor::ExtractedField;
+use crate::field_provenance::FieldProvenance;
+use crate::links_to_record_details::LinksToRecordDetails;
+use crate::page_blobs::PageBlobs;
+use crate::pages_with_all_records::PagesWithAllRecords;
+use crate::record_emails::RecordEmails;
+use crate::records_data::RecordsData;
+use crate::record_field_history::RecordFieldHistory;
+use crate::records_html::RecordsHtml;
+use crate::website_pages::WebsitePages;
+use crate::websites_html::WebsitesHtml;
+
+// Rows per keyset page for reads of HTML-carrying tables. Each row holds a
+// whole page, so this bounds a stage's memory, and it is the unit extraction
+// stages check fill rates on.
+pub const BATCH_SIZE: i64 = 200;
+
+// Repositories per aggregate, over the model operations the Houzz directory
+// stages and email extraction need. MySqlStorage delegates to the models,
+// SqliteStorage keeps a local file and MemoryStorage backs tests. Upserts
+// return whether the row was new; unique keys make them safe to run concurrently.
+#[async_trait]
+pub trait PagesRepository: Send + Sync {
+    async fn create_page(&self, page: &PagesWithAllRecords) -> Result<(), Error>;
+    async fn get_unprocessed_pages(&self, after_id: i32, limit: i64) -> Result<Vec<PagesWithAllRecords>, Error>;
+    async fn get_page_by_query(&self, query: &str) -> Result<Option<PagesWithAllRecords>, Error>;
+    async fn mark_page_processed(&self, page: &PagesWithAllRecords) -> Result<(), Error>;
+    // The page's links and its processed flag in one transaction. Returns how many links were new.
+    async fn save_page_links(&self, page: &PagesWithAllRecords, links: &[LinksToRecordDetails]) -> Result<usize, Error>;
+}
+
+#[async_trait]
+pub trait LinksRepository: Send + Sync {
+    async fn upsert_link(&self, link: &LinksToRecordDetails) -> Result<bool, Error>;
+    async fn get_link_by_id(&self, id: i32) -> Result<LinksToRecordDetails, Error>;
+    async fn get_link_by_url(&self, link: &str) -> Result<Option<LinksToRecordDetails>, Error>;
+    async fn get_unvisited_links(&self) -> Result<Vec<LinksToRecordDetails>, Error>;
+    async fn mark_link_visited(&self, link: &LinksToRecordDetails) -> Result<(), Error>;
+}
+
+#[async_trait]
+pub trait RecordsHtmlRepository: Send + Sync {
+    async fn upsert_records_html(&self, record: &RecordsHtml) -> Result<bool, Error>;
+    async fn records_html_exists(&self, link_to_record_details_id: i32) -> Result<bool, Error>;
+    async fn get_unprocessed_records_html(&self, after_id: i32, limit: i64) -> Result<Vec<RecordsHtml>, Error>;
+    async fn get_records_html_by_link(&self, link: &str) -> Result<Option<RecordsHtml>, Error>;
+    // The fetched page and its link's visited flag in one transaction. True when the page is new.
+    async fn save_record_html(&self, record: &RecordsHtml, link: &LinksToRecordDetails) -> Result<bool, Error>;
+    // A re-crawl of a link that already has a page: replaces the page, marks the
+    // link visited and requeues the page for extraction if it changed. True when changed.
+    async fn refresh_record_html(&self, record: &RecordsHtml, link: &LinksToRecordDetails) -> Result<bool, Error>;
+    // For pages whose extraction wrote nothing, e.g. a business unchanged since the last crawl.
+    async fn mark_records_html_processed(&self, id: i32) -> Result<(), Error>;
+}
+
+#[async_trait]
+pub trait RecordsDataRepository: Send + Sync {
+    // Both writes also mark the records_html the values came from processed, in the same transaction.
+    async fn upsert_records_data(&self, record: &RecordsData) -> Result<(i32, bool), Error>;
+    async fn records_data_exists_by_website(&self, website: &str) -> Result<bool, Error>;
+    async fn get_records_data_by_id(&self, id: i32) -> Result<RecordsData, Error>;
+    async fn get_records_data_by_records_html_id(&self, records_html_id: i32) -> Result<Option<RecordsData>, Error>;
+    async fn update_records_data_fields(&self, record: &RecordsData) -> Result<(), Error>;
+    // Versions a field value, see RecordFieldHistory::save_version. True when written.
+    async fn save_field_version(&self, version: &RecordFieldHistory) -> Result<bool, Error>;
+}
+
+#[async_trait]
+pub trait WebsitesRepository: Send + Sync {
+    async fn create_website(&self, website: &WebsitesHtml) -> Result<i32, Error>;
+    async fn get_websites(&self, after_id: i32, limit: i64) -> Result<Vec<WebsitesHtml>, Error>;
+    async fn get_website_by_url(&self, website: &str) -> Result<Option<WebsitesHtml>, Error>;
+    async fn create_website_page(&self, page: &WebsitePages) -> Result<(), Error>;
+    async fn get_website_pages(&self, websites_html_id: i32) -> Result<Vec<WebsitePages>, Error>;
+    async fn get_website_page_by_url(&self, url: &str) -> Result<Option<WebsitePages>, Error>;
+}
+
+#[async_trait]
+pub trait RecordEmailsRepository: Send + Sync {
+    async fn create_record_email(&self, record: &RecordEmails) -> Result<i32, Error>;
+    async fn record_email_exists(&self, record_id: i32, email: &str) -> Result<bool, Error>;
+    async fn get_record_emails(&self, record_id: i32) -> Result<Vec<RecordEmails>, Error>;
+    async fn refresh_email_view(&self) -> Result<(), Error>;
+}
+
+// Provenance rows and fill rates: what the stages log about their own extraction.
+#[async_trait]
+pub trait ExtractionLogRepository: Send + Sync {
+    async fn create_field_provenance(&self, record: &FieldProvenance) -> Result<(), Error>;
+    async fn create_fill_rate(&self, record: &ExtractionFillRates) -> Result<(), Error>;
+    async fn get_fill_rate_history(&self, stage: &str, field: &str, limit: i64) -> Result<Vec<f32>, Error>;
+
+    // Logs and skips failed inserts, like FieldProvenance::save_fields.
+    async fn save_fields(&self, target_table: &str, target_id: i32, source_table: &str, source_id: i32, page_url: &str, fields: &[ExtractedField]) {
+        for field_provenance in FieldProvenance::from_fields(target_table, target_id, source_table, source_id, page_url, fields) {
+            if let Err(e) = self.create_field_provenance(&field_provenance).await {
+                eprintln!("Error inserting provenance: {:?}", e);
+            }
+        }
+    }
+}
+
+// Every repository at once, for stages that touch several aggregates.
+#[async_trait]
+pub trait Storage:
+    PagesRepository + LinksRepository + RecordsHtmlRepository + RecordsDataRepository + WebsitesRepository + RecordEmailsRepository + ExtractionLogRepository
+{
+    // (url, html) of every stored page of a website, see WebsitePages::get_pages_for_website.
+    async fn get_pages_for_website(&self, website_html: &WebsitesHtml) -> Result<Vec<(String, String)>, Error> {
+        let website_pages = self.get_website_pages(website_html.id).await?;
+
+        if !website_pages.is_empty() {
+            return Ok(website_pages.into_iter().map(|page| (page.url, page.html)).collect());
+        }
+
+        let record_data = self.get_records_data_by_id(website_html.records_data_id).await?;
+
+        Ok(WebsitePages::fallback_pages(website_html, record_data.contact_us_link))
+    }
+}
+
+impl<T> Storage for T where
+    T: PagesRepository + LinksRepository + RecordsHtmlRepository + RecordsDataRepository + WebsitesRepository + RecordEmailsRepository + ExtractionLogRepository
+{
+}
+
+pub struct MySqlStorage {
+    pool: MySqlPool,
+}
+
+impl MySqlStorage {
+    pub fn new(pool: MySqlPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl PagesRepository for MySqlStorage {
+    async fn create_page(&self, page: &PagesWithAllRecords) -> Result<(), Error> {
+        PagesWithAllRecords::create_record(page, &self.pool).await
+    }
+
+    async fn get_unprocessed_pages(&self, after_id: i32, limit: i64) -> Result<Vec<PagesWithAllRecords>, Error> {
+        PagesWithAllRecords::get_unprocessed_records_after(&self.pool, after_id, limit).await
+    }
+
+    async fn get_page_by_query(&self, query: &str) -> Result<Option<PagesWithAllRecords>, Error> {
+        PagesWithAllRecords::get_record_by_query(&self.pool, query).await
+    }
+
+    async fn mark_page_processed(&self, page: &PagesWithAllRecords) -> Result<(), Error> {
+        PagesWithAllRecords::mark_record_as_processed(page, &self.pool).await
+    }
+
+    async fn save_page_links(&self, page: &PagesWithAllRecords, links: &[LinksToRecordDetails]) -> Result<usize, Error> {
+        let mut transaction = self.pool.begin().await?;
+        let mut inserted = 0;
+
+        for link in links {
+            if LinksToRecordDetails::upsert_record(&mut transaction, link).await? {
+                inserted += 1;
+            }
+        }
+
+        PagesWithAllRecords::mark_record_as_processed(page, &mut transaction).await?;
+        transaction.commit().await?;
+
+        Ok(inserted)
+    }
+}
+
+#[async_trait]
+impl LinksRepository for MySqlStorage {
+    async fn upsert_link(&self, link: &LinksToRecordDetails) -> Result<bool, Error> {
+        Ok(LinksToRecordDetails::upsert_record(&self.pool, link).await?)
+    }
+
+    async fn get_link_by_id(&self, id: i32) -> Result<LinksToRecordDetails, Error> {
+        Ok(LinksToRecordDetails::get_record_by_id(&self.pool, id).await?)
+    }
+
+    async fn get_link_by_url(&self, link: &str) -> Result<Option<LinksToRecordDetails>, Error> {
+        Ok(LinksToRecordDetails::get_record_by_link(&self.pool, link).await?)
+    }
+
+    async fn get_unvisited_links(&self) -> Result<Vec<LinksToRecordDetails>, Error> {
+        Ok(LinksToRecordDetails::get_all_unvisited_records(&self.pool).await?)
+    }
+
+    async fn mark_link_visited(&self, link: &LinksToRecordDetails) -> Result<(), Error> {
+        Ok(LinksToRecordDetails::mark_record_as_visited(&self.pool, link).await?)
+    }
+}
+
+#[async_trait]
+impl RecordsHtmlRepository for MySqlStorage {
+    async fn upsert_records_html(&self, record: &RecordsHtml) -> Result<bool, Error> {
+        let mut transaction = self.pool.begin().await?;
+        PageBlobs::store_html(&mut transaction, &record.html).await?;
+        let inserted = RecordsHtml::upsert_record(&mut transaction, record).await?;
+        transaction.commit().await?;
+
+        Ok(inserted)
+    }
+
+    async fn records_html_exists(&self, link_to_record_details_id: i32) -> Result<bool, Error> {
+        Ok(RecordsHtml::record_exists(&self.pool, link_to_record_details_id).await?)
+    }
+
+    async fn get_unprocessed_records_html(&self, after_id: i32, limit: i64) -> Result<Vec<RecordsHtml>, Error> {
+        Ok(RecordsHtml::get_unprocessed_records_after(&self.pool, after_id, limit).await?)
+    }
+
+    async fn get_records_html_by_link(&self, link: &str) -> Result<Option<RecordsHtml>, Error> {
+        Ok(RecordsHtml::get_record_by_link(&self.pool, link).await?)
+    }
+
+    async fn save_record_html(&self, record: &RecordsHtml, link: &LinksToRecordDetails) -> Result<bool, Error> {
+        let mut transaction = self.pool.begin().await?;
+        PageBlobs::store_html(&mut transaction, &record.html).await?;
+        let inserted = RecordsHtml::upsert_record(&mut transaction, record).await?;
+        LinksToRecordDetails::mark_record_as_visited(&mut transaction, link).await?;
+        transaction.commit().await?;
+
+        Ok(inserted)
+    }
+
+    async fn refresh_record_html(&self, record: &RecordsHtml, link: &LinksToRecordDetails) -> Result<bool, Error> {
+        let mut transaction = self.pool.begin().await?;
+        PageBlobs::store_html(&mut transaction, &record.html).await?;
+        let changed = RecordsHtml::refresh_record(&mut transaction, record).await?;
+        LinksToRecordDetails::mark_record_as_visited(&mut transaction, link).await?;
+        transaction.commit().await?;
+
+        Ok(changed)
+    }
+
+    async fn mark_records_html_processed(&self, id: i32) -> Result<(), Error> {
+        Ok(RecordsHtml::mark_record_as_processed(&self.pool, id).await?)
+    }
+}
+
+#[async_trait]
+impl RecordsDataRepository for MySqlStorage {
+    async fn upsert_records_data(&self, record: &RecordsData) -> Result<(i32, bool), Error> {
+        let mut transaction = self.pool.begin().await?;
+        let upserted = RecordsData::upsert_record(&mut transaction, record).await?;
+        RecordsHtml::mark_record_as_processed(&mut transaction, record.records_html_id).await?;
+        transaction.commit().await?;
+
+        Ok(upserted)
+    }
+
+    async fn records_data_exists_by_website(&self, website: &str) -> Result<bool, Error> {
+        Ok(RecordsData::record_exists_by_website(&self.pool, website).await?)
+    }
+
+    async fn get_records_data_by_id(&self, id: i32) -> Result<RecordsData, Error> {
+        Ok(RecordsData::get_record_data_by_records_data_id(&self.pool, id).await?)
+    }
+
+    async fn get_records_data_by_records_html_id(&self, records_html_id: i32) -> Result<Option<RecordsData>, Error> {
+        Ok(RecordsData::get_record_by_records_html_id(&self.pool, records_html_id).await?)
+    }
+
+    async fn update_records_data_fields(&self, record: &RecordsData) -> Result<(), Error> {
+        let mut transaction = self.pool.begin().await?;
+        RecordsData::update_extracted_fields(&mut transaction, record).await?;
+        RecordsHtml::mark_record_as_processed(&mut transaction, record.records_html_id).await?;
+        transaction.commit().await?;
+
+        Ok(())
+    }
+
+    async fn save_field_version(&self, version: &RecordFieldHistory) -> Result<bool, Error> {
+        Ok(RecordFieldHistory::save_version(&self.pool, version).await?)
+    }
+}
+
+#[async_trait]
+impl WebsitesRepository for MySqlStorage {
+    async fn create_website(&self, website: &WebsitesHtml) -> Result<i32, Error> {
+        Ok(WebsitesHtml::create_record(&self.pool, website).await?)
+    }
+
+    async fn get_websites(&self, after_id: i32, limit: i64) -> Result<Vec<WebsitesHtml>, Error> {
+        Ok(WebsitesHtml::get_websites_after(&self.pool, after_id, limit).await?)
+    }
+
+    async fn get_website_by_url(&self, website: &str) -> Result<Option<WebsitesHtml>, Error> {
+        Ok(WebsitesHtml::get_website_by_url(&self.pool, website).await?)
+    }
+
+    async fn create_website_page(&self, page: &WebsitePages) -> Result<(), Error> {
+        Ok(WebsitePages::create_record(&self.pool, page).await?)
+    }
+
+    async fn get_website_pages(&self, websites_html_id: i32) -> Result<Vec<WebsitePages>, Error> {
+        Ok(WebsitePages::get_pages_by_websites_html_id(&self.pool, websites_html_id).await?)
+    }
+
+    async fn get_website_page_by_url(&self, url: &str) -> Result<Option<WebsitePages>, Error> {
+        Ok(WebsitePages::get_record_by_url(&self.pool, url).await?)
+    }
+}
+
+#[async_trait]
+impl RecordEmailsRepository for MySqlStorage {
+    async fn create_record_email(&self, record: &RecordEmails) -> Result<i32, Error> {
+        Ok(RecordEmails::create_record(&self.pool, record).await?)
+    }
+
+    async fn record_email_exists(&self, record_id: i32, email: &str) -> Result<bool, Error> {
+        Ok(RecordEmails::record_exists(&self.pool, record_id, email).await?)
+    }
+
+    async fn get_record_emails(&self, record_id: i32) -> Result<Vec<RecordEmails>, Error> {
+        Ok(RecordEmails::get_records_by_record_id(&self.pool, record_id).await?)
+    }
+
+    async fn refresh_email_view(&self) -> Result<(), Error> {
+        Ok(RecordEmails::create_compat_view(&self.pool).await?)
+    }
+}
+
+#[async_trait]
+impl ExtractionLogRepository for MySqlStorage {
+    async fn create_field_provenance(&self, record: &FieldProvenance) -> Result<(), Error> {
+        Ok(FieldProvenance::create_record(&self.pool, record).await?)
+    }
+
+    async fn create_fill_rate(&self, record: &ExtractionFillRates) -> Result<(), Error> {
+        Ok(ExtractionFillRates::create_record(&self.pool, record).await?)
+    }
+
+    async fn get_fill_rate_history(&self, stage: &str, field: &str, limit: i64) -> Result<Vec<f32>, Error> {
+        Ok(ExtractionFillRates::get_history(&self.pool, stage, field, limit).await?)
+    }
+}
@@ -0,0 +1,126 @@
+use regex::Regex;
+use scraper::Html;
+use unicode_normalization::UnicodeNormalization;
+
+// Every extracted field goes through here before it is stored, so
+// "Smith &amp; Sons", "<span>Smith</span> &amp; Sons" and "Smith  &  Sons" are one value.
+pub fn normalize_text(raw: &str) -> String {
+    let text = strip_tags(raw);
+    let text = decode_html_entities(&text);
+    let text = collapse_whitespace(&text);
+    text.nfc().collect()
+}
+
+// Text content of an HTML fragment. Tags are replaced by a space so "<br>" still separates words.
+pub fn strip_tags(raw: &str) -> String {
+    if !raw.contains('<') {
+        return raw.to_string();
+    }
+
+    let fragment = Html::parse_fragment(raw);
+    let mut text = String::new();
+
+    for node in fragment.tree.nodes() {
+        if let Some(node_text) = node.value().as_text() {
+            text.push_str(node_text);
+        } else if node.value().is_element() {
+            text.push(' ');
+        }
+    }
+
+    // The parser already decoded entities; encode the ampersand again so decoding stays idempotent.
+    text.replace('&', "&amp;")
+}
+
+pub fn collapse_whitespace(raw: &str) -> String {
+    raw.split(|c: char| c.is_whitespace() || c == '\u{a0}' || c == '\u{200b}')
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+pub fn decode_html_entities(input: &str) -> String {
+    let entity_regex = Regex::new(r"&(#[0-9]+|#[xX][0-9a-fA-F]+|[a-zA-Z]+);").unwrap();
+
+    entity_regex
+        .replace_all(input, |captures: &regex::Captures| {
+            let entity = &captures[1];
+            let decoded = if let Some(hex) = entity.strip_prefix("#x").or_else(|| entity.strip_prefix("#X")) {
+                u32::from_str_radix(hex, 16).ok().and_then(char::from_u32)
+            } else if let Some(decimal) = entity.strip_prefix('#') {
+                decimal.parse::<u32>().ok().and_then(char::from_u32)
+            } else {
+                match entity {
+                    "amp" => Some('&'),
+                    "lt" => Some('<'),
+                    "gt" => Some('>'),
+                    "quot" => Some('"'),
+                    "apos" => Some('\''),
+                    "nbsp" => Some(' '),
+                    "commat" => Some('@'),
+                    "period" => Some('.'),
+                    "ndash" => Some('–'),
+                    "mdash" => Some('—'),
+                    "lsquo" => Some('‘'),
+                    "rsquo" => Some('’'),
+                    "ldquo" => Some('“'),
+                    "rdquo" => Some('”'),
+                    "hellip" => Some('…'),
+                    "copy" => Some('©'),
+                    "reg" => Some('®'),
+                    "trade" => Some('™'),
+                    "eacute" => Some('é'),
+                    "egrave" => Some('è'),
+                    "agrave" => Some('à'),
+                    "ccedil" => Some('ç'),
+                    _ => None,
+                }
+            };
+
+            match decoded {
+                Some(c) => c.to_string(),
+                None => captures[0].to_string(),
+            }
+        })
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data;
+    use crate::extractor::Extractor;
+
+    #[test]
+    fn should_decode_entities_and_strip_tags() {
+        assert_eq!(normalize_text("Smith &amp; Sons"), "Smith & Sons");
+        assert_eq!(normalize_text("<span>Smith</span> &amp; <b>Sons</b>"), "Smith & Sons");
+        assert_eq!(normalize_text("<span>150 Ram Forest Road</span><br><span>Gormley</span>"), "150 Ram Forest Road Gormley");
+        assert_eq!(normalize_text("Caf&eacute; &#8211; Bar"), "Café – Bar");
+        assert_eq!(normalize_text("Tom &amp;amp; Jerry"), "Tom &amp; Jerry");
+    }
+
+    #[test]
+    fn should_collapse_whitespace() {
+        assert_eq!(normalize_text("\n    Figure 4\u{a0}Landscapes  \t"), "Figure 4 Landscapes");
+        assert_eq!(normalize_text("   "), "");
+    }
+
+    #[test]
+    fn should_normalize_to_nfc() {
+        let decomposed = "Re\u{301}novations Gagnon";
+        assert_eq!(normalize_text(decomposed), "R\u{e9}novations Gagnon");
+        assert_eq!(normalize_text(decomposed), normalize_text("Rénovations Gagnon"));
+    }
+
+    #[test]
+    fn should_be_idempotent_on_fixture_fields() {
+        let extractor = Extractor::new(data::test_generate_houzz_html());
+
+        for company_info in extractor.get_company_info_houzz() {
+            assert_eq!(normalize_text(&company_info.company), company_info.company);
+            assert!(!company_info.company.contains('<'));
+            assert!(!company_info.company.contains("&amp;"));
+        }
+    }
+}
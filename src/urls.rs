@@ -0,0 +1,241 @@
+use scraper::{Html, Selector};
+use url::Url;
+
+// Query parameters added by ad networks and newsletters; they never change the page.
+const TRACKING_PARAMS: [&str; 12] = [
+    "gclid", "fbclid", "msclkid", "dclid", "yclid", "igshid", "mc_cid", "mc_eid", "_ga", "_gl", "ref_src", "hsctatracking",
+];
+
+// Social networks, directories and site builders that host many businesses, with
+// how many path segments name one of them: facebook.com/<page>, houzz.com/pro/<pro>.
+const SHARED_HOSTS: [(&str, usize); 22] = [
+    ("facebook.com", 1),
+    ("instagram.com", 1),
+    ("twitter.com", 1),
+    ("x.com", 1),
+    ("tiktok.com", 1),
+    ("pinterest.com", 1),
+    ("linktr.ee", 1),
+    ("g.page", 1),
+    ("linkedin.com", 2),
+    ("youtube.com", 2),
+    ("houzz.com", 2),
+    ("houzz.ca", 2),
+    ("houzz.co.uk", 2),
+    ("yelp.com", 2),
+    ("yelp.ca", 2),
+    ("homestars.com", 2),
+    ("sites.google.com", 2),
+    ("wixsite.com", 1),
+    ("square.site", 1),
+    ("godaddysites.com", 1),
+    ("weebly.com", 1),
+    ("wordpress.com", 1),
+];
+
+// Matches the canonical_domain columns.
+const MAX_CANONICAL_DOMAIN_LEN: usize = 255;
+
+// Resolves hrefs found on a page against the URL it was actually served from,
+// or against its <base href> when the page declares one.
+pub struct UrlResolver {
+    base: Url,
+}
+
+impl UrlResolver {
+    pub fn new(page_url: &str, html: &str) -> Option<Self> {
+        let page_url = Url::parse(page_url).ok()?;
+
+        let base = match find_base_href(html) {
+            Some(base_href) => page_url.join(&base_href).unwrap_or(page_url),
+            None => page_url,
+        };
+
+        Some(Self { base })
+    }
+
+    // Absolute, canonical http(s) URL for an href, or None for mailto:, tel:, javascript: and the like.
+    pub fn resolve(&self, href: &str) -> Option<String> {
+        let url = self.base.join(href.trim()).ok()?;
+
+        if url.scheme() != "http" && url.scheme() != "https" {
+            return None;
+        }
+
+        Some(canonicalize(url))
+    }
+}
+
+pub fn find_base_href(html: &str) -> Option<String> {
+    let document = Html::parse_document(html);
+    let base_selector = Selector::parse("base[href]").unwrap();
+
+    document
+        .select(&base_selector)
+        .next()
+        .and_then(|element| element.value().attr("href"))
+        .map(|href| href.trim().to_string())
+        .filter(|href| !href.is_empty())
+}
+
+// Websites are stored without a scheme by some directories, e.g. "www.mcfees.com".
+pub fn parse_website(website: &str) -> Option<Url> {
+    let website = website.trim();
+
+    if website.is_empty() {
+        return None;
+    }
+
+    if website.contains("://") {
+        Url::parse(website).ok()
+    } else {
+        Url::parse(&format!("https://{}", website)).ok()
+    }
+}
+
+pub fn canonicalize_url(url: &str) -> Option<String> {
+    let url = parse_website(url)?;
+
+    if url.scheme() != "http" && url.scheme() != "https" {
+        return None;
+    }
+
+    Some(canonicalize(url))
+}
+
+// One value per business website: www.mcfees.com, https://mcfees.com/ and
+// http://www.mcfees.com/contact all map to "mcfees.com". On a shared host the
+// business is in the path, so facebook.com/mcfees maps to "facebook.com/mcfees".
+pub fn canonical_domain(website: &str) -> Option<String> {
+    let url = parse_website(website)?;
+    let host = url.host_str()?.trim_end_matches('.').to_lowercase();
+    let host = host.strip_prefix("www.").unwrap_or(&host).to_string();
+
+    if host.is_empty() || !host.contains('.') {
+        return None;
+    }
+
+    let segments = match shared_host_segments(&host) {
+        Some(segments) => segments,
+        None => return Some(host),
+    };
+
+    // Without the segments that name the business (profile.php names it in the query)
+    // it could be anyone's page, so it is not deduped.
+    let host = host.strip_prefix("m.").unwrap_or(&host);
+    let path: Vec<String> = url
+        .path_segments()?
+        .filter(|segment| !segment.is_empty())
+        .take(segments)
+        .map(|segment| segment.to_lowercase())
+        .collect();
+
+    if path.len() < segments || path.iter().any(|segment| segment.ends_with(".php")) {
+        return None;
+    }
+
+    Some(format!("{}/{}", host, path.join("/"))).filter(|key| key.len() <= MAX_CANONICAL_DOMAIN_LEN)
+}
+
+fn shared_host_segments(host: &str) -> Option<usize> {
+    SHARED_HOSTS
+        .iter()
+        .find(|(shared_host, _)| host == *shared_host || host.ends_with(&format!(".{}", shared_host)))
+        .map(|(_, segments)| *segments)
+}
+
+// Lowercase host, no fragment, no tracking parameters. The url crate already
+// lowercases the host and drops default ports when parsing.
+fn canonicalize(mut url: Url) -> String {
+    url.set_fragment(None);
+
+    if url.query().is_some() {
+        let kept: Vec<(String, String)> = url
+            .query_pairs()
+            .filter(|(key, _)| !is_tracking_param(key))
+            .map(|(key, value)| (key.into_owned(), value.into_owned()))
+            .collect();
+
+        if kept.is_empty() {
+            url.set_query(None);
+        } else {
+            url.query_pairs_mut().clear().extend_pairs(kept);
+        }
+    }
+
+    url.to_string()
+}
+
+fn is_tracking_param(key: &str) -> bool {
+    let key = key.to_lowercase();
+    key.starts_with("utm_") || TRACKING_PARAMS.contains(&key.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_resolve_relative_links_against_page_url() {
+        let resolver = UrlResolver::new("http://www.smithbuild.co.uk/about/team.html", "").unwrap();
+
+        assert_eq!(resolver.resolve("/contact-us/").unwrap(), "http://www.smithbuild.co.uk/contact-us/");
+        assert_eq!(resolver.resolve("contact/index.html").unwrap(), "http://www.smithbuild.co.uk/about/contact/index.html");
+        assert_eq!(resolver.resolve("../contact").unwrap(), "http://www.smithbuild.co.uk/contact");
+        assert_eq!(resolver.resolve("//cdn.smithbuild.co.uk/x").unwrap(), "http://cdn.smithbuild.co.uk/x");
+        assert_eq!(resolver.resolve("https://LetsLandscape.ca/Contact-Us/").unwrap(), "https://letslandscape.ca/Contact-Us/");
+    }
+
+    #[test]
+    fn should_use_base_href() {
+        let html = r#"<html><head><base href="https://shop.mcfees.com/en/"></head><body><a href="contact">Contact</a></body></html>"#;
+        let resolver = UrlResolver::new("https://mcfees.com/", html).unwrap();
+
+        assert_eq!(resolver.resolve("contact").unwrap(), "https://shop.mcfees.com/en/contact");
+    }
+
+    #[test]
+    fn should_skip_non_http_links() {
+        let resolver = UrlResolver::new("https://mcfees.com/", "").unwrap();
+
+        assert_eq!(resolver.resolve("mailto:info@mcfees.com"), None);
+        assert_eq!(resolver.resolve("tel:+19057131230"), None);
+        assert_eq!(resolver.resolve("javascript:void(0)"), None);
+    }
+
+    #[test]
+    fn should_canonicalize_urls() {
+        assert_eq!(
+            canonicalize_url("https://WWW.McFees.com:443/contact?utm_source=houzz&id=7&fbclid=abc#form").unwrap(),
+            "https://www.mcfees.com/contact?id=7"
+        );
+        assert_eq!(canonicalize_url("www.mcfees.com").unwrap(), "https://www.mcfees.com/");
+        assert_eq!(canonicalize_url("http://mcfees.com/?gclid=1").unwrap(), "http://mcfees.com/");
+        assert_eq!(canonicalize_url(""), None);
+    }
+
+    #[test]
+    fn should_keep_pages_on_a_shared_host_apart() {
+        let mcfees = canonical_domain("https://www.facebook.com/mcfeesconstruction/").unwrap();
+        let smith = canonical_domain("https://m.facebook.com/SmithBuild?ref=page_internal").unwrap();
+
+        assert_eq!(mcfees, "facebook.com/mcfeesconstruction");
+        assert_eq!(smith, "facebook.com/smithbuild");
+        assert_ne!(mcfees, smith);
+        assert_ne!(canonical_domain("houzz.com/pro/mcfees"), canonical_domain("houzz.com/pro/smithbuild"));
+    }
+
+    #[test]
+    fn should_compute_canonical_domain() {
+        assert_eq!(canonical_domain("www.mcfees.com").unwrap(), "mcfees.com");
+        assert_eq!(canonical_domain("https://mcfees.com/").unwrap(), "mcfees.com");
+        assert_eq!(canonical_domain("http://WWW.McFees.com/contact?id=1").unwrap(), "mcfees.com");
+        assert_eq!(canonical_domain("https://shop.smithbuild.co.uk").unwrap(), "shop.smithbuild.co.uk");
+        assert_eq!(canonical_domain("localhost"), None);
+        assert_eq!(canonical_domain("https://mcfees.wixsite.com/site").unwrap(), "mcfees.wixsite.com/site");
+        assert_eq!(canonical_domain("https://sites.google.com/view/McFees/home").unwrap(), "sites.google.com/view/mcfees");
+        assert_eq!(canonical_domain("https://www.houzz.com/pro"), None);
+        assert_eq!(canonical_domain("https://www.facebook.com/profile.php?id=100064"), None);
+        assert_eq!(canonical_domain(""), None);
+    }
+}
@@ -0,0 +1,411 @@
+use anyhow::{anyhow, Error};
+use chrono::{DateTime, SecondsFormat, Utc};
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use uuid::Uuid;
+
+pub const DEFAULT_MAX_FILE_BYTES: u64 = 1024 * 1024 * 1024;
+
+// get_all_pages_houzz keeps only this element of a listing page, so archived
+// listings are recognised by it on import.
+pub const HOUZZ_LISTING_SELECTOR: &str = ".pro-results";
+
+// A record's headers as (lowercased name, value) pairs, in file order.
+type WarcHeaders = Vec<(String, String)>;
+
+#[derive(Clone, Debug)]
+pub struct WarcConfig {
+    pub directory: PathBuf,
+    pub prefix: String,
+    pub max_file_bytes: u64,
+}
+
+impl WarcConfig {
+    // WARC_DIR turns archiving on; WARC_MAX_FILE_MB overrides the rotation size.
+    pub fn from_env() -> Option<WarcConfig> {
+        let directory = env::var("WARC_DIR").ok()?;
+        let max_file_bytes = env::var("WARC_MAX_FILE_MB")
+            .ok()
+            .and_then(|megabytes| megabytes.parse::<u64>().ok())
+            .map(|megabytes| megabytes * 1024 * 1024)
+            .unwrap_or(DEFAULT_MAX_FILE_BYTES);
+
+        Some(WarcConfig {
+            directory: PathBuf::from(directory),
+            prefix: "general-scrapper".to_string(),
+            max_file_bytes,
+        })
+    }
+}
+
+// One fetch: the URL asked for, where the browser ended up, and the HTML the
+// stage kept. `selector` is set when only that element was captured.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WarcRecord {
+    pub target_uri: String,
+    pub final_uri: String,
+    pub date: DateTime<Utc>,
+    pub selector: Option<String>,
+    pub html: String,
+}
+
+struct WarcFile {
+    file: File,
+    bytes: u64,
+}
+
+struct WriterState {
+    current: Option<WarcFile>,
+    serial: u32,
+}
+
+// Appends fetched pages as WARC 1.1 resource records, starting a new file once
+// the current one passes max_file_bytes. Shared by every client of a stage.
+pub struct WarcWriter {
+    config: WarcConfig,
+    state: Mutex<WriterState>,
+}
+
+impl WarcWriter {
+    pub fn new(config: WarcConfig) -> Result<WarcWriter, Error> {
+        fs::create_dir_all(&config.directory)?;
+
+        Ok(WarcWriter {
+            config,
+            state: Mutex::new(WriterState { current: None, serial: 0 }),
+        })
+    }
+
+    pub fn from_env() -> Result<Option<Arc<WarcWriter>>, Error> {
+        match WarcConfig::from_env() {
+            Some(config) => Ok(Some(Arc::new(WarcWriter::new(config)?))),
+            None => Ok(None),
+        }
+    }
+
+    pub fn write(&self, record: &WarcRecord) -> Result<(), Error> {
+        let bytes = resource_record(record);
+        let mut state = self.state.lock().map_err(|_| anyhow!("WARC writer lock poisoned"))?;
+
+        let rotate = match &state.current {
+            Some(current) => current.bytes >= self.config.max_file_bytes,
+            None => true,
+        };
+        if rotate {
+            state.serial += 1;
+            state.current = Some(self.open_file(state.serial)?);
+        }
+
+        let current = state.current.as_mut().ok_or_else(|| anyhow!("No open WARC file"))?;
+        current.file.write_all(&bytes)?;
+        current.file.flush()?;
+        current.bytes += bytes.len() as u64;
+
+        Ok(())
+    }
+
+    fn open_file(&self, serial: u32) -> Result<WarcFile, Error> {
+        let file_name = format!("{}-{}-{:05}.warc", self.config.prefix, Utc::now().format("%Y%m%d%H%M%S"), serial);
+        let path = self.config.directory.join(&file_name);
+        println!("Writing WARC file {}", path.display());
+
+        let mut file = File::create(&path)?;
+        let info = warcinfo_record(&file_name);
+        file.write_all(&info)?;
+
+        Ok(WarcFile { file, bytes: info.len() as u64 })
+    }
+}
+
+fn record_bytes(headers: &[(&str, String)], block: &[u8]) -> Vec<u8> {
+    let mut bytes = b"WARC/1.1\r\n".to_vec();
+
+    for (name, value) in headers {
+        bytes.extend_from_slice(format!("{}: {}\r\n", name, value).as_bytes());
+    }
+    bytes.extend_from_slice(format!("Content-Length: {}\r\n\r\n", block.len()).as_bytes());
+    bytes.extend_from_slice(block);
+    bytes.extend_from_slice(b"\r\n\r\n");
+
+    bytes
+}
+
+fn record_id() -> String {
+    format!("<urn:uuid:{}>", Uuid::new_v4())
+}
+
+fn warcinfo_record(file_name: &str) -> Vec<u8> {
+    let block = format!("software: general-scrapper/{}\r\nformat: WARC File Format 1.1\r\n", env!("CARGO_PKG_VERSION"));
+
+    record_bytes(&[
+        ("WARC-Type", "warcinfo".to_string()),
+        ("WARC-Record-ID", record_id()),
+        ("WARC-Date", Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true)),
+        ("WARC-Filename", file_name.to_string()),
+        ("Content-Type", "application/warc-fields".to_string()),
+    ], block.as_bytes())
+}
+
+// The HTML is the DOM the browser rendered, not the HTTP response, so it goes
+// in a resource record. X-Final-URI and X-Selector are our own fields.
+fn resource_record(record: &WarcRecord) -> Vec<u8> {
+    let mut headers = vec![
+        ("WARC-Type", "resource".to_string()),
+        ("WARC-Record-ID", record_id()),
+        ("WARC-Date", record.date.to_rfc3339_opts(SecondsFormat::Secs, true)),
+        ("WARC-Target-URI", record.target_uri.clone()),
+        ("X-Final-URI", record.final_uri.clone()),
+        ("Content-Type", "text/html; charset=utf-8".to_string()),
+    ];
+    if let Some(selector) = &record.selector {
+        headers.push(("X-Selector", selector.clone()));
+    }
+
+    record_bytes(&headers, record.html.as_bytes())
+}
+
+// Where a resource record starts in its file, and everything about it but the HTML.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WarcIndexEntry {
+    pub offset: u64,
+    pub target_uri: String,
+    pub date: DateTime<Utc>,
+}
+
+// Reads the resource records back, skipping warcinfo and any other record types.
+pub struct WarcReader<R: BufRead> {
+    reader: R,
+    position: u64,
+}
+
+pub fn read_file(path: &Path) -> Result<WarcReader<BufReader<File>>, Error> {
+    Ok(WarcReader::new(BufReader::new(File::open(path)?)))
+}
+
+// The record starting at `offset`, as found by WarcReader::next_index_entry.
+pub fn read_record_at(path: &Path, offset: u64) -> Result<WarcRecord, Error> {
+    let mut file = File::open(path)?;
+    file.seek(SeekFrom::Start(offset))?;
+
+    WarcReader::new(BufReader::new(file))
+        .next()
+        .ok_or_else(|| anyhow!("No WARC record at {} in {}", offset, path.display()))?
+}
+
+impl<R: BufRead> WarcReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self { reader, position: 0 }
+    }
+
+    // The next resource record's offset, URI and date. Blocks are skipped, not
+    // read, so indexing a large archive keeps one line in memory at a time.
+    pub fn next_index_entry(&mut self) -> Result<Option<WarcIndexEntry>, Error> {
+        while let Some((offset, headers)) = self.read_headers()? {
+            self.skip_block(&headers)?;
+
+            if header(&headers, "warc-type").as_deref() != Some("resource") {
+                continue;
+            }
+
+            let target_uri = header(&headers, "warc-target-uri").unwrap_or_default();
+            return Ok(Some(WarcIndexEntry {
+                offset,
+                date: record_date(&headers, &target_uri)?,
+                target_uri,
+            }));
+        }
+
+        Ok(None)
+    }
+
+    fn read_record(&mut self) -> Result<Option<WarcRecord>, Error> {
+        while let Some((_, headers)) = self.read_headers()? {
+            if header(&headers, "warc-type").as_deref() != Some("resource") {
+                self.skip_block(&headers)?;
+                continue;
+            }
+
+            let mut block = vec![0; content_length(&headers)?];
+            self.reader.read_exact(&mut block)?;
+            self.position += block.len() as u64;
+
+            let target_uri = header(&headers, "warc-target-uri").unwrap_or_default();
+            return Ok(Some(WarcRecord {
+                final_uri: header(&headers, "x-final-uri").unwrap_or_else(|| target_uri.clone()),
+                date: record_date(&headers, &target_uri)?,
+                target_uri,
+                selector: header(&headers, "x-selector"),
+                html: String::from_utf8(block)?,
+            }));
+        }
+
+        Ok(None)
+    }
+
+    // The offset of the next record's version line and its headers, lowercased.
+    fn read_headers(&mut self) -> Result<Option<(u64, WarcHeaders)>, Error> {
+        let mut line = String::new();
+        let mut offset;
+
+        // Blank lines separate records.
+        loop {
+            line.clear();
+            offset = self.position;
+            if self.read_line(&mut line)? == 0 {
+                return Ok(None);
+            }
+            if !line.trim().is_empty() {
+                break;
+            }
+        }
+
+        if !line.starts_with("WARC/") {
+            return Err(anyhow!("Expected a WARC version line, got {:?}", line.trim()));
+        }
+
+        let mut headers = Vec::new();
+        loop {
+            line.clear();
+            if self.read_line(&mut line)? == 0 {
+                return Err(anyhow!("WARC file ends inside record headers"));
+            }
+            let header = line.trim_end();
+            if header.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = header.split_once(':') {
+                headers.push((name.trim().to_lowercase(), value.trim().to_string()));
+            }
+        }
+
+        Ok(Some((offset, headers)))
+    }
+
+    fn read_line(&mut self, line: &mut String) -> Result<usize, Error> {
+        let read = self.reader.read_line(line)?;
+        self.position += read as u64;
+
+        Ok(read)
+    }
+
+    fn skip_block(&mut self, headers: &[(String, String)]) -> Result<(), Error> {
+        let length = content_length(headers)? as u64;
+        let skipped = io::copy(&mut (&mut self.reader).take(length), &mut io::sink())?;
+        if skipped < length {
+            return Err(anyhow!("WARC file ends inside a record block"));
+        }
+        self.position += skipped;
+
+        Ok(())
+    }
+}
+
+fn header(headers: &[(String, String)], name: &str) -> Option<String> {
+    headers.iter().find(|(header, _)| header == name).map(|(_, value)| value.clone())
+}
+
+fn content_length(headers: &[(String, String)]) -> Result<usize, Error> {
+    header(headers, "content-length")
+        .and_then(|length| length.parse().ok())
+        .ok_or_else(|| anyhow!("WARC record without a Content-Length"))
+}
+
+fn record_date(headers: &[(String, String)], target_uri: &str) -> Result<DateTime<Utc>, Error> {
+    header(headers, "warc-date")
+        .and_then(|date| DateTime::parse_from_rfc3339(&date).ok())
+        .map(|date| date.with_timezone(&Utc))
+        .ok_or_else(|| anyhow!("WARC record for {} without a valid WARC-Date", target_uri))
+}
+
+impl<R: BufRead> Iterator for WarcReader<R> {
+    type Item = Result<WarcRecord, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_record().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn record(url: &str, selector: Option<&str>, html: &str) -> WarcRecord {
+        WarcRecord {
+            target_uri: url.to_string(),
+            final_uri: url.replace("http://", "https://"),
+            date: Utc.with_ymd_and_hms(2024, 3, 1, 12, 30, 0).unwrap(),
+            selector: selector.map(|selector| selector.to_string()),
+            html: html.to_string(),
+        }
+    }
+
+    #[test]
+    fn should_read_back_written_records() {
+        let listing = record("http://www.houzz.com/professionals/general-contractor?fi=15", Some(HOUZZ_LISTING_SELECTOR), "<div class=\"pro-results\">é</div>");
+        let details = record("http://www.houzz.com/pro/acme", None, "<body>\r\n\r\nWARC/1.1\r\n</body>");
+
+        let mut bytes = warcinfo_record("test.warc");
+        bytes.extend(resource_record(&listing));
+        bytes.extend(resource_record(&details));
+
+        let records: Vec<WarcRecord> = WarcReader::new(&bytes[..]).collect::<Result<_, _>>().unwrap();
+
+        assert_eq!(records, vec![listing, details]);
+    }
+
+    #[test]
+    fn should_index_records_and_read_them_at_their_offset() {
+        let directory = env::temp_dir().join(format!("warc-test-{}", Uuid::new_v4()));
+        let writer = WarcWriter::new(WarcConfig { directory: directory.clone(), prefix: "test".to_string(), max_file_bytes: DEFAULT_MAX_FILE_BYTES }).unwrap();
+        let listing = record("https://example.com/a", Some(HOUZZ_LISTING_SELECTOR), "<div class=\"pro-results\">a</div>");
+        let details = record("https://example.com/b", None, "<p>b</p>");
+        writer.write(&listing).unwrap();
+        writer.write(&details).unwrap();
+
+        let path = fs::read_dir(&directory).unwrap().next().unwrap().unwrap().path();
+        let mut reader = read_file(&path).unwrap();
+        let mut entries = Vec::new();
+        while let Some(entry) = reader.next_index_entry().unwrap() {
+            entries.push(entry);
+        }
+
+        assert_eq!(entries.iter().map(|entry| entry.target_uri.as_str()).collect::<Vec<_>>(), vec!["https://example.com/a", "https://example.com/b"]);
+        assert_eq!(read_record_at(&path, entries[1].offset).unwrap(), details);
+        assert_eq!(read_record_at(&path, entries[0].offset).unwrap(), listing);
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn should_rotate_files_past_max_size() {
+        let directory = env::temp_dir().join(format!("warc-test-{}", Uuid::new_v4()));
+        let writer = WarcWriter::new(WarcConfig { directory: directory.clone(), prefix: "test".to_string(), max_file_bytes: 1 }).unwrap();
+
+        writer.write(&record("https://example.com/a", None, "<p>a</p>")).unwrap();
+        writer.write(&record("https://example.com/b", None, "<p>b</p>")).unwrap();
+
+        let mut paths: Vec<PathBuf> = fs::read_dir(&directory).unwrap().map(|entry| entry.unwrap().path()).collect();
+        paths.sort();
+        assert_eq!(paths.len(), 2);
+
+        let urls: Vec<String> = paths
+            .iter()
+            .flat_map(|path| read_file(path).unwrap())
+            .map(|record| record.unwrap().target_uri)
+            .collect();
+        assert_eq!(urls, vec!["https://example.com/a", "https://example.com/b"]);
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn should_reject_files_that_are_not_warc() {
+        let mut reader = WarcReader::new(&b"<html></html>"[..]);
+
+        assert!(reader.next().unwrap().is_err());
+    }
+}
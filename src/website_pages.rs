@@ -0,0 +1,78 @@
+use sqlx::{FromRow, Error, Executor, MySql, query, query_as};
+use sqlx::mysql::MySqlPool;
+use crate::records_data::RecordsData;
+use crate::websites_html::WebsitesHtml;
+
+#[derive(Clone, Debug, FromRow)]
+pub struct WebsitePages {
+    pub id: i32,
+    pub websites_html_id: i32,
+    pub url: String,
+    pub depth: i32,
+    pub score: i32,
+    pub html: String,
+}
+
+impl WebsitePages {
+    pub async fn create_record<'e, E: Executor<'e, Database = MySql>>(executor: E, page: &WebsitePages) -> Result<(), Error> {
+        println!("Creating website page: {}", page.url);
+        query("INSERT INTO website_pages (websites_html_id, url, depth, score, html) VALUES (?, ?, ?, ?, ?)")
+            .bind(&page.websites_html_id)
+            .bind(&page.url)
+            .bind(&page.depth)
+            .bind(&page.score)
+            .bind(&page.html)
+            .execute(executor)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_pages_by_websites_html_id(pool: &MySqlPool, websites_html_id: i32) -> Result<Vec<WebsitePages>, Error> {
+        let website_pages: Vec<WebsitePages> = query_as("SELECT * FROM website_pages WHERE websites_html_id = ? ORDER BY depth, score DESC")
+            .bind(websites_html_id)
+            .fetch_all(pool)
+            .await?;
+
+        Ok(website_pages)
+    }
+
+    pub async fn get_record_by_url(pool: &MySqlPool, url: &str) -> Result<Option<WebsitePages>, Error> {
+        let website_page: Option<WebsitePages> = query_as("SELECT * FROM website_pages WHERE url = ? ORDER BY id DESC LIMIT 1")
+            .bind(url)
+            .fetch_optional(pool)
+            .await?;
+
+        Ok(website_page)
+    }
+
+    pub async fn delete_pages_by_websites_html_id(pool: &MySqlPool, websites_html_id: i32) -> Result<(), Error> {
+        query("DELETE FROM website_pages WHERE websites_html_id = ?")
+            .bind(websites_html_id)
+            .execute(pool)
+            .await?;
+
+        Ok(())
+    }
+
+    // (url, html) of every stored page of a website; sites fetched before the
+    // crawler only have the homepage and contact page on websites_html.
+    pub async fn get_pages_for_website(pool: &MySqlPool, website_html: &WebsitesHtml) -> Result<Vec<(String, String)>, Error> {
+        let website_pages = WebsitePages::get_pages_by_websites_html_id(pool, website_html.id).await?;
+
+        if !website_pages.is_empty() {
+            return Ok(website_pages.into_iter().map(|page| (page.url, page.html)).collect());
+        }
+
+        let record_data = RecordsData::get_record_data_by_records_data_id(pool, website_html.records_data_id).await?;
+
+        Ok(WebsitePages::fallback_pages(website_html, record_data.contact_us_link))
+    }
+
+    pub fn fallback_pages(website_html: &WebsitesHtml, contact_us_link: Option<String>) -> Vec<(String, String)> {
+        vec![
+            (website_html.website.clone(), website_html.main_page_html.clone()),
+            (contact_us_link.unwrap_or_default(), website_html.contact_page_html.clone()),
+        ]
+    }
+}
@@ -1,6 +1,9 @@
 use sqlx::{Row, FromRow, Error, MySql, query, query_as};
-use sqlx::mysql::MySqlPool;
+use sqlx::mysql::{MySqlConnection, MySqlPool};
 use anyhow::Result;
+use crate::page_blobs::PageBlobs;
+use crate::website_pages::WebsitePages;
+use crate::urls;
 
 #[derive(Clone, Debug, FromRow)]
 pub struct WebsitesHtml {
@@ -9,6 +12,7 @@ pub struct WebsitesHtml {
     pub website: String,
     pub main_page_html: String,
     pub contact_page_html: String,
+    pub final_url: Option<String>,
 }
 #[derive(Clone, Debug, FromRow)]
 pub struct PartialWebsitesHtml {
@@ -19,17 +23,53 @@ pub struct PartialWebsitesHtml {
 }
 
 impl WebsitesHtml {
-    pub async fn create_record(pool: &MySqlPool, website: &WebsitesHtml) -> Result<(), Error> {
-        println!("Creating website: {:?}", website);
-        query("INSERT INTO websites_html (records_data_id, website, main_page_html, contact_page_html) VALUES (?, ?, ?, ?)")
+    // Where the site actually lives: the final URL after redirects when we have it.
+    pub fn canonical_domain(&self) -> Option<String> {
+        self.final_url
+            .as_deref()
+            .and_then(urls::canonical_domain)
+            .or_else(|| urls::canonical_domain(&self.website))
+    }
+
+    pub async fn create_record(pool: &MySqlPool, website: &WebsitesHtml) -> Result<i32, Error> {
+        println!("Creating website: {:?}", website.website);
+        let mut transaction = pool.begin().await?;
+        let websites_html_id = WebsitesHtml::insert_record(&mut transaction, website).await?;
+        transaction.commit().await?;
+
+        Ok(websites_html_id)
+    }
+
+    // A website crawled for the first time: it and the pages found are stored in one
+    // transaction, so a failed page never leaves a website that looks crawled.
+    pub async fn create_crawled_website(pool: &MySqlPool, website: &WebsitesHtml, pages: &[WebsitePages]) -> Result<i32, Error> {
+        println!("Creating website {} with {} crawled pages", website.website, pages.len());
+        let mut transaction = pool.begin().await?;
+        let websites_html_id = WebsitesHtml::insert_record(&mut transaction, website).await?;
+
+        for page in pages {
+            WebsitePages::create_record(&mut transaction, &WebsitePages { websites_html_id, ..page.clone() }).await?;
+        }
+        transaction.commit().await?;
+
+        Ok(websites_html_id)
+    }
+
+    async fn insert_record(connection: &mut MySqlConnection, website: &WebsitesHtml) -> Result<i32, Error> {
+        let main_page_html_hash = PageBlobs::store_html(&mut *connection, &website.main_page_html).await?;
+        let contact_page_html_hash = PageBlobs::store_html(&mut *connection, &website.contact_page_html).await?;
+
+        let result = query("INSERT INTO websites_html (records_data_id, website, main_page_html, contact_page_html, main_page_html_hash, contact_page_html_hash, final_url, canonical_domain) VALUES (?, ?, '', '', ?, ?, ?, ?)")
             .bind(&website.records_data_id)
             .bind(&website.website)
-            .bind(&website.main_page_html)
-            .bind(&website.contact_page_html)
-            .execute(pool)
+            .bind(main_page_html_hash)
+            .bind(contact_page_html_hash)
+            .bind(&website.final_url)
+            .bind(website.canonical_domain())
+            .execute(connection)
             .await?;
 
-        Ok(())
+        Ok(result.last_insert_id() as i32)
     }
 
     pub async fn record_exists(pool: &MySqlPool, records_data_id: i32) -> Result<bool, Error> {
@@ -42,24 +82,28 @@ impl WebsitesHtml {
     }
 
     pub async fn website_exists(pool: &MySqlPool, website: &str) -> Result<bool, Error> {
-        let exists: (i32,) = query_as("SELECT EXISTS( SELECT 1 FROM websites_html WHERE website = ? )")
+        let exists: (i32,) = query_as("SELECT EXISTS( SELECT 1 FROM websites_html WHERE website = ? OR canonical_domain = ? )")
             .bind(website)
+            .bind(urls::canonical_domain(website))
             .fetch_one(pool)
             .await?;
 
         Ok(exists.0 == 1)
     }
 
-    pub async fn get_all_websites(pool: &MySqlPool) -> Result<Vec<WebsitesHtml>, Error> {
-        let websites_html: Vec<WebsitesHtml> = query_as("SELECT * FROM websites_html")
+    // One keyset page: ids after `after_id`, at most `limit` rows, each with its HTML.
+    pub async fn get_websites_after(pool: &MySqlPool, after_id: i32, limit: i64) -> Result<Vec<WebsitesHtml>, Error> {
+        let websites_html: Vec<WebsitesHtml> = query_as("SELECT * FROM websites_html WHERE id > ? ORDER BY id LIMIT ?")
+            .bind(after_id)
+            .bind(limit)
             .fetch_all(pool)
             .await?;
 
-        Ok(websites_html)
+        WebsitesHtml::with_blob_html(pool, websites_html).await
     }
 
     pub async fn get_all_websites_with_no_contact_page_html(pool: &MySqlPool) -> Result<Vec<PartialWebsitesHtml>, Error> {
-        let websites_html: Vec<PartialWebsitesHtml> = query_as("SELECT id, records_data_id, website, contact_page_html FROM websites_html WHERE contact_page_html = '' OR contact_page_html IS NULL")
+        let websites_html: Vec<PartialWebsitesHtml> = query_as("SELECT id, records_data_id, website, contact_page_html FROM websites_html WHERE contact_page_html_hash IS NULL AND (contact_page_html = '' OR contact_page_html IS NULL)")
             .fetch_all(pool)
             .await?;
 
@@ -78,31 +122,116 @@ impl WebsitesHtml {
 
     pub async fn update_main_page_html(pool: &MySqlPool, website: &WebsitesHtml) -> Result<(), Error> {
         println!("Updating main page html: {:?}", website);
-        query("UPDATE websites_html SET main_page_html = ? WHERE id = ?")
-            .bind(&website.main_page_html)
+        let mut transaction = pool.begin().await?;
+        let main_page_html_hash = PageBlobs::store_html(&mut transaction, &website.main_page_html).await?;
+
+        query("UPDATE websites_html SET main_page_html = '', main_page_html_hash = ?, fetched_at = NOW() WHERE id = ?")
+            .bind(main_page_html_hash)
             .bind(&website.id)
-            .execute(pool)
+            .execute(&mut transaction)
             .await?;
+        transaction.commit().await?;
+
+        Ok(())
+    }
+
+    // A website crawled again: its start page, where it landed and the pages found
+    // replace the previous crawl in one transaction.
+    pub async fn replace_crawled_pages(pool: &MySqlPool, website: &WebsitesHtml, pages: &[WebsitePages]) -> Result<(), Error> {
+        println!("Replacing {} crawled pages of {}", pages.len(), website.website);
+        let mut transaction = pool.begin().await?;
+        let main_page_html_hash = PageBlobs::store_html(&mut transaction, &website.main_page_html).await?;
+
+        query("UPDATE websites_html SET main_page_html = '', main_page_html_hash = ?, final_url = ?, fetched_at = NOW() WHERE id = ?")
+            .bind(main_page_html_hash)
+            .bind(&website.final_url)
+            .bind(&website.id)
+            .execute(&mut transaction)
+            .await?;
+
+        query("DELETE FROM website_pages WHERE websites_html_id = ?")
+            .bind(&website.id)
+            .execute(&mut transaction)
+            .await?;
+
+        for page in pages {
+            WebsitePages::create_record(&mut transaction, &WebsitePages { websites_html_id: website.id, ..page.clone() }).await?;
+        }
+        transaction.commit().await?;
 
         Ok(())
     }
 
     pub async fn update_contact_page_html(pool: &MySqlPool, website: &WebsitesHtml) -> Result<(), Error> {
-        query("UPDATE websites_html SET contact_page_html = ? WHERE website = ?")
-            .bind(&website.contact_page_html)
+        let mut transaction = pool.begin().await?;
+        let contact_page_html_hash = PageBlobs::store_html(&mut transaction, &website.contact_page_html).await?;
+
+        query("UPDATE websites_html SET contact_page_html = '', contact_page_html_hash = ? WHERE website = ?")
+            .bind(contact_page_html_hash)
             .bind(&website.website)
+            .execute(&mut transaction)
+            .await?;
+        transaction.commit().await?;
+
+        Ok(())
+    }
+
+    pub async fn update_canonical_domain(pool: &MySqlPool, website: &WebsitesHtml) -> Result<(), Error> {
+        query("UPDATE websites_html SET canonical_domain = ? WHERE id = ?")
+            .bind(website.canonical_domain())
+            .bind(&website.id)
             .execute(pool)
             .await?;
 
         Ok(())
     }
 
+    // Id of the website's row when its main page was fetched more than
+    // `max_age_days` ago, or before fetched_at existed.
+    pub async fn get_stale_website_id(pool: &MySqlPool, website: &str, max_age_days: i32) -> Result<Option<i32>, Error> {
+        let website_id: Option<(i32,)> = query_as("SELECT id FROM websites_html WHERE website = ? AND (fetched_at IS NULL OR fetched_at < NOW() - INTERVAL ? DAY) ORDER BY id DESC LIMIT 1")
+            .bind(website)
+            .bind(max_age_days)
+            .fetch_optional(pool)
+            .await?;
+
+        Ok(website_id.map(|website_id| website_id.0))
+    }
+
+    // Latest row fetched for a website URL.
+    pub async fn get_website_by_url(pool: &MySqlPool, website: &str) -> Result<Option<WebsitesHtml>, Error> {
+        let website: Option<WebsitesHtml> = query_as("SELECT * FROM websites_html WHERE website = ? ORDER BY id DESC LIMIT 1")
+            .bind(website)
+            .fetch_optional(pool)
+            .await?;
+
+        WebsitesHtml::with_blob_html(pool, website.into_iter().collect()).await.map(|mut websites_html| websites_html.pop())
+    }
+
     pub async fn get_website_by_records_data_id(pool: &MySqlPool, records_data_id: i32) -> Result<WebsitesHtml, Error> {
         let website: WebsitesHtml = query_as("SELECT * FROM websites_html WHERE records_data_id = ?")
             .bind(records_data_id)
             .fetch_one(pool)
             .await?;
 
-        Ok(website)
+        Ok(WebsitesHtml::with_blob_html(pool, vec![website]).await?.remove(0))
+    }
+
+    // Rows written since page_blobs keep '' inline; their HTML comes from the blobs.
+    async fn with_blob_html(pool: &MySqlPool, mut websites_html: Vec<WebsitesHtml>) -> Result<Vec<WebsitesHtml>, Error> {
+        let ids: Vec<i32> = websites_html.iter().map(|website_html| website_html.id).collect();
+        let mut main_page_html = PageBlobs::get_html_for_rows(pool, "websites_html", "main_page_html_hash", &ids).await?;
+        let mut contact_page_html = PageBlobs::get_html_for_rows(pool, "websites_html", "contact_page_html_hash", &ids).await?;
+
+        for website_html in websites_html.iter_mut() {
+            if let Some(html) = main_page_html.remove(&website_html.id) {
+                website_html.main_page_html = html;
+            }
+            if let Some(html) = contact_page_html.remove(&website_html.id) {
+                website_html.contact_page_html = html;
+            }
+        }
+
+        Ok(websites_html)
     }
 }
\ No newline at end of file